@@ -1,35 +1,44 @@
 import socket
 
-def send_and_receive(message, host='127.0.0.1', port=7777):
-    sock = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
-    
+def unescape(reply):
+    # Replies are kept on one line, see the README
+    escapes = {'\\': '\\', 'r': '\r', 'n': '\n'}
+    out, chars = [], iter(reply)
+    for c in chars:
+        out.append(escapes.get(next(chars, ''), '') if c == '\\' else c)
+    return ''.join(out)
+
+def send_and_receive(sock, reader, message):
     try:
-        sock.connect((host, port))
-        
-        sock.sendall(message.encode('utf-8'))
-        
-        # Shutdown sending side to signal EOF
-        sock.shutdown(socket.SHUT_WR)
-        
-        # Receive response
-        data = sock.recv(64)
+        # Commands are newline-delimited
+        sock.sendall((message + '\n').encode('utf-8'))
+
+        # Every response ends with a newline
+        data = reader.readline()
         if data:
-            print(f"Received: {data.decode('utf-8')}")
+            response = unescape(data.rstrip('\n'))
+            print(f"Received: {response}")
         else:
             print("No response received.")
-            
+
     except Exception as e:
         print(f"Error: {e}")
+
+def main(host='127.0.0.1', port=7777):
+    sock = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+    sock.connect((host, port))
+    reader = sock.makefile('r', encoding='utf-8', newline='\n')
+
+    try:
+        while True:
+            message = input("Enter message (or 'quit' to exit): ")
+            if message.lower() == 'quit':
+                break
+            if message:
+                send_and_receive(sock, reader, message)
     finally:
+        reader.close()
         sock.close()
 
-def main():
-    while True:
-        message = input("Enter message (or 'quit' to exit): ")
-        if message.lower() == 'quit':
-            break
-        if message:
-            send_and_receive(message)
-
 if __name__ == "__main__":
     main()
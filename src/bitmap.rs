@@ -0,0 +1,381 @@
+//! Bitmap commands
+//! Bitmaps are strings read bit by bit, from the most significant bit of the first byte on.
+//! Bits past the end read as 0 and writing them pads the string with zero bytes.
+
+use crate::{command::{BitFieldOperation, BitFieldOverflow, BitFieldType, BitOperation, BitRange}, dictionary::{range_bounds, Dictionary, Entry, MAX_VALUE_SIZE}, errors::DictionaryError, value::Value};
+
+impl Dictionary {
+    /// Missing keys are created, the expiration is kept
+    /// # Returns
+    /// The previous bit
+    pub fn setbit(&mut self, key: &str, offset: u64, bit: bool) -> Result<bool, DictionaryError> {
+        let len = bytes_for(offset, 1)?;
+        let mut map = self.map.lock().unwrap();
+        let bytes = self.live_entry_or_insert(&mut map, key, || Value::String(Vec::new())).value.as_bytes_mut()?;
+
+        if bytes.len() < len {
+            bytes.resize(len, 0);
+        }
+        let previous = read_bits(bytes, offset, 1) == 1;
+        write_bits(bytes, offset, 1, bit as u64);
+
+        Ok(previous)
+    }
+
+    /// # Returns
+    /// The bit at `offset`, 0 for a missing key
+    pub fn getbit(&self, key: &str, offset: u64) -> Result<bool, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        match self.live_entry(&mut map, key) {
+            Ok(entry) => Ok(read_bits(entry.value.as_bytes()?, offset, 1) == 1),
+            Err(DictionaryError::DoesNotExist) => Ok(false),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// # Returns
+    /// How many bits are set in `range`, or in the whole string
+    pub fn bitcount(&self, key: &str, range: Option<BitRange>) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let bytes = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_bytes()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(0),
+            Err(e) => return Err(e)
+        };
+
+        let Some((start, end)) = bit_bounds(bytes.len(), range) else {
+            return Ok(0);
+        };
+        Ok(range_bytes(bytes, start, end).map(|(_, byte, mask)| (byte & mask).count_ones() as usize).sum())
+    }
+
+    /// The string is considered padded with zero bits when looking for a 0
+    /// without an end to the range, like Redis
+    /// # Returns
+    /// The offset in bits of the first `bit` in `range`, -1 if there is none
+    pub fn bitpos(&self, key: &str, bit: bool, range: Option<BitRange>) -> Result<i64, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let bytes = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_bytes()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(if bit { -1 } else { 0 }),
+            Err(e) => return Err(e)
+        };
+
+        // Nothing to pad either once the range is empty
+        let Some((start, end)) = bit_bounds(bytes.len(), range) else {
+            return Ok(-1);
+        };
+
+        // Whole bytes without the bit are skipped at once
+        let found = range_bytes(bytes, start, end).find_map(|(index, byte, mask)| {
+            let hits = if bit { byte } else { !byte } & mask;
+            (hits != 0).then(|| index as u64 * 8 + hits.leading_zeros() as u64)
+        });
+        let open_ended = range.is_none_or(|range| range.end.is_none());
+        Ok(match found {
+            Some(offset) => offset as i64,
+            None if !bit && open_ended => bytes.len() as i64 * 8,
+            None => -1
+        })
+    }
+
+    /// Stores `operation` of the strings at `keys` in `destination`, missing keys being empty strings.
+    /// `destination` is overwritten whatever it held and deleted if the result is empty.
+    /// # Returns
+    /// The length of the result in bytes
+    pub fn bitop(&mut self, operation: BitOperation, destination: String, keys: &[String]) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let sources = keys.iter()
+            .map(|key| match self.live_entry(&mut map, key) {
+                Ok(entry) => Ok(entry.value.as_bytes()?.clone()),
+                Err(DictionaryError::DoesNotExist) => Ok(Vec::new()),
+                Err(e) => Err(e)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let len = sources.iter().map(Vec::len).max().unwrap_or_default();
+        let byte = |source: &Vec<u8>, index: usize| source.get(index).copied().unwrap_or_default();
+        let result: Vec<u8> = (0..len)
+            .map(|index| {
+                let mut bytes = sources.iter().map(|source| byte(source, index));
+                let first = bytes.next().unwrap_or_default();
+                match operation {
+                    BitOperation::And => bytes.fold(first, |result, byte| result & byte),
+                    BitOperation::Or => bytes.fold(first, |result, byte| result | byte),
+                    BitOperation::Xor => bytes.fold(first, |result, byte| result ^ byte),
+                    BitOperation::Not => !first
+                }
+            })
+            .collect();
+
+        match result.is_empty() {
+            true => {
+                map.remove(&destination);
+            },
+            false => {
+                map.insert(destination, Entry::new(Value::String(result)));
+            }
+        }
+
+        Ok(len)
+    }
+
+    /// Runs the sub-commands in order, a missing key is only created by SET and INCRBY
+    /// # Returns
+    /// The value read by each GET, the previous value for each SET and the new one for each INCRBY
+    pub fn bitfield(&mut self, key: &str, operations: &[BitFieldOperation]) -> Result<Vec<Option<i64>>, DictionaryError> {
+        let mut len = 0;
+        for operation in operations {
+            if let BitFieldOperation::Set(field, offset, _) | BitFieldOperation::IncrBy(field, offset, _) = operation {
+                len = len.max(bytes_for(*offset, field.bits)?);
+            }
+        }
+
+        let mut map = self.map.lock().unwrap();
+        let mut empty = Vec::new();
+        let bytes = match len > 0 {
+            true => self.live_entry_or_insert(&mut map, key, || Value::String(Vec::new())).value.as_bytes_mut()?,
+            false => match self.live_entry(&mut map, key) {
+                Ok(entry) => entry.value.as_bytes_mut()?,
+                Err(DictionaryError::DoesNotExist) => &mut empty,
+                Err(e) => return Err(e)
+            }
+        };
+        if bytes.len() < len {
+            bytes.resize(len, 0);
+        }
+
+        let mut overflow = BitFieldOverflow::default();
+        let mut results = Vec::new();
+        for operation in operations {
+            match *operation {
+                BitFieldOperation::Get(field, offset) => results.push(Some(read_field(bytes, field, offset))),
+                BitFieldOperation::Set(field, offset, value) => {
+                    let previous = read_field(bytes, field, offset);
+                    let written = write_field(bytes, field, offset, value as i128, overflow);
+                    results.push(written.map(|_| previous));
+                },
+                BitFieldOperation::IncrBy(field, offset, by) => {
+                    let current = read_field(bytes, field, offset);
+                    results.push(write_field(bytes, field, offset, current as i128 + by as i128, overflow));
+                },
+                BitFieldOperation::Overflow(mode) => overflow = mode
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+/// # Returns
+/// - The length in bytes a string needs for the `bits` bits from `offset` on
+/// - If that's more than `MAX_VALUE_SIZE`, Err(DictionaryError::ValueTooLarge)
+fn bytes_for(offset: u64, bits: u32) -> Result<usize, DictionaryError> {
+    offset.checked_add(bits as u64)
+        .map(|end| end.div_ceil(8))
+        .filter(|len| *len <= MAX_VALUE_SIZE as u64)
+        .map(|len| len as usize)
+        .ok_or(DictionaryError::ValueTooLarge)
+}
+
+/// `range` as inclusive bit offsets in a string of `len` bytes
+/// # Returns
+/// The first and last offset, None if the range is empty
+fn bit_bounds(len: usize, range: Option<BitRange>) -> Option<(u64, u64)> {
+    let Some(range) = range else {
+        return (len > 0).then(|| (0, len as u64 * 8 - 1));
+    };
+
+    let end = range.end.unwrap_or(-1);
+    match range.bits {
+        true => range_bounds(range.start, end, len * 8).map(|(start, end)| (start as u64, end as u64)),
+        false => range_bounds(range.start, end, len).map(|(start, end)| (start as u64 * 8, end as u64 * 8 + 7))
+    }
+}
+
+/// The bytes holding the bits `start..=end` of `bytes`, which must be in bounds
+/// # Returns
+/// Each byte with its index and the mask of its bits in the range
+fn range_bytes(bytes: &[u8], start: u64, end: u64) -> impl Iterator<Item = (usize, u8, u8)> + '_ {
+    let (first, last) = ((start / 8) as usize, (end / 8) as usize);
+    bytes[first..=last].iter().zip(first..).map(move |(byte, index)| {
+        let mut mask = 0xff;
+        if index == first {
+            mask &= 0xff >> (start % 8);
+        }
+        if index == last {
+            mask &= 0xff << (7 - end % 8);
+        }
+        (index, *byte, mask)
+    })
+}
+
+/// # Returns
+/// The `bits` bits from `offset` on as an unsigned number, at most 64 of them
+fn read_bits(bytes: &[u8], offset: u64, bits: u32) -> u64 {
+    (offset..offset.saturating_add(bits as u64)).fold(0, |value, offset| {
+        let byte = usize::try_from(offset / 8).ok().and_then(|index| bytes.get(index)).copied().unwrap_or_default();
+        value << 1 | (byte >> (7 - offset % 8) & 1) as u64
+    })
+}
+
+/// Writes the low `bits` bits of `value` from `offset` on, `bytes` must be long enough
+fn write_bits(bytes: &mut [u8], offset: u64, bits: u32, value: u64) {
+    for i in 0..bits {
+        let position = offset + i as u64;
+        let mask = 1 << (7 - position % 8);
+        let byte = &mut bytes[(position / 8) as usize];
+        match value >> (bits - 1 - i) & 1 {
+            1 => *byte |= mask,
+            _ => *byte &= !mask
+        }
+    }
+}
+
+/// Smallest and greatest value of a field
+fn field_range(field: BitFieldType) -> (i128, i128) {
+    match field.signed {
+        true => (-(1 << (field.bits - 1)), (1 << (field.bits - 1)) - 1),
+        false => (0, (1 << field.bits) - 1)
+    }
+}
+
+fn read_field(bytes: &[u8], field: BitFieldType, offset: u64) -> i64 {
+    let raw = read_bits(bytes, offset, field.bits);
+    match field.signed {
+        // Sign extended from the top bit of the field
+        true => ((raw << (64 - field.bits)) as i64) >> (64 - field.bits),
+        false => raw as i64
+    }
+}
+
+/// Writes `value` to a field, as `overflow` says if it's out of range
+/// # Returns
+/// The value written, None if OVERFLOW FAIL prevented it
+fn write_field(bytes: &mut [u8], field: BitFieldType, offset: u64, value: i128, overflow: BitFieldOverflow) -> Option<i64> {
+    let (min, max) = field_range(field);
+    let value = match overflow {
+        _ if (min..=max).contains(&value) => value,
+        BitFieldOverflow::Wrap => (value - min).rem_euclid(1 << field.bits) + min,
+        BitFieldOverflow::Sat => value.clamp(min, max),
+        BitFieldOverflow::Fail => return None
+    };
+
+    write_bits(bytes, offset, field.bits, value as u64);
+    Some(value as i64)
+}
+
+#[cfg(test)]
+mod bitmap {
+    use crate::{command::CommandResult, dictionary::testing::run};
+
+    use super::*;
+
+    #[test]
+    fn setbit_getbit() {
+        let mut dict = Dictionary::new();
+
+        assert_eq!(run(&mut dict, "SETBIT active 7 1"), Ok(CommandResult::Bit(false)));
+        assert_eq!(run(&mut dict, "SETBIT active 7 1"), Ok(CommandResult::Bit(true)));
+        assert_eq!(run(&mut dict, "GETBIT active 7"), Ok(CommandResult::Bit(true)));
+        assert_eq!(run(&mut dict, "GETBIT active 6"), Ok(CommandResult::Bit(false)));
+        assert_eq!(run(&mut dict, "GETBIT active 1000"), Ok(CommandResult::Bit(false)));
+        assert_eq!(run(&mut dict, "GETBIT nope 0"), Ok(CommandResult::Bit(false)));
+        assert_eq!(dict.get("active"), Ok(b"\x01".to_vec()));
+
+        // Bits past the end pad with zero bytes
+        run(&mut dict, "SETBIT active 16 1").unwrap();
+        assert_eq!(dict.strlen("active"), Ok(3));
+        // Any bytes, not only UTF-8
+        assert_eq!(dict.get("active"), Ok(vec![0x01, 0x00, 0x80]));
+        assert_eq!(dict.type_name("active"), "string");
+
+        // Bits of text
+        run(&mut dict, "SET text a").unwrap();
+        run(&mut dict, "SETBIT text 6 1").unwrap();
+        assert_eq!(dict.get("text"), Ok(b"c".to_vec()));
+
+        assert_eq!(dict.setbit("huge", MAX_VALUE_SIZE as u64 * 8, true), Err(DictionaryError::ValueTooLarge));
+    }
+
+    #[test]
+    fn bitcount_bitpos() {
+        let mut dict = Dictionary::new();
+        // 0xff 0xf0 0x00
+        dict.set_with("key".to_string(), "\u{7f}".to_string(), Default::default()).unwrap();
+        run(&mut dict, "SETBIT key 0 1").unwrap();
+        for offset in 8..12 {
+            dict.setbit("key", offset, true).unwrap();
+        }
+        dict.setbit("key", 23, false).unwrap();
+
+        assert_eq!(run(&mut dict, "BITCOUNT key"), Ok(CommandResult::BitCount(12)));
+        assert_eq!(run(&mut dict, "BITCOUNT key 1 -1"), Ok(CommandResult::BitCount(4)));
+        assert_eq!(run(&mut dict, "BITCOUNT key 5 30 BIT"), Ok(CommandResult::BitCount(7)));
+        assert_eq!(run(&mut dict, "BITCOUNT key 2 1"), Ok(CommandResult::BitCount(0)));
+        assert_eq!(run(&mut dict, "BITCOUNT nope"), Ok(CommandResult::BitCount(0)));
+
+        assert_eq!(run(&mut dict, "BITPOS key 0"), Ok(CommandResult::BitPos(12)));
+        assert_eq!(run(&mut dict, "BITPOS key 1 1"), Ok(CommandResult::BitPos(8)));
+        assert_eq!(run(&mut dict, "BITPOS key 1 2"), Ok(CommandResult::BitPos(-1)));
+        assert_eq!(run(&mut dict, "BITPOS key 1 3 12 BIT"), Ok(CommandResult::BitPos(3)));
+        assert_eq!(run(&mut dict, "BITPOS key 0 0 0"), Ok(CommandResult::BitPos(-1)));
+        assert_eq!(run(&mut dict, "BITPOS nope 0"), Ok(CommandResult::BitPos(0)));
+        assert_eq!(run(&mut dict, "BITPOS nope 1"), Ok(CommandResult::BitPos(-1)));
+
+        // Without an end, the string is padded with zeros
+        dict.bitfield("ones", &[BitFieldOperation::Set(BitFieldType { signed: false, bits: 8 }, 0, 255)]).unwrap();
+        assert_eq!(run(&mut dict, "BITPOS ones 0"), Ok(CommandResult::BitPos(8)));
+        assert_eq!(run(&mut dict, "BITPOS ones 0 0 -1"), Ok(CommandResult::BitPos(-1)));
+        assert_eq!(run(&mut dict, "BITPOS key 0 100"), Ok(CommandResult::BitPos(-1)));
+        assert_eq!(run(&mut dict, "BITPOS key 0 3"), Ok(CommandResult::BitPos(-1)));
+
+        // Bit ranges starting and ending inside the same byte
+        assert_eq!(run(&mut dict, "BITCOUNT key 9 10 BIT"), Ok(CommandResult::BitCount(2)));
+        assert_eq!(run(&mut dict, "BITPOS key 0 9 13 BIT"), Ok(CommandResult::BitPos(12)));
+        assert_eq!(run(&mut dict, "BITPOS key 0 9 11 BIT"), Ok(CommandResult::BitPos(-1)));
+    }
+
+    #[test]
+    fn bitop() {
+        let mut dict = Dictionary::new();
+        run(&mut dict, "SET a abc").unwrap();
+        run(&mut dict, "SET b a").unwrap();
+
+        assert_eq!(run(&mut dict, "BITOP AND dest a b"), Ok(CommandResult::BitOp(3)));
+        assert_eq!(dict.get("dest"), Ok(b"a\0\0".to_vec()));
+        assert_eq!(run(&mut dict, "BITOP OR dest a b nope"), Ok(CommandResult::BitOp(3)));
+        assert_eq!(dict.get("dest"), Ok(b"abc".to_vec()));
+        assert_eq!(run(&mut dict, "BITOP XOR dest a b"), Ok(CommandResult::BitOp(3)));
+        assert_eq!(dict.get("dest"), Ok(b"\0bc".to_vec()));
+
+        run(&mut dict, "BITOP NOT dest b").unwrap();
+        assert_eq!(dict.getbit("dest", 0), Ok(true));
+        assert_eq!(dict.bitcount("dest", None), Ok(5));
+
+        // Empty results delete the destination, whatever it held
+        run(&mut dict, "LPUSH list a").unwrap();
+        assert_eq!(run(&mut dict, "BITOP OR list nope"), Ok(CommandResult::BitOp(0)));
+        assert!(!dict.exists("list"));
+    }
+
+    #[test]
+    fn bitfield() {
+        let mut dict = Dictionary::new();
+
+        assert_eq!(run(&mut dict, "BITFIELD counters GET u8 0"), Ok(CommandResult::BitField(vec![Some(0)])));
+        assert!(!dict.exists("counters"));
+
+        assert_eq!(run(&mut dict, "BITFIELD counters SET u8 #1 200 GET u8 8 INCRBY u8 #1 100"), Ok(CommandResult::BitField(vec![Some(0), Some(200), Some(44)])));
+        assert_eq!(run(&mut dict, "BITFIELD counters OVERFLOW SAT INCRBY u8 8 250 OVERFLOW FAIL INCRBY u8 8 1"), Ok(CommandResult::BitField(vec![Some(255), None])));
+        assert_eq!(dict.getbit("counters", 8), Ok(true));
+
+        // Signed fields span bytes and wrap in two's complement
+        assert_eq!(run(&mut dict, "BITFIELD signed SET i5 3 15 INCRBY i5 3 1 GET u5 3"), Ok(CommandResult::BitField(vec![Some(0), Some(-16), Some(16)])));
+        assert_eq!(run(&mut dict, "BITFIELD signed OVERFLOW SAT INCRBY i5 3 -100 SET i64 8 -1 GET i64 8"), Ok(CommandResult::BitField(vec![Some(-16), Some(0), Some(-1)])));
+        assert_eq!(run(&mut dict, "BITFIELD signed OVERFLOW FAIL SET u4 0 16"), Ok(CommandResult::BitField(vec![None])));
+
+        run(&mut dict, "SET string 19").unwrap();
+        assert_eq!(run(&mut dict, "BITFIELD string GET u8 0"), Ok(CommandResult::BitField(vec![Some(b'1' as i64)])));
+    }
+}
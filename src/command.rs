@@ -1,46 +1,700 @@
-use std::{time::Duration};
+use std::{fmt::Display, ops::Bound, time::{Duration, SystemTime}};
 
-#[derive(Debug, PartialEq, Eq)]
+use crate::{errors::DictionaryError, stream::{PendingInfo, PendingSummary, StreamEntry, StreamId}};
+
+#[derive(Debug, PartialEq)]
 pub enum Command {
-    Set(String, String),
+    /// Values may be binary
+    Set(String, Vec<u8>, SetOptions),
     Get(String),
-    Del(String),
-    Exists(String),
-    Expire(String, Duration),
+    GetDel(String),
+    GetEx(String, Option<GetExOption>),
+    Append(String, Vec<u8>),
+    Strlen(String),
+    /// Inclusive byte offsets, negative ones counting from the end
+    GetRange(String, i64, i64),
+    /// Byte offset
+    SetRange(String, usize, Vec<u8>),
+    Del(Vec<String>),
+    Exists(Vec<String>),
+    MGet(Vec<String>),
+    /// `(key, value)` pairs
+    MSet(Vec<(String, Vec<u8>)>),
+    /// `(key, value)` pairs, only set if none of the keys exist
+    MSetNx(Vec<(String, Vec<u8>)>),
+    /// From, to
+    Rename(String, String),
+    /// From, to
+    RenameNx(String, String),
+    /// From, to, and whether to replace an existing key
+    Copy(String, String, bool),
+    Expire(String, Duration, Option<ExpireCondition>),
+    /// EXPIREAT and PEXPIREAT
+    ExpireAt(String, SystemTime, Option<ExpireCondition>),
     Incr(String),
     Decr(String),
+    IncrBy(String, i64),
+    DecrBy(String, i64),
+    IncrByFloat(String, f64),
+    /// FLUSHALL, every database
     Clear,
+    /// The selected database only
+    FlushDb,
+    Select(usize),
+    SwapDb(usize, usize),
+    /// To the database with that index
+    Move(String, usize),
     Save,
-    Load
+    Load,
+    Ping(Option<String>),
+    /// Switches the connection to the given RESP version
+    Hello(Option<u8>),
+    /// Stops the server, saving a final snapshot if true
+    Shutdown(bool),
+    Info,
+    /// Glob pattern, see `glob`
+    Keys(String),
+    Scan(u64, ScanOptions),
+    DbSize,
+    RandomKey,
+    Ttl(String),
+    Pttl(String),
+    Persist(String),
+    Type(String),
+    /// LPUSH and RPUSH
+    Push(String, Vec<String>, ListEnd),
+    /// LPOP and RPOP, with an optional count
+    Pop(String, Option<usize>, ListEnd),
+    LRange(String, i64, i64),
+    LLen(String),
+    LIndex(String, i64),
+    LTrim(String, i64, i64),
+    HSet(String, Vec<(String, String)>),
+    /// HSET replying OK, as older clients expect
+    HMSet(String, Vec<(String, String)>),
+    HSetNx(String, String, String),
+    HGet(String, String),
+    HMGet(String, Vec<String>),
+    HDel(String, Vec<String>),
+    HExists(String, String),
+    HLen(String),
+    HKeys(String),
+    HVals(String),
+    HGetAll(String),
+    HIncrBy(String, String, i64),
+    HIncrByFloat(String, String, f64),
+    /// HSCAN, TYPE is not accepted
+    HScan(String, u64, ScanOptions),
+    SAdd(String, Vec<String>),
+    SRem(String, Vec<String>),
+    SMembers(String),
+    SIsMember(String, String),
+    SCard(String),
+    /// SINTER, SUNION and SDIFF
+    SetOp(SetOperation, Vec<String>),
+    /// SINTERSTORE, SUNIONSTORE and SDIFFSTORE, with the destination first
+    SetOpStore(SetOperation, String, Vec<String>),
+    /// With an optional count, negative ones allowing repeats
+    SRandMember(String, Option<i64>),
+    SPop(String, Option<usize>),
+    /// `(score, member)` pairs
+    ZAdd(String, Vec<(f64, String)>, ZAddOptions),
+    /// ZADD with INCR: key, increment, member
+    ZAddIncr(String, f64, String, ZAddOptions),
+    /// Key, increment, member
+    ZIncrBy(String, f64, String),
+    /// ZRANGE and ZRANGEBYSCORE
+    ZRange(String, ZRangeOptions),
+    ZRank(String, String),
+    ZScore(String, String),
+    ZCard(String),
+    ZRem(String, Vec<String>),
+    /// ZPOPMIN and ZPOPMAX, with a count of 1 by default
+    ZPop(String, usize, ZPopEnd),
+    /// Key, ID, field/value pairs and MAXLEN
+    XAdd(String, XAddId, Vec<(String, String)>, Option<usize>),
+    XLen(String),
+    /// XRANGE and XREVRANGE: key, start, end, COUNT and whether reversed
+    XRange(String, Bound<StreamId>, Bound<StreamId>, Option<usize>, bool),
+    /// Keys with where to read from, and COUNT
+    XRead(Vec<(String, StreamStart)>, Option<usize>),
+    /// Group, consumer, keys with where to read from, and COUNT
+    XReadGroup(String, String, Vec<(String, StreamStart)>, Option<usize>),
+    /// Key, group, where it starts and MKSTREAM
+    XGroupCreate(String, String, StreamStart, bool),
+    XGroupDestroy(String, String),
+    XAck(String, String, Vec<StreamId>),
+    XPending(String, String),
+    /// Key, group, start, end, count and consumer
+    XPendingRange(String, String, Bound<StreamId>, Bound<StreamId>, usize, Option<String>),
+    /// Key, group, consumer, minimum idle time and IDs
+    XClaim(String, String, String, Duration, Vec<StreamId>),
+    /// Key, bit offset and the bit to write
+    SetBit(String, u64, bool),
+    GetBit(String, u64),
+    /// The whole string without a range
+    BitCount(String, Option<BitRange>),
+    /// Key, the bit to look for and an optional range
+    BitPos(String, bool, Option<BitRange>),
+    /// Operation, destination and source keys
+    BitOp(BitOperation, String, Vec<String>),
+    BitField(String, Vec<BitFieldOperation>),
+    PfAdd(String, Vec<String>),
+    PfCount(Vec<String>),
+    /// Destination, then source keys
+    PfMerge(String, Vec<String>)
+}
+
+impl Command {
+    /// Reads that reply nil to a missing key, any other command fails with
+    /// `DictionaryError::DoesNotExist` only when the key must exist
+    pub fn nil_when_missing(&self) -> bool {
+        matches!(self, Command::Get(_) | Command::GetDel(_) | Command::GetEx(..) | Command::Pop(..))
+    }
+}
+
+/// Which end of a list a command works on
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ListEnd {
+    Left,
+    Right
+}
+
+/// ZADD \[NX|XX\] \[GT|LT\] \[CH\]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct ZAddOptions {
+    /// Whether the member may or must exist already
+    pub condition: Option<SetCondition>,
+    /// Only update existing members to a greater or lower score
+    pub comparison: Option<ZAddComparison>,
+    /// Count changed members along with new ones
+    pub changed: bool
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ZAddComparison {
+    Gt,
+    Lt
+}
+
+/// ZRANGE \<start\> \<stop\> \[BYSCORE|BYLEX\] \[REV\] \[LIMIT offset count\] \[WITHSCORES\]
+#[derive(Debug, PartialEq, Clone)]
+pub struct ZRangeOptions {
+    pub by: ZRangeBy,
+    /// From the highest score down
+    pub rev: bool,
+    /// Offset and count, BYSCORE and BYLEX only
+    pub limit: Option<(usize, usize)>,
+    pub with_scores: bool
+}
+
+/// What ZRANGE's start and stop are, score and lex bounds are kept as min then max even with REV
+#[derive(Debug, PartialEq, Clone)]
+pub enum ZRangeBy {
+    /// Inclusive, negative ones counting from the end
+    Rank(i64, i64),
+    Score(Bound<f64>, Bound<f64>),
+    /// Only meaningful when every member has the same score
+    Lex(Bound<String>, Bound<String>)
+}
+
+/// Which end ZPOPMIN and ZPOPMAX pop from
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ZPopEnd {
+    Min,
+    Max
+}
+
+/// The ID XADD gives to the new entry
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum XAddId {
+    /// `*`
+    Auto,
+    /// `ms-*`
+    AutoSeq(u64),
+    Explicit(StreamId)
+}
+
+/// Where XREAD, XREADGROUP and XGROUP CREATE start
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StreamStart {
+    /// After this ID
+    After(StreamId),
+    /// `$`, after the last entry
+    Last,
+    /// `>`, entries never delivered to the group
+    New
+}
+
+/// BITCOUNT and BITPOS `start [end [BYTE|BIT]]`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BitRange {
+    /// Inclusive, negative ones counting from the end
+    pub start: i64,
+    /// Up to the end of the string if None, BITPOS only
+    pub end: Option<i64>,
+    /// Whether offsets count bits rather than bytes
+    pub bits: bool
+}
+
+/// How BITOP combines its sources, shorter ones being padded with zero bytes
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    /// Of a single source
+    Not
+}
+
+/// A BITFIELD sub-command, offsets are in bits with `#n` already multiplied by the width
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BitFieldOperation {
+    Get(BitFieldType, u64),
+    Set(BitFieldType, u64, i64),
+    IncrBy(BitFieldType, u64, i64),
+    /// For the SET and INCRBY after it, WRAP until then
+    Overflow(BitFieldOverflow)
+}
+
+/// `i1` to `i64` or `u1` to `u63`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BitFieldType {
+    pub signed: bool,
+    pub bits: u32
+}
+
+/// What BITFIELD SET and INCRBY do with values out of the range of their type
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum BitFieldOverflow {
+    /// Modulo the range, two's complement style for signed types
+    #[default]
+    Wrap,
+    /// Clamped to the range
+    Sat,
+    /// Nothing is written and the reply is nil
+    Fail
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// How SINTER, SUNION and SDIFF combine their sets, from the first one on
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SetOperation {
+    Inter,
+    Union,
+    Diff
+}
+
+#[derive(Debug, PartialEq)]
 pub enum CommandResult {
-    Set,
-    Get(String),
-    Del,
-    Exists(bool),
-    Expire,
-    Incr,
-    Decr,
+    /// Whether the value was written
+    Set(bool),
+    /// SET with GET, whether the value was written and the previous one
+    SetGet(bool, Option<Vec<u8>>),
+    /// Also GETDEL, GETEX and GETRANGE
+    Get(Vec<u8>),
+    /// New length in bytes
+    Append(usize),
+    /// Length in bytes
+    Strlen(usize),
+    /// New length in bytes
+    SetRange(usize),
+    /// How many keys were deleted
+    Del(usize),
+    /// How many of the keys exist, repeated keys being counted again
+    Exists(usize),
+    /// One value per key, None for missing keys
+    MGet(Vec<Option<Vec<u8>>>),
+    MSet,
+    /// Whether the keys were set
+    MSetNx(bool),
+    Rename,
+    /// Whether the key was renamed
+    RenameNx(bool),
+    /// Whether the key was copied
+    Copy(bool),
+    /// Whether the expiration was set
+    Expire(bool),
+    /// The new value, also for INCRBY
+    Incr(i64),
+    /// The new value, also for DECRBY
+    Decr(i64),
+    IncrByFloat(f64),
     Clear,
+    FlushDb,
+    Select,
+    SwapDb,
+    /// Whether the key was moved
+    Move(bool),
     Save,
-    Load
+    Load,
+    Ping(Option<String>),
+    Hello(Option<u8>),
+    Shutdown(bool),
+    /// `(name, value)` pairs
+    Info(Vec<(String, String)>),
+    Keys(Vec<String>),
+    /// The cursor to continue from, 0 once done, and a batch of keys
+    Scan(u64, Vec<String>),
+    DbSize(usize),
+    /// None if there are no keys
+    RandomKey(Option<String>),
+    Ttl(Ttl),
+    Pttl(Ttl),
+    /// Whether an expiration was removed
+    Persist(bool),
+    /// "none" for a missing key
+    Type(String),
+    /// New length of the list
+    Push(usize),
+    /// LRANGE, and LPOP/RPOP with a count
+    List(Vec<String>),
+    LLen(usize),
+    LIndex(Option<String>),
+    LTrim,
+    /// How many fields are new
+    HSet(usize),
+    HMSet,
+    HSetNx(bool),
+    HGet(Option<String>),
+    /// How many fields were deleted
+    HDel(usize),
+    HExists(bool),
+    HLen(usize),
+    HGetAll(Vec<(String, String)>),
+    /// How many members are new
+    SAdd(usize),
+    /// How many members were removed
+    SRem(usize),
+    SIsMember(bool),
+    SCard(usize),
+    /// Size of the stored set
+    SetOpStore(usize),
+    /// SRANDMEMBER and SPOP without a count, None for a missing key
+    Member(Option<String>),
+    /// How many members are new, or changed with CH
+    ZAdd(usize),
+    /// ZSCORE, and ZADD with INCR which gives None when the update was prevented
+    Score(Option<f64>),
+    /// Members with their scores, ZRANGE with WITHSCORES and ZPOPMIN/ZPOPMAX
+    Scored(Vec<(String, f64)>),
+    ZRank(Option<usize>),
+    ZCard(usize),
+    /// How many members were removed
+    ZRem(usize),
+    XAdd(StreamId),
+    XLen(usize),
+    /// XRANGE, XREVRANGE and XCLAIM
+    Entries(Vec<StreamEntry>),
+    /// XREAD and XREADGROUP, only streams with entries
+    XRead(Vec<(String, Vec<StreamEntry>)>),
+    XGroupCreate,
+    /// Whether the group existed
+    XGroupDestroy(bool),
+    /// How many entries were acknowledged
+    XAck(usize),
+    XPending(PendingSummary),
+    XPendingRange(Vec<PendingInfo>),
+    /// GETBIT, and SETBIT with the previous bit
+    Bit(bool),
+    /// How many bits are set
+    BitCount(usize),
+    /// -1 if the bit was not found
+    BitPos(i64),
+    /// Length of the stored string
+    BitOp(usize),
+    /// One value per GET, SET and INCRBY, None when OVERFLOW FAIL prevented a write
+    BitField(Vec<Option<i64>>),
+    /// Whether the estimate may have changed
+    PfAdd(bool),
+    /// Estimated number of distinct elements
+    PfCount(u64),
+    PfMerge
+}
+
+/// SET \[NX|XX\] \[EX s|PX ms|EXAT ts|PXAT ms|KEEPTTL\] \[GET\]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct SetOptions {
+    pub condition: Option<SetCondition>,
+    /// No expiration if None, like a plain SET
+    pub expiration: Option<SetExpiration>,
+    /// Reply with the previous value
+    pub get: bool
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SetCondition {
+    /// Only if the key does not exist
+    Nx,
+    /// Only if the key exists
+    Xx
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SetExpiration {
+    /// EX and PX
+    In(Duration),
+    /// EXAT and PXAT
+    At(SystemTime),
+    /// KEEPTTL, the expiration of the previous value is kept
+    Keep
+}
+
+pub const DEFAULT_SCAN_COUNT: usize = 10;
+
+/// SCAN \[MATCH pattern\] \[COUNT n\] \[TYPE type\]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ScanOptions {
+    /// Glob pattern, see `glob`
+    pub pattern: Option<String>,
+    /// How many keys to look at, filters apply afterwards
+    pub count: usize,
+    /// Only keys of this type, as named by `Entry::type_name`
+    pub type_name: Option<String>
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions { pattern: None, count: DEFAULT_SCAN_COUNT, type_name: None }
+    }
+}
+
+/// GETEX \[EX s|PX ms|EXAT ts|PXAT ms|PERSIST\]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GetExOption {
+    /// Never `SetExpiration::Keep`, that's no option at all
+    Expire(SetExpiration),
+    Persist
+}
+
+/// When an expiration may be set
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ExpireCondition {
+    /// Only if the key has no expiration
+    Nx,
+    /// Only if the key has an expiration
+    Xx,
+    /// Only if the new expiration is later, no expiration counting as never
+    Gt,
+    /// Only if the new expiration is sooner, no expiration counting as never
+    Lt
+}
+
+/// Remaining lifetime of a key
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Ttl {
+    Remaining(Duration),
+    NoExpiry,
+    NoKey
+}
+
+impl Ttl {
+    /// Redis style, -1 for no expiry and -2 for no key
+    pub fn as_secs(&self) -> i64 {
+        match self {
+            // Rounded to the nearest second
+            Ttl::Remaining(remaining) => ((remaining.as_millis() + 500) / 1000) as i64,
+            Ttl::NoExpiry => -1,
+            Ttl::NoKey => -2
+        }
+    }
+
+    /// Redis style, -1 for no expiry and -2 for no key
+    pub fn as_millis(&self) -> i64 {
+        match self {
+            Ttl::Remaining(remaining) => remaining.as_millis() as i64,
+            Ttl::NoExpiry => -1,
+            Ttl::NoKey => -2
+        }
+    }
+
+    /// Human readable, in humantime format to the second or to the millisecond
+    fn fmt_human(&self, f: &mut std::fmt::Formatter<'_>, millis: bool) -> std::fmt::Result {
+        match self {
+            Ttl::Remaining(_) => {
+                let remaining = match millis {
+                    true => Duration::from_millis(self.as_millis() as u64),
+                    false => Duration::from_secs(self.as_secs() as u64)
+                };
+                write!(f, "{}", humantime::format_duration(remaining))
+            },
+            Ttl::NoExpiry => write!(f, "No expiration."),
+            Ttl::NoKey => write!(f, "{}", DictionaryError::DoesNotExist)
+        }
+    }
 }
 
-impl ToString for CommandResult {
-    fn to_string(&self) -> String {
+impl Display for CommandResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            // Binary strings are shown lossily, RESP replies carry them as they are
             CommandResult::Get(got) => {
-                got.to_string()
+                write!(f, "{}", String::from_utf8_lossy(got))
+            },
+            CommandResult::Append(len) | CommandResult::Strlen(len) | CommandResult::SetRange(len) => {
+                write!(f, "{len}")
+            },
+            CommandResult::Incr(value) | CommandResult::Decr(value) => {
+                write!(f, "{value}")
+            },
+            CommandResult::IncrByFloat(value) => {
+                write!(f, "{value}")
+            },
+            CommandResult::Set(false) => {
+                write!(f, "Key was not set.")
+            },
+            CommandResult::SetGet(_, previous) => {
+                match previous {
+                    Some(previous) => write!(f, "{}", String::from_utf8_lossy(previous)),
+                    None => write!(f, "{}", DictionaryError::DoesNotExist)
+                }
+            },
+            CommandResult::Del(count) | CommandResult::Exists(count) => {
+                write!(f, "{count}")
+            },
+            CommandResult::MGet(values) => {
+                let values: Vec<_> = values.iter().map(|value| value.as_deref().map_or("(nil)".into(), String::from_utf8_lossy)).collect();
+                write!(f, "{}", values.join(" "))
+            },
+            CommandResult::Keys(keys) => {
+                write!(f, "{}", keys.join(" "))
+            },
+            CommandResult::Scan(cursor, keys) => {
+                match keys.is_empty() {
+                    true => write!(f, "{cursor}"),
+                    false => write!(f, "{cursor} {}", keys.join(" "))
+                }
+            },
+            CommandResult::Type(type_name) => {
+                write!(f, "{type_name}")
+            },
+            CommandResult::Push(len) | CommandResult::LLen(len) | CommandResult::HSet(len) | CommandResult::HDel(len) | CommandResult::HLen(len) => {
+                write!(f, "{len}")
+            },
+            CommandResult::SAdd(len) | CommandResult::SRem(len) | CommandResult::SCard(len) | CommandResult::SetOpStore(len) => {
+                write!(f, "{len}")
+            },
+            CommandResult::ZAdd(len) | CommandResult::ZCard(len) | CommandResult::ZRem(len) => {
+                write!(f, "{len}")
+            },
+            CommandResult::XLen(len) | CommandResult::XAck(len) => {
+                write!(f, "{len}")
+            },
+            CommandResult::Bit(bit) => {
+                write!(f, "{}", *bit as u8)
+            },
+            CommandResult::BitCount(count) | CommandResult::BitOp(count) => {
+                write!(f, "{count}")
             },
-            CommandResult::Exists(check) => {
-                check.to_string()
+            CommandResult::PfAdd(changed) => {
+                write!(f, "{}", *changed as u8)
+            },
+            CommandResult::PfCount(count) => {
+                write!(f, "{count}")
+            },
+            CommandResult::BitPos(position) => {
+                write!(f, "{position}")
+            },
+            CommandResult::BitField(values) => {
+                let values: Vec<String> = values.iter().map(|value| value.map_or("(nil)".to_string(), |value| value.to_string())).collect();
+                write!(f, "{}", values.join(" "))
+            },
+            CommandResult::XAdd(id) => {
+                write!(f, "{id}")
+            },
+            CommandResult::Entries(entries) => {
+                write!(f, "{}", entries_to_string(entries))
+            },
+            CommandResult::XRead(streams) => {
+                write!(f, "{}", streams.iter().map(|(key, entries)| format!("{key} {}", entries_to_string(entries))).collect::<Vec<_>>().join(" "))
+            },
+            CommandResult::XGroupDestroy(existed) => {
+                write!(f, "{existed}")
+            },
+            CommandResult::XPending(summary) => {
+                match summary.range {
+                    Some((first, last)) => {
+                        let consumers: Vec<String> = summary.consumers.iter().map(|(consumer, count)| format!("{consumer} {count}")).collect();
+                        write!(f, "{} {first} {last} {}", summary.count, consumers.join(" "))
+                    },
+                    None => write!(f, "0")
+                }
+            },
+            CommandResult::XPendingRange(pending) => {
+                let lines: Vec<String> = pending.iter()
+                    .map(|info| format!("{} {} {} {}", info.id, info.consumer, info.idle.as_millis(), info.deliveries))
+                    .collect();
+                write!(f, "{}", lines.join(" "))
+            },
+            CommandResult::ZRank(rank) => {
+                match rank {
+                    Some(rank) => write!(f, "{rank}"),
+                    None => write!(f, "(nil)")
+                }
+            },
+            CommandResult::Score(score) => {
+                match score {
+                    Some(score) => write!(f, "{score}"),
+                    None => write!(f, "(nil)")
+                }
+            },
+            CommandResult::Scored(members) => {
+                write!(f, "{}", members.iter().map(|(member, score)| format!("{member} {score}")).collect::<Vec<_>>().join(" "))
+            },
+            CommandResult::List(values) => {
+                write!(f, "{}", values.join(" "))
+            },
+            CommandResult::LIndex(value) | CommandResult::HGet(value) | CommandResult::Member(value) => {
+                write!(f, "{}", value.as_deref().unwrap_or("(nil)"))
+            },
+            CommandResult::DbSize(size) => {
+                write!(f, "{size}")
+            },
+            CommandResult::RandomKey(key) => {
+                write!(f, "{}", key.as_deref().unwrap_or("(nil)"))
+            },
+            CommandResult::RenameNx(done) | CommandResult::Copy(done) | CommandResult::Move(done) => {
+                write!(f, "{done}")
+            },
+            CommandResult::HGetAll(pairs) => {
+                write!(f, "{}", pairs.iter().map(|(field, value)| format!("{field} {value}")).collect::<Vec<_>>().join(" "))
+            },
+            CommandResult::MSetNx(set) | CommandResult::HSetNx(set) | CommandResult::HExists(set) | CommandResult::SIsMember(set) => {
+                write!(f, "{set}")
+            },
+            CommandResult::Ping(message) => {
+                write!(f, "{}", message.as_deref().unwrap_or("PONG"))
+            },
+            CommandResult::Hello(_) => {
+                write!(f, "kvdis {}", env!("CARGO_PKG_VERSION"))
+            },
+            CommandResult::Info(info) => {
+                let info: Vec<String> = info.iter().map(|(name, value)| format!("{name}:{value}")).collect();
+                write!(f, "{}", info.join(" "))
+            },
+            CommandResult::Ttl(ttl) => {
+                ttl.fmt_human(f, false)
+            },
+            CommandResult::Pttl(ttl) => {
+                ttl.fmt_human(f, true)
+            },
+            CommandResult::Persist(persisted) | CommandResult::Expire(persisted) => {
+                write!(f, "{persisted}")
             }
 
             _ => {
-                "".to_string()
+                Ok(())
             }
         }
     }
 }
+
+/// Each ID followed by its fields and values
+fn entries_to_string(entries: &[StreamEntry]) -> String {
+    entries.iter()
+        .map(|(id, fields)| {
+            let fields: Vec<String> = fields.iter().map(|(field, value)| format!("{field} {value}")).collect();
+            format!("{id} {}", fields.join(" "))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
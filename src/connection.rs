@@ -1,52 +1,824 @@
-use std::{io::{self, BufRead, BufReader, BufWriter, Write}, net::TcpListener};
+use std::{io::{self, BufRead, BufReader, BufWriter, Read, Write}, net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream}, sync::{atomic::{AtomicUsize, Ordering}, Arc}, thread};
+#[cfg(unix)]
+use std::{fs, os::unix::net::{UnixListener, UnixStream}, path::Path};
 
-use crate::{command::Command, dictionary::Dictionary};
+use crate::{command::{Command, CommandResult}, dictionary::Dictionary, errors::{ConnectionError, DictionaryError, ParseError}, pool::ThreadPool, resp::{self, Frame, Protocol}, shutdown::{ClientStream, Shutdown}};
 
 type Port = u16;
 pub const DEFAULT_PORT: Port = 7777;
+pub const DEFAULT_BIND: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+pub const DEFAULT_MAX_CLIENTS: usize = 64;
+/// Longest inline command, in bytes, larger values have to go through RESP
+pub const MAX_INLINE_LEN: usize = 1024 * 1024;
+/// Size of both the read and write buffers of a connection,
+/// roughly bounding how many pipelined commands are handled per batch
+const BUFFER_SIZE: usize = 64 * 1024;
 
-pub fn bind(port: Option<Port>) -> TcpListener {
-    let listener = match port {
-        Some(port) => {
-            TcpListener::bind(format!("127.0.0.1:{port}"))
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Size of the worker pool, which is also the number of clients served at once.
+    /// Clients connecting past this limit are rejected.
+    /// The async server has no pool, only the limit applies.
+    pub max_clients: usize
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { max_clients: DEFAULT_MAX_CLIENTS }
+    }
+}
+
+/// Keeps count of connected clients, releasing its slot when dropped
+struct ClientSlot(Arc<AtomicUsize>);
+
+impl ClientSlot {
+    fn claim(clients: &Arc<AtomicUsize>, max: usize) -> Option<Self> {
+        if clients.fetch_add(1, Ordering::SeqCst) >= max {
+            clients.fetch_sub(1, Ordering::SeqCst);
+            return None;
         }
-        None => {
-            TcpListener::bind(format!("127.0.0.1:{}", DEFAULT_PORT.to_string()))
+
+        Some(ClientSlot(Arc::clone(clients)))
+    }
+}
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Anything `run` can accept clients from
+pub trait Listener: Sync {
+    type Stream: ClientStream;
+
+    /// Accepts the next client, its stream is in blocking mode
+    fn accept_client(&self) -> io::Result<Self::Stream>;
+
+    /// Connects to the listener, unblocking a pending `accept_client`
+    fn wake(&self) -> io::Result<()>;
+}
+
+impl Listener for TcpListener {
+    type Stream = TcpStream;
+
+    fn accept_client(&self) -> io::Result<TcpStream> {
+        let (stream, _) = self.accept()?;
+        stream.set_nonblocking(false)?;
+        Ok(stream)
+    }
+
+    fn wake(&self) -> io::Result<()> {
+        let mut addr = self.local_addr()?;
+        // Wildcard addresses can't be connected to
+        match addr.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => addr.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            IpAddr::V6(ip) if ip.is_unspecified() => addr.set_ip(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+            _ => {}
         }
-    };
 
-    match listener {
+        TcpStream::connect(addr).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+impl Listener for UnixListener {
+    type Stream = UnixStream;
+
+    fn accept_client(&self) -> io::Result<UnixStream> {
+        let (stream, _) = self.accept()?;
+        stream.set_nonblocking(false)?;
+        Ok(stream)
+    }
+
+    fn wake(&self) -> io::Result<()> {
+        let addr = self.local_addr()?;
+        let path = addr.as_pathname()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::AddrNotAvailable, "Unnamed socket"))?;
+
+        UnixStream::connect(path).map(|_| ())
+    }
+}
+
+/// Binds to `addr`, which may be any IPv4 or IPv6 address such as `0.0.0.0` or `::1`
+pub fn bind(addr: IpAddr, port: Option<Port>) -> TcpListener {
+    let addr = SocketAddr::new(addr, port.unwrap_or(DEFAULT_PORT));
+
+    match TcpListener::bind(addr) {
+        Err(e) => panic!("Listening socket could not be created: {e}"),
+        Ok(l) => l
+    }
+}
+
+/// Binds a Unix domain socket at `path`, replacing a socket left behind by a previous run.
+/// `permissions` are applied to the socket file, e.g. `0o770` to only let a group connect.
+/// # Umask
+/// The file is created with `permissions` already in place, so nobody can connect in between.
+/// This goes through the process-wide umask, which is restored right after.
+#[cfg(unix)]
+pub fn bind_unix(path: &Path, permissions: Option<u32>) -> UnixListener {
+    use std::os::unix::fs::FileTypeExt;
+
+    if let Ok(metadata) = fs::symlink_metadata(path)
+        && metadata.file_type().is_socket() {
+        let _ = fs::remove_file(path);
+    }
+
+    // SAFETY: umask can't fail and only touches the process' file mode creation mask
+    let previous = permissions.map(|mode| unsafe { libc::umask(!mode as libc::mode_t & 0o777) });
+    let bound = UnixListener::bind(path);
+    if let Some(mask) = previous {
+        unsafe { libc::umask(mask) };
+    }
+
+    match bound {
         Err(e) => panic!("Listening socket could not be created: {e}"),
         Ok(l) => l
     }
 }
 
-/// # Half-duplex connection
-/// Commands are received, processed, and responded to.
+/// Accepts clients and hands each one to a worker of a pool of `config.max_clients` threads.
+/// Every worker operates on a clone of `dict`, all sharing the same map.
+/// # Shutdown
+/// Returns once `shutdown` is requested, after every client has been answered
+/// the commands it sent before the request.
+pub fn run<L>(dict: &Dictionary, listener: &L, config: &Config, shutdown: &Arc<Shutdown>) -> io::Result<()>
+where
+    L: Listener,
+    for<'a> &'a L::Stream: Read + Write
+{
+    thread::scope(|scope| {
+        // A blocked accept doesn't see the request, someone has to get the loop going again
+        scope.spawn(|| {
+            shutdown.wait();
+
+            if let Err(e) = listener.wake() {
+                eprintln!("Listener could not be woken up: {e}");
+            }
+        });
+
+        accept_clients(dict, listener, config, shutdown);
+    });
+
+    Ok(())
+}
+
+fn accept_clients<L>(dict: &Dictionary, listener: &L, config: &Config, shutdown: &Arc<Shutdown>)
+where
+    L: Listener,
+    for<'a> &'a L::Stream: Read + Write
+{
+    let pool = ThreadPool::new(config.max_clients);
+    let clients = Arc::new(AtomicUsize::new(0));
+
+    loop {
+        let stream = listener.accept_client();
+        if shutdown.is_requested() {
+            break;
+        }
+
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Connection could not be accepted: {e}");
+                continue;
+            }
+        };
+
+        let Some(slot) = ClientSlot::claim(&clients, config.max_clients) else {
+            let _ = (&stream).write_all(format!("[Error]: {}\n", ConnectionError::MaxClients).as_bytes());
+            continue;
+        };
+
+        let stream = Arc::new(stream);
+        let id = shutdown.track(stream.clone());
+
+        let mut dict = dict.clone();
+        let shutdown = Arc::clone(shutdown);
+        pool.execute(move || {
+            let _slot = slot;
+
+            // A misbehaving client should not take the server down with it
+            if let Err(e) = handle_client(&mut dict, &*stream, &shutdown) {
+                eprintln!("Connection closed with error: {e}");
+            }
+
+            shutdown.untrack(id);
+        });
+    }
+
+    // Idle clients are hung up on, busy ones finish what they were sent.
+    // Dropping the pool waits for all of them.
+    shutdown.close_clients();
+    drop(pool);
+}
+
+/// How a client talks to us, settled by its first request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Inline commands answered with newline-terminated text
+    Text,
+    /// RESP arrays or inline commands, answered in RESP
+    Resp(Protocol)
+}
+
+/// Per-connection state
+#[derive(Debug)]
+struct Session<'a> {
+    mode: Option<Mode>,
+    shutdown: &'a Shutdown,
+    /// Set once the client asked the server to shut down, no more commands are read
+    closing: bool
+}
+
+impl<'a> Session<'a> {
+    fn new(shutdown: &'a Shutdown) -> Self {
+        Session { mode: None, shutdown, closing: false }
+    }
+
+    /// Runs a request, appending its reply to `out`
+    fn respond(&mut self, dict: &mut Dictionary, is_resp: bool, request: Result<Command, ParseError>, out: &mut Vec<u8>) {
+        let mode = *self.mode.get_or_insert(match is_resp {
+            true => Mode::Resp(Protocol::Resp2),
+            false => Mode::Text
+        });
+
+        // The rest of the stream can't be trusted
+        if matches!(request, Err(ParseError::Protocol | ParseError::LineTooLong)) {
+            self.closing = true;
+        }
+
+        let nil_when_missing = request.as_ref().is_ok_and(Command::nil_when_missing);
+        let result = request.map(|command| dict.run_headless(command));
+
+        if let Ok(Ok(CommandResult::Shutdown(save))) = result {
+            self.shutdown.request(save);
+            self.closing = true;
+        }
+
+        // HELLO with a version upgrades even a text connection
+        if let Ok(Ok(CommandResult::Hello(version))) = result {
+            let protocol = match (version, mode) {
+                (Some(3), _) => Protocol::Resp3,
+                (Some(_), _) => Protocol::Resp2,
+                (None, Mode::Resp(protocol)) => protocol,
+                (None, Mode::Text) => {
+                    out.extend_from_slice(CommandResult::Hello(None).to_string().as_bytes());
+                    out.push(b'\n');
+                    return;
+                }
+            };
+
+            self.mode = Some(Mode::Resp(protocol));
+            resp::hello(protocol).encode(protocol, out);
+            return;
+        }
+
+        match mode {
+            Mode::Text => {
+                let response = match result {
+                    Ok(Ok(result)) => result.to_string(),
+                    Ok(Err(e)) => e.to_string(),
+                    Err(e) => format!("[Error]: {e}")
+                };
+                out.extend_from_slice(escape_text(&response).as_bytes());
+                out.push(b'\n');
+            },
+            Mode::Resp(protocol) => {
+                let frame = match result {
+                    Ok(Ok(result)) => Frame::from(result),
+                    Ok(Err(DictionaryError::DoesNotExist)) if nil_when_missing => Frame::Null,
+                    Ok(Err(e)) => Frame::from(&e),
+                    Err(e) => Frame::from(&e)
+                };
+                frame.encode(protocol, out);
+            }
+        }
+    }
+}
+
+/// Keeps a text reply on one line, values may hold line breaks.
+/// `\` becomes `\\`, CR `\r` and LF `\n`.
+fn escape_text(reply: &str) -> String {
+    let mut escaped = String::with_capacity(reply.len());
+    for c in reply.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\r' => escaped.push_str("\\r"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c)
+        }
+    }
+
+    escaped
+}
+
+/// Reads the next request, detecting whether it is a RESP array or an inline command
+/// # Returns
+/// - None on EOF
+/// - Some((is_resp, parsed command))
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Option<(bool, Result<Command, ParseError>)>> {
+    let Some(&first) = reader.fill_buf()?.first() else {
+        return Ok(None);
+    };
+
+    if first == b'*' {
+        let command = Command::from_request(resp::read_request(reader)?);
+
+        Ok(Some((true, command)))
+    } else {
+        let mut line = Vec::new();
+        let command = match resp::read_line(reader, &mut line, MAX_INLINE_LEN) {
+            Ok(()) => inline_command(line),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => Err(ParseError::LineTooLong),
+            Err(e) => return Err(e)
+        };
+
+        Ok(Some((false, command)))
+    }
+}
+
+/// Errors are replied to in the protocol of the session before it is closed, see `Session::respond`
+fn inline_command(line: Vec<u8>) -> Result<Command, ParseError> {
+    String::from_utf8(line).map_err(|_| ParseError::Protocol)?.parse::<Command>()
+}
+
+/// # Persistent connection
+/// Commands are read, processed, and responded to
+/// until the client closes its side of the connection.
+/// Clients whose first command is a RESP array get RESP replies,
+/// otherwise every response is plain text terminated by a single `\n`.
 /// With the exception of SAVE/LOAD calls which run on a seperate thread.
-pub fn run(dict: &mut Dictionary, listener: &TcpListener) -> io::Result<()> {
-    for stream in listener.incoming() {
-        let stream = stream?;
-        let mut reader = BufReader::new(&stream);
-        let mut writer = BufWriter::new(&stream);
+/// # Pipelining
+/// Every command already buffered is run in order before the replies are flushed together,
+/// so clients can send many commands without waiting for each reply.
+fn handle_client<S>(dict: &mut Dictionary, stream: &S, shutdown: &Shutdown) -> io::Result<()>
+where
+    for<'a> &'a S: Read + Write
+{
+    let mut reader = BufReader::with_capacity(BUFFER_SIZE, stream);
+    let mut writer = BufWriter::with_capacity(BUFFER_SIZE, stream);
+    let mut session = Session::new(shutdown);
 
-        let mut command = String::new();
-        reader.read_line(&mut command)?;
+    let mut out = Vec::new();
+    loop {
+        let (is_resp, request) = match read_request(&mut reader) {
+            Ok(Some(request)) => request,
+            // EOF
+            Ok(None) => return writer.flush(),
+            // The stream is out of sync, tell the client why before hanging up
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                let _ = writer.write_all(format!("-ERR {}\r\n", ParseError::Protocol).as_bytes());
+                let _ = writer.flush();
+                return Err(e);
+            },
+            Err(e) => return Err(e)
+        };
+
+        // Blank lines are not worth a response
+        if request != Err(ParseError::IsEmpty) {
+            out.clear();
+            session.respond(dict, is_resp, request, &mut out);
+            writer.write_all(&out)?;
+        }
+
+        if session.closing {
+            return writer.flush();
+        }
+
+        // Nothing left to run without blocking, send what we have
+        if reader.buffer().is_empty() {
+            writer.flush()?;
+        }
+    }
+}
 
-        let command = match command.parse::<Command>() {
-            Ok(com) => com,
+/// Async twin of `run`, every client is served by its own task.
+/// Must be called from within a Tokio runtime.
+#[cfg(feature = "async")]
+pub async fn run_async(dict: &Dictionary, listener: &tokio::net::TcpListener, config: &Config, shutdown: &Arc<Shutdown>) -> io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let clients = Arc::new(AtomicUsize::new(0));
+    let mut tasks = tokio::task::JoinSet::new();
+
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = shutdown.requested() => break
+        };
+
+        // Finished clients are only dropped once joined
+        while tasks.try_join_next().is_some() {}
+
+        let mut stream = match accepted {
+            Ok((stream, _)) => stream,
             Err(e) => {
-                writer.write_all(format!(
-                        "[Error]: {}", e.to_string()
-                        )
-                    .as_bytes())?;
+                eprintln!("Connection could not be accepted: {e}");
                 continue;
             }
         };
 
-        let result = dict.run(command);
-        writer.write_all(result.as_bytes())?;
+        let Some(slot) = ClientSlot::claim(&clients, config.max_clients) else {
+            let _ = stream.write_all(format!("[Error]: {}\n", ConnectionError::MaxClients).as_bytes()).await;
+            continue;
+        };
+
+        let mut dict = dict.clone();
+        let shutdown = Arc::clone(shutdown);
+        tasks.spawn(async move {
+            let _slot = slot;
+
+            if let Err(e) = handle_client_async(&mut dict, stream, &shutdown).await {
+                eprintln!("Connection closed with error: {e}");
+            }
+        });
     }
 
+    // Connections notice the shutdown on their own, wait for them to be done
+    while tasks.join_next().await.is_some() {}
+
     Ok(())
 }
+
+/// Async twin of `read_request`
+#[cfg(feature = "async")]
+async fn read_request_async<R>(reader: &mut R) -> io::Result<Option<(bool, Result<Command, ParseError>)>>
+where
+    R: tokio::io::AsyncBufRead + Unpin
+{
+    use tokio::io::AsyncBufReadExt;
+
+    let Some(&first) = reader.fill_buf().await?.first() else {
+        return Ok(None);
+    };
+
+    if first == b'*' {
+        let command = Command::from_request(resp::read_request_async(reader).await?);
+
+        Ok(Some((true, command)))
+    } else {
+        let mut line = Vec::new();
+        let command = match resp::read_line_async(reader, &mut line, MAX_INLINE_LEN).await {
+            Ok(()) => inline_command(line),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => Err(ParseError::LineTooLong),
+            Err(e) => return Err(e)
+        };
+
+        Ok(Some((false, command)))
+    }
+}
+
+/// Async twin of `handle_client`, with the same pipelining and shutdown behaviour
+#[cfg(feature = "async")]
+async fn handle_client_async(dict: &mut Dictionary, mut stream: tokio::net::TcpStream, shutdown: &Shutdown) -> io::Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+
+    let (read, write) = stream.split();
+    let mut reader = BufReader::with_capacity(BUFFER_SIZE, read);
+    let mut writer = BufWriter::with_capacity(BUFFER_SIZE, write);
+    let mut session = Session::new(shutdown);
+
+    let mut out = Vec::new();
+    loop {
+        // Waiting for data consumes nothing, so giving up on it for a shutdown is safe
+        tokio::select! {
+            filled = reader.fill_buf() => {
+                filled?;
+            },
+            _ = shutdown.requested() => {
+                return writer.flush().await;
+            }
+        }
+
+        let (is_resp, request) = match read_request_async(&mut reader).await {
+            Ok(Some(request)) => request,
+            Ok(None) => return writer.flush().await,
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                let _ = writer.write_all(format!("-ERR {}\r\n", ParseError::Protocol).as_bytes()).await;
+                let _ = writer.flush().await;
+                return Err(e);
+            },
+            Err(e) => return Err(e)
+        };
+
+        if request != Err(ParseError::IsEmpty) {
+            out.clear();
+            session.respond(dict, is_resp, request, &mut out);
+            writer.write_all(&out).await?;
+        }
+
+        if session.closing {
+            return writer.flush().await;
+        }
+
+        if reader.buffer().is_empty() {
+            writer.flush().await?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod connection {
+    use std::io::Read;
+
+    use super::*;
+
+    fn spawn_server(config: Config) -> std::net::SocketAddr {
+        let listener = bind(DEFAULT_BIND, Some(0));
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let dict = Dictionary::new();
+            run(&dict, &listener, &config, &Arc::new(Shutdown::default())).unwrap();
+        });
+
+        addr
+    }
+
+    fn request<S: Read + Write>(reader: &mut BufReader<S>, command: &str) -> String {
+        reader.get_mut().write_all(command.as_bytes()).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        line
+    }
+
+    #[test]
+    fn multiple_commands_one_connection() {
+        let addr = spawn_server(Config::default());
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"SET metanoia 19\nGET metanoia\nEXISTS metanoia\nNOPE\n").unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "\n19\n1\n[Error]: Not a command!\n");
+    }
+
+    #[test]
+    fn text_replies_stay_on_one_line() {
+        let addr = spawn_server(Config::default());
+
+        let mut resp = BufReader::new(TcpStream::connect(addr).unwrap());
+        assert_eq!(request(&mut resp, "*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$8\r\na\r\nb\\c\nd\r\n"), "+OK\r\n");
+
+        let mut text = BufReader::new(TcpStream::connect(addr).unwrap());
+        assert_eq!(request(&mut text, "GET k\n"), "a\\r\\nb\\\\c\\nd\n");
+    }
+
+    #[test]
+    fn concurrent_clients() {
+        let addr = spawn_server(Config::default());
+
+        let mut first = BufReader::new(TcpStream::connect(addr).unwrap());
+        let mut second = BufReader::new(TcpStream::connect(addr).unwrap());
+
+        // Both connections are open at the same time
+        assert_eq!(request(&mut first, "SET shared 1\n"), "\n");
+        assert_eq!(request(&mut second, "INCR shared\n"), "2\n");
+        assert_eq!(request(&mut first, "GET shared\n"), "2\n");
+    }
+
+    #[test]
+    fn select_per_connection() {
+        let addr = spawn_server(Config::default());
+
+        let mut first = BufReader::new(TcpStream::connect(addr).unwrap());
+        let mut second = BufReader::new(TcpStream::connect(addr).unwrap());
+
+        assert_eq!(request(&mut first, "SELECT 1\n"), "\n");
+        assert_eq!(request(&mut first, "SET metanoia 1\n"), "\n");
+        assert_eq!(request(&mut second, "EXISTS metanoia\n"), "0\n");
+        assert_eq!(request(&mut second, "SELECT 1\n"), "\n");
+        assert_eq!(request(&mut second, "GET metanoia\n"), "1\n");
+    }
+
+    #[test]
+    fn max_clients_rejected() {
+        let addr = spawn_server(Config { max_clients: 1 });
+
+        let mut first = BufReader::new(TcpStream::connect(addr).unwrap());
+        // Make sure the first client occupies the only slot
+        assert_eq!(request(&mut first, "EXISTS nothing\n"), "0\n");
+
+        let mut second = BufReader::new(TcpStream::connect(addr).unwrap());
+        let mut line = String::new();
+        second.read_line(&mut line).unwrap();
+        assert_eq!(line, format!("[Error]: {}\n", ConnectionError::MaxClients));
+
+        // The slot is freed once the first client leaves
+        drop(first);
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let mut third = BufReader::new(TcpStream::connect(addr).unwrap());
+        assert_eq!(request(&mut third, "EXISTS nothing\n"), "0\n");
+    }
+
+    #[test]
+    fn resp_client() {
+        let addr = spawn_server(Config::default());
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"*3\r\n$3\r\nSET\r\n$8\r\ngreeting\r\n$11\r\nhello world\r\n").unwrap();
+        stream.write_all(b"*2\r\n$3\r\nGET\r\n$8\r\ngreeting\r\n").unwrap();
+        stream.write_all(b"*2\r\n$3\r\nGET\r\n$7\r\nmissing\r\n").unwrap();
+        // Only reads answer nil for a missing key
+        stream.write_all(b"*3\r\n$6\r\nRENAME\r\n$7\r\nmissing\r\n$2\r\nto\r\n").unwrap();
+        stream.write_all(b"*3\r\n$6\r\nEXPIRE\r\n$7\r\nmissing\r\n$2\r\n10\r\n").unwrap();
+        // Inline commands still work, but are answered in RESP
+        stream.write_all(b"EXISTS greeting\r\n").unwrap();
+        stream.write_all(b"*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n").unwrap();
+        stream.write_all(b"*2\r\n$3\r\nGET\r\n$7\r\nmissing\r\n").unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let mut hello = Vec::new();
+        resp::hello(Protocol::Resp3).encode(Protocol::Resp3, &mut hello);
+        let expected = format!(
+            "+OK\r\n$11\r\nhello world\r\n$-1\r\n-ERR no such key\r\n:0\r\n:1\r\n{}_\r\n",
+            String::from_utf8(hello).unwrap()
+        );
+        assert_eq!(response, expected);
+    }
+
+    #[test]
+    fn resp_protocol_error_closes() {
+        let addr = spawn_server(Config::default());
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"*1\r\n:12\r\n").unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "-ERR Protocol error.\r\n");
+    }
+
+    #[test]
+    fn long_inline_commands() {
+        let addr = spawn_server(Config::default());
+
+        // Past a RESP header line, well within an inline command
+        let mut text = BufReader::new(TcpStream::connect(addr).unwrap());
+        let value = "v".repeat(70 * 1024);
+        assert_eq!(request(&mut text, &format!("SET big {value}\n")), "\n");
+        assert_eq!(request(&mut text, "STRLEN big\n"), format!("{}\n", value.len()));
+
+        // Too long is told in text before hanging up, nothing is left unread so the reply isn't reset
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let command = format!("SET bigger {}", "v".repeat(MAX_INLINE_LEN));
+        stream.write_all(&command.as_bytes()[..MAX_INLINE_LEN]).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert_eq!(response, format!("[Error]: {}\n", ParseError::LineTooLong));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET \xff\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "[Error]: Protocol error.\n");
+    }
+
+    #[test]
+    fn pipelined_commands() {
+        let addr = spawn_server(Config::default());
+
+        let mut commands = String::new();
+        for i in 0..1000 {
+            commands.push_str(&format!("SET key{i} {i}\n"));
+        }
+        commands.push_str("GET key999\nINCR key41\nGET key41\n");
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(commands.as_bytes()).unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        // Replies come back in order, one per command
+        let lines: Vec<&str> = response.lines().collect();
+        assert_eq!(lines.len(), 1003);
+        assert_eq!(&lines[1000..], ["999", "42", "42"]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_server() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let listener = runtime.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let dict = Dictionary::new();
+            runtime.block_on(run_async(&dict, &listener, &Config::default(), &Arc::new(Shutdown::default()))).unwrap();
+        });
+
+        let mut text = BufReader::new(TcpStream::connect(addr).unwrap());
+        assert_eq!(request(&mut text, "SET metanoia 19\n"), "\n");
+
+        // Same map, other client, other protocol
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"*2\r\n$3\r\nGET\r\n$8\r\nmetanoia\r\n").unwrap();
+        stream.write_all(b"*2\r\n$4\r\nINCR\r\n$8\r\nmetanoia\r\n").unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "$2\r\n19\r\n:20\r\n");
+    }
+
+    #[test]
+    fn ipv6() {
+        let listener = bind(IpAddr::V6(std::net::Ipv6Addr::LOCALHOST), Some(0));
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            run(&Dictionary::new(), &listener, &Config::default(), &Arc::new(Shutdown::default())).unwrap();
+        });
+
+        let mut client = BufReader::new(TcpStream::connect(addr).unwrap());
+        assert_eq!(request(&mut client, "PING\n"), "PONG\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_socket() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("kvdis-test-{}.sock", std::process::id()));
+        let listener = bind_unix(&path, Some(0o600));
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        thread::spawn(move || {
+            run(&Dictionary::new(), &listener, &Config::default(), &Arc::new(Shutdown::default())).unwrap();
+        });
+
+        let mut client = BufReader::new(UnixStream::connect(&path).unwrap());
+        assert_eq!(request(&mut client, "SET metanoia 19\n"), "\n");
+        assert_eq!(request(&mut client, "GET metanoia\n"), "19\n");
+
+        // A stale socket file does not prevent binding again
+        drop(client);
+        let _listener = bind_unix(&path, None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn shutdown_command() {
+        let listener = bind(DEFAULT_BIND, Some(0));
+        let addr = listener.local_addr().unwrap();
+        let shutdown = Arc::new(Shutdown::default());
+        let server = {
+            let shutdown = Arc::clone(&shutdown);
+            thread::spawn(move || {
+                run(&Dictionary::new(), &listener, &Config::default(), &shutdown).unwrap();
+            })
+        };
+
+        let mut idle = BufReader::new(TcpStream::connect(addr).unwrap());
+        assert_eq!(request(&mut idle, "PING\n"), "PONG\n");
+
+        // Commands after SHUTDOWN are not run
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"PING\nSHUTDOWN NOSAVE\nPING\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "PONG\n\n");
+
+        // The server returns and hangs up on idle clients
+        server.join().unwrap();
+        assert!(shutdown.is_requested());
+        assert!(!shutdown.should_save());
+        let mut rest = String::new();
+        idle.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn shutdown_drains_clients() {
+        let listener = bind(DEFAULT_BIND, Some(0));
+        let addr = listener.local_addr().unwrap();
+        let shutdown = Arc::new(Shutdown::default());
+        let server = {
+            let shutdown = Arc::clone(&shutdown);
+            thread::spawn(move || {
+                run(&Dictionary::new(), &listener, &Config::default(), &shutdown).unwrap();
+            })
+        };
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        assert_eq!(request(&mut reader, "SET metanoia 19\n"), "\n");
+
+        // As if a signal came in
+        shutdown.request(true);
+        server.join().unwrap();
+
+        // Further commands go nowhere
+        let _ = stream.write_all(b"GET metanoia\n");
+        let mut rest = String::new();
+        let _ = reader.read_to_string(&mut rest);
+        assert_eq!(rest, "");
+        assert!(shutdown.should_save());
+    }
+}
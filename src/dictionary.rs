@@ -1,23 +1,141 @@
-use std::{collections::HashMap, path::PathBuf, sync::{Arc, Mutex}, thread, time::{Duration, SystemTime}};
+use std::{fmt::Display, hash::{BuildHasher, Hasher, RandomState}, path::PathBuf, str::FromStr, sync::{atomic::Ordering, Arc, Mutex, MutexGuard}, thread::{self, JoinHandle}, time::{Duration, SystemTime}};
 
-use crate::{command::{Command, CommandResult}, errors::DictionaryError, persistence::{Serializer, DEFAULT_STORAGE_PATH}};
+use crate::{command::{Command, CommandResult, ExpireCondition, GetExOption, ScanOptions, SetCondition, SetExpiration, SetOptions, Ttl}, errors::DictionaryError, expiry::ExpiryStats, glob, keyspace::Keyspace, persistence::{Serializer, DEFAULT_STORAGE_PATH}, value::Value};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Entry {
-    pub value: String,
+    pub value: Value,
     pub expiration: Option<SystemTime>
 }
 
-#[derive(Debug)]
+impl Entry {
+    /// Without expiration
+    pub fn new(value: impl Into<Value>) -> Self {
+        Entry { value: value.into(), expiration: None }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expiration.is_some_and(|expiration| expiration <= SystemTime::now())
+    }
+
+    /// As filtered on by SCAN's TYPE
+    pub fn type_name(&self) -> &'static str {
+        self.value.type_name()
+    }
+}
+
+/// Redis style inclusive range over a sequence of `len` items,
+/// negative indexes count from the end and out of range ones are clamped
+/// # Returns
+/// The first and last index, None if the range is empty
+pub(crate) fn range_bounds(start: i64, end: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let from_end = |index: i64| if index < 0 { (len + index).max(0) } else { index };
+    let (start, end) = (from_end(start), from_end(end).min(len - 1));
+
+    (start <= end).then_some((start as usize, end as usize))
+}
+
+/// # Returns
+/// - `lifetime` from now
+/// - If that's past what `SystemTime` can hold, Err(DictionaryError::InvalidExpireTime)
+pub(crate) fn deadline(lifetime: Duration) -> Result<SystemTime, DictionaryError> {
+    SystemTime::now().checked_add(lifetime).ok_or(DictionaryError::InvalidExpireTime)
+}
+
+/// A random index below `len`, which must not be 0
+pub(crate) fn random_index(len: usize) -> usize {
+    RandomState::new().build_hasher().finish() as usize % len
+}
+
+/// Longest value APPEND and SETRANGE may produce, in bytes
+pub const MAX_VALUE_SIZE: usize = 512 * 1024 * 1024;
+
+pub const DEFAULT_DATABASES: usize = 16;
+
+/// One logical database, SELECT picks which one commands run on
+pub type Database = Arc<Mutex<Keyspace>>;
+type DatabaseGuard<'a> = MutexGuard<'a, Keyspace>;
+
+/// Cloning a `Dictionary` is cheap, clones share the same databases
+/// but each has its own selected one, see `select`
+#[derive(Debug, Clone)]
 pub struct Dictionary {
-    pub map: Arc<Mutex<HashMap<String, Entry>>>
+    /// The selected database
+    pub map: Database,
+    pub databases: Arc<Vec<Database>>,
+    /// Index of `map` in `databases`
+    selected: usize,
+    /// SAVE/LOAD threads, see `join_background_jobs`
+    jobs: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    pub expiry_stats: Arc<ExpiryStats>,
+    /// Debugging aid, see `report_expired`
+    report_expired: bool
+}
+
+impl Default for Dictionary {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-/// Treats everything as a String,
-/// `incr` and `decr` operations work on parsable i64 bound Strings
+/// Values are typed, see `Value`, commands of other types live in their own modules.
+/// `incr` and `decr` operations work on parsable i64 bound Strings,
+/// `incr_by_float` on parsable f64 bound Strings
 impl Dictionary {
     pub fn new() -> Self {
-        Dictionary { map: Arc::new(Mutex::new(HashMap::new())) }
+        Self::with_databases(DEFAULT_DATABASES)
+    }
+
+    /// Database 0 is selected
+    /// # Panics
+    /// If `count` is zero
+    pub fn with_databases(count: usize) -> Self {
+        assert!(count > 0, "A dictionary needs at least one database");
+
+        let databases: Vec<Database> = (0..count).map(|_| Arc::new(Mutex::new(Keyspace::new()))).collect();
+        Dictionary {
+            map: Arc::clone(&databases[0]),
+            databases: Arc::new(databases),
+            selected: 0,
+            jobs: Arc::new(Mutex::new(Vec::new())),
+            expiry_stats: Arc::new(ExpiryStats::default()),
+            report_expired: false
+        }
+    }
+
+    /// Expired keys are deleted as soon as they are touched and behave as if they never existed.
+    /// With `report` set, the operation that deletes one fails with `DictionaryError::IsExpired`
+    /// instead of `DictionaryError::DoesNotExist`.
+    pub fn report_expired(mut self, report: bool) -> Self {
+        self.report_expired = report;
+        self
+    }
+
+    /// Looks `key` up in the locked `map`, deleting it if it has expired
+    /// # Returns
+    /// - If found and alive, Ok(&mut Entry)
+    /// - If it does not exist or just expired, Err(DictionaryError::DoesNotExist)
+    /// - If it just expired and expirations are reported, Err(DictionaryError::IsExpired)
+    pub(crate) fn live_entry<'a>(&self, map: &'a mut Keyspace, key: &str) -> Result<&'a mut Entry, DictionaryError> {
+        if map.get(key).is_some_and(Entry::is_expired) {
+            map.remove(key);
+            self.expiry_stats.expired_keys.fetch_add(1, Ordering::Relaxed);
+
+            return match self.report_expired {
+                true => Err(DictionaryError::IsExpired),
+                false => Err(DictionaryError::DoesNotExist)
+            };
+        }
+
+        map.get_mut(key).ok_or(DictionaryError::DoesNotExist)
+    }
+
+    /// Like `live_entry`, inserting `default()` when the key is missing or expired.
+    /// An existing entry is kept whatever its type, callers check it.
+    pub(crate) fn live_entry_or_insert<'a>(&self, map: &'a mut Keyspace, key: &str, default: impl FnOnce() -> Value) -> &'a mut Entry {
+        let _ = self.live_entry(map, key);
+        map.get_or_insert_with(key, || Entry::new(default()))
     }
 
     /// Runs a `Command`
@@ -27,36 +145,107 @@ impl Dictionary {
     pub fn run_headless(&mut self, command: Command) -> Result<CommandResult, DictionaryError> {
         use Command::*;
         match command {
-            Set(key, value) => {
-                self.set(key, Entry { value, expiration: None });
-                Ok(CommandResult::Set)
+            Set(key, value, options) => {
+                let (written, previous) = self.set_with(key, value, options)?;
+                match options.get {
+                    true => Ok(CommandResult::SetGet(written, previous)),
+                    false => Ok(CommandResult::Set(written))
+                }
             },
             Get(key) => {
-                return Ok(CommandResult::Get(self.get(&key)?));
+                Ok(CommandResult::Get(self.get(&key)?))
+            },
+            GetDel(key) => {
+                Ok(CommandResult::Get(self.getdel(&key)?))
+            },
+            GetEx(key, option) => {
+                Ok(CommandResult::Get(self.getex(&key, option)?))
+            },
+            Append(key, value) => {
+                Ok(CommandResult::Append(self.append(&key, &value)?))
+            },
+            Strlen(key) => {
+                Ok(CommandResult::Strlen(self.strlen(&key)?))
+            },
+            GetRange(key, start, end) => {
+                Ok(CommandResult::Get(self.getrange(&key, start, end)?))
             },
-            Del(key) => {
-                self.del(&key)?;
-                Ok(CommandResult::Del)
+            SetRange(key, offset, value) => {
+                Ok(CommandResult::SetRange(self.setrange(&key, offset, &value)?))
             },
-            Exists(key) => {
-                Ok(CommandResult::Exists(self.exists(&key)))
+            Del(keys) => {
+                Ok(CommandResult::Del(self.del_many(&keys)))
             },
-            Expire(key, lifetime) => {
-                self.expire(&key, lifetime)?;
-                Ok(CommandResult::Expire)
+            Exists(keys) => {
+                Ok(CommandResult::Exists(self.count_existing(&keys)))
+            },
+            MGet(keys) => {
+                Ok(CommandResult::MGet(self.mget(&keys)))
+            },
+            MSet(pairs) => {
+                self.mset(pairs);
+                Ok(CommandResult::MSet)
+            },
+            MSetNx(pairs) => {
+                Ok(CommandResult::MSetNx(self.msetnx(pairs)))
+            },
+            Rename(from, to) => {
+                self.rename(&from, to)?;
+                Ok(CommandResult::Rename)
+            },
+            RenameNx(from, to) => {
+                Ok(CommandResult::RenameNx(self.renamenx(&from, to)?))
+            },
+            Copy(from, to, replace) => {
+                Ok(CommandResult::Copy(self.copy(&from, to, replace)))
+            },
+            // Missing keys are not an error, like in Redis
+            Expire(key, lifetime, condition) => {
+                match self.expire_at(&key, deadline(lifetime)?, condition) {
+                    Err(DictionaryError::DoesNotExist) => Ok(CommandResult::Expire(false)),
+                    result => Ok(CommandResult::Expire(result?))
+                }
+            },
+            ExpireAt(key, at, condition) => {
+                match self.expire_at(&key, at, condition) {
+                    Err(DictionaryError::DoesNotExist) => Ok(CommandResult::Expire(false)),
+                    result => Ok(CommandResult::Expire(result?))
+                }
             },
             Incr(key) => {
-                self.incr(&key)?;
-                Ok(CommandResult::Incr)
+                Ok(CommandResult::Incr(self.incr_by(&key, 1)?))
             },
             Decr(key) => {
-                self.decr(&key)?;
-                Ok(CommandResult::Decr)
+                Ok(CommandResult::Decr(self.decr_by(&key, 1)?))
+            },
+            IncrBy(key, by) => {
+                Ok(CommandResult::Incr(self.incr_by(&key, by)?))
+            },
+            DecrBy(key, by) => {
+                Ok(CommandResult::Decr(self.decr_by(&key, by)?))
+            },
+            IncrByFloat(key, by) => {
+                Ok(CommandResult::IncrByFloat(self.incr_by_float(&key, by)?))
             },
             Clear => {
-                self.clear();
+                self.clear_all();
                 Ok(CommandResult::Clear)
             },
+            FlushDb => {
+                self.clear();
+                Ok(CommandResult::FlushDb)
+            },
+            Select(index) => {
+                self.select(index)?;
+                Ok(CommandResult::Select)
+            },
+            SwapDb(first, second) => {
+                self.swapdb(first, second)?;
+                Ok(CommandResult::SwapDb)
+            },
+            Move(key, index) => {
+                Ok(CommandResult::Move(self.move_key(&key, index)?))
+            },
             Save => {
                 self.save(PathBuf::from(DEFAULT_STORAGE_PATH));
                 Ok(CommandResult::Save)
@@ -64,6 +253,238 @@ impl Dictionary {
             Load => {
                 self.load(PathBuf::from(DEFAULT_STORAGE_PATH));
                 Ok(CommandResult::Load)
+            },
+            Ping(message) => {
+                Ok(CommandResult::Ping(message))
+            },
+            // The protocol itself is switched by the connection
+            Hello(version) => {
+                Ok(CommandResult::Hello(version))
+            },
+            // Stopping the server is up to the connection
+            Shutdown(save) => {
+                Ok(CommandResult::Shutdown(save))
+            },
+            Info => {
+                Ok(CommandResult::Info(self.info()))
+            },
+            Keys(pattern) => {
+                Ok(CommandResult::Keys(self.keys(&pattern)))
+            },
+            Scan(cursor, options) => {
+                let (cursor, keys) = self.scan(cursor, &options);
+                Ok(CommandResult::Scan(cursor, keys))
+            },
+            DbSize => {
+                Ok(CommandResult::DbSize(self.dbsize()))
+            },
+            RandomKey => {
+                Ok(CommandResult::RandomKey(self.random_key()))
+            },
+            Ttl(key) => {
+                Ok(CommandResult::Ttl(self.ttl(&key)?))
+            },
+            Pttl(key) => {
+                Ok(CommandResult::Pttl(self.ttl(&key)?))
+            },
+            Persist(key) => {
+                Ok(CommandResult::Persist(self.persist(&key)?))
+            },
+            Type(key) => {
+                Ok(CommandResult::Type(self.type_name(&key).to_string()))
+            },
+            Push(key, values, end) => {
+                Ok(CommandResult::Push(self.push(&key, values, end)?))
+            },
+            Pop(key, None, end) => {
+                Ok(CommandResult::Get(self.pop(&key, 1, end)?.remove(0).into_bytes()))
+            },
+            Pop(key, Some(count), end) => {
+                Ok(CommandResult::List(self.pop(&key, count, end)?))
+            },
+            LRange(key, start, end) => {
+                Ok(CommandResult::List(self.lrange(&key, start, end)?))
+            },
+            LLen(key) => {
+                Ok(CommandResult::LLen(self.llen(&key)?))
+            },
+            LIndex(key, index) => {
+                Ok(CommandResult::LIndex(self.lindex(&key, index)?))
+            },
+            LTrim(key, start, end) => {
+                self.ltrim(&key, start, end)?;
+                Ok(CommandResult::LTrim)
+            },
+            HSet(key, pairs) => {
+                Ok(CommandResult::HSet(self.hset(&key, pairs)?))
+            },
+            HMSet(key, pairs) => {
+                self.hset(&key, pairs)?;
+                Ok(CommandResult::HMSet)
+            },
+            HSetNx(key, field, value) => {
+                Ok(CommandResult::HSetNx(self.hsetnx(&key, field, value)?))
+            },
+            HGet(key, field) => {
+                Ok(CommandResult::HGet(self.hget(&key, &field)?))
+            },
+            HMGet(key, fields) => {
+                let values = self.hmget(&key, &fields)?;
+                Ok(CommandResult::MGet(values.into_iter().map(|value| value.map(String::into_bytes)).collect()))
+            },
+            HDel(key, fields) => {
+                Ok(CommandResult::HDel(self.hdel(&key, &fields)?))
+            },
+            HExists(key, field) => {
+                Ok(CommandResult::HExists(self.hget(&key, &field)?.is_some()))
+            },
+            HLen(key) => {
+                Ok(CommandResult::HLen(self.hlen(&key)?))
+            },
+            HKeys(key) => {
+                Ok(CommandResult::List(self.hgetall(&key)?.into_iter().map(|(field, _)| field).collect()))
+            },
+            HVals(key) => {
+                Ok(CommandResult::List(self.hgetall(&key)?.into_iter().map(|(_, value)| value).collect()))
+            },
+            HGetAll(key) => {
+                Ok(CommandResult::HGetAll(self.hgetall(&key)?))
+            },
+            HIncrBy(key, field, by) => {
+                Ok(CommandResult::Incr(self.hincr_by(&key, &field, by)?))
+            },
+            HIncrByFloat(key, field, by) => {
+                Ok(CommandResult::IncrByFloat(self.hincr_by_float(&key, &field, by)?))
+            },
+            HScan(key, cursor, options) => {
+                let (cursor, fields) = self.hscan(&key, cursor, &options)?;
+                Ok(CommandResult::Scan(cursor, fields))
+            },
+            SAdd(key, members) => {
+                Ok(CommandResult::SAdd(self.sadd(&key, members)?))
+            },
+            SRem(key, members) => {
+                Ok(CommandResult::SRem(self.srem(&key, &members)?))
+            },
+            SMembers(key) => {
+                Ok(CommandResult::List(self.smembers(&key)?))
+            },
+            SIsMember(key, member) => {
+                Ok(CommandResult::SIsMember(self.sismember(&key, &member)?))
+            },
+            SCard(key) => {
+                Ok(CommandResult::SCard(self.scard(&key)?))
+            },
+            SetOp(operation, keys) => {
+                Ok(CommandResult::List(self.set_operation(operation, &keys)?))
+            },
+            SetOpStore(operation, destination, keys) => {
+                Ok(CommandResult::SetOpStore(self.set_operation_store(operation, destination, &keys)?))
+            },
+            SRandMember(key, None) => {
+                Ok(CommandResult::Member(self.srandmember(&key, 1)?.pop()))
+            },
+            SRandMember(key, Some(count)) => {
+                Ok(CommandResult::List(self.srandmember(&key, count)?))
+            },
+            SPop(key, None) => {
+                Ok(CommandResult::Member(self.spop(&key, 1)?.pop()))
+            },
+            SPop(key, Some(count)) => {
+                Ok(CommandResult::List(self.spop(&key, count)?))
+            },
+            ZAdd(key, pairs, options) => {
+                Ok(CommandResult::ZAdd(self.zadd(&key, pairs, options)?))
+            },
+            ZAddIncr(key, by, member, options) => {
+                Ok(CommandResult::Score(self.zadd_incr(&key, by, member, options)?))
+            },
+            ZIncrBy(key, by, member) => {
+                Ok(CommandResult::IncrByFloat(self.zincr_by(&key, by, member)?))
+            },
+            ZRange(key, options) => {
+                let members = self.zrange(&key, &options)?;
+                match options.with_scores {
+                    true => Ok(CommandResult::Scored(members)),
+                    false => Ok(CommandResult::List(members.into_iter().map(|(member, _)| member).collect()))
+                }
+            },
+            ZRank(key, member) => {
+                Ok(CommandResult::ZRank(self.zrank(&key, &member)?))
+            },
+            ZScore(key, member) => {
+                Ok(CommandResult::Score(self.zscore(&key, &member)?))
+            },
+            ZCard(key) => {
+                Ok(CommandResult::ZCard(self.zcard(&key)?))
+            },
+            ZRem(key, members) => {
+                Ok(CommandResult::ZRem(self.zrem(&key, &members)?))
+            },
+            ZPop(key, count, end) => {
+                Ok(CommandResult::Scored(self.zpop(&key, count, end)?))
+            },
+            XAdd(key, id, fields, max_len) => {
+                Ok(CommandResult::XAdd(self.xadd(&key, id, fields, max_len)?))
+            },
+            XLen(key) => {
+                Ok(CommandResult::XLen(self.xlen(&key)?))
+            },
+            XRange(key, start, end, count, rev) => {
+                Ok(CommandResult::Entries(self.xrange(&key, start, end, count, rev)?))
+            },
+            XRead(streams, count) => {
+                Ok(CommandResult::XRead(self.xread(&streams, count)?))
+            },
+            XReadGroup(group, consumer, streams, count) => {
+                Ok(CommandResult::XRead(self.xreadgroup(&group, &consumer, &streams, count)?))
+            },
+            XGroupCreate(key, group, start, make_stream) => {
+                self.xgroup_create(&key, group, start, make_stream)?;
+                Ok(CommandResult::XGroupCreate)
+            },
+            XGroupDestroy(key, group) => {
+                Ok(CommandResult::XGroupDestroy(self.xgroup_destroy(&key, &group)?))
+            },
+            XAck(key, group, ids) => {
+                Ok(CommandResult::XAck(self.xack(&key, &group, &ids)?))
+            },
+            XPending(key, group) => {
+                Ok(CommandResult::XPending(self.xpending(&key, &group)?))
+            },
+            XPendingRange(key, group, start, end, count, consumer) => {
+                Ok(CommandResult::XPendingRange(self.xpending_range(&key, &group, start, end, count, consumer.as_deref())?))
+            },
+            XClaim(key, group, consumer, min_idle, ids) => {
+                Ok(CommandResult::Entries(self.xclaim(&key, &group, &consumer, min_idle, &ids)?))
+            },
+            SetBit(key, offset, bit) => {
+                Ok(CommandResult::Bit(self.setbit(&key, offset, bit)?))
+            },
+            GetBit(key, offset) => {
+                Ok(CommandResult::Bit(self.getbit(&key, offset)?))
+            },
+            BitCount(key, range) => {
+                Ok(CommandResult::BitCount(self.bitcount(&key, range)?))
+            },
+            BitPos(key, bit, range) => {
+                Ok(CommandResult::BitPos(self.bitpos(&key, bit, range)?))
+            },
+            BitOp(operation, destination, keys) => {
+                Ok(CommandResult::BitOp(self.bitop(operation, destination, &keys)?))
+            },
+            BitField(key, operations) => {
+                Ok(CommandResult::BitField(self.bitfield(&key, &operations)?))
+            },
+            PfAdd(key, elements) => {
+                Ok(CommandResult::PfAdd(self.pfadd(&key, &elements)?))
+            },
+            PfCount(keys) => {
+                Ok(CommandResult::PfCount(self.pfcount(&keys)?))
+            },
+            PfMerge(destination, sources) => {
+                self.pfmerge(&destination, &sources)?;
+                Ok(CommandResult::PfMerge)
             }
         }
     }
@@ -80,125 +501,632 @@ impl Dictionary {
         map.insert(key, value);
     }
 
+    /// SET with options, checking the condition and writing under the same lock
     /// # Returns
-    /// - If found, Ok(String)
-    /// - If expired, Err(CommandError::IsExpired)
-    /// - If it does not exist, Err(CommandError::DoesNotExist)
-    pub fn get(&self, key: &str) -> Result<String, DictionaryError> {
-        let map = self.map.lock().unwrap();
-        match map.get(key) {
-            Some(e) => {
-                // Check if expired
-                if let Some(lifetime) = e.expiration {
-                    if lifetime <= SystemTime::now() {
-                        return Err(DictionaryError::IsExpired);
-                    }
-                }
+    /// Whether the value was written, and the previous value
+    pub fn set_with(&mut self, key: String, value: impl Into<Vec<u8>>, options: SetOptions) -> Result<(bool, Option<Vec<u8>>), DictionaryError> {
+        // Resolved before locking, None keeps the previous expiration
+        let expiration = match options.expiration {
+            None => Some(None),
+            Some(SetExpiration::In(lifetime)) => Some(Some(deadline(lifetime)?)),
+            Some(SetExpiration::At(at)) => Some(Some(at)),
+            Some(SetExpiration::Keep) => None
+        };
+
+        let mut map = self.map.lock().unwrap();
+        // Expired keys count as absent, any type is overwritten
+        let previous = self.live_entry(&mut map, &key).ok().map(|entry| (entry.value.as_bytes().cloned(), entry.expiration));
+        if options.get && let Some((Err(e), _)) = previous {
+            return Err(e);
+        }
+
+        let allowed = match options.condition {
+            None => true,
+            Some(SetCondition::Nx) => previous.is_none(),
+            Some(SetCondition::Xx) => previous.is_some()
+        };
+        let (previous, previous_expiration) = previous.unzip();
+        let previous = previous.and_then(Result::ok);
+        if !allowed {
+            return Ok((false, previous));
+        }
+
+        let expiration = expiration.unwrap_or(previous_expiration.flatten());
+        map.insert(key, Entry { value: Value::from(value.into()), expiration });
+
+        Ok((true, previous))
+    }
+
+    /// # Returns
+    /// - If found, Ok(bytes)
+    /// - If it does not exist or has expired, Err(DictionaryError::DoesNotExist)
+    pub fn get(&self, key: &str) -> Result<Vec<u8>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let entry = self.live_entry(&mut map, key)?;
+
+        Ok(entry.value.as_bytes()?.clone())
+    }
+
+    pub fn getdel(&mut self, key: &str) -> Result<Vec<u8>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let value = self.live_entry(&mut map, key)?.value.as_bytes()?.clone();
+
+        map.remove(key);
+        Ok(value)
+    }
+
+    /// GET, then changes the expiration as `option` says
+    pub fn getex(&mut self, key: &str, option: Option<GetExOption>) -> Result<Vec<u8>, DictionaryError> {
+        // Resolved before locking, None keeps the expiration
+        let expiration = match option {
+            None | Some(GetExOption::Expire(SetExpiration::Keep)) => None,
+            Some(GetExOption::Persist) => Some(None),
+            Some(GetExOption::Expire(SetExpiration::In(lifetime))) => Some(Some(deadline(lifetime)?)),
+            Some(GetExOption::Expire(SetExpiration::At(at))) => Some(Some(at))
+        };
+
+        let mut map = self.map.lock().unwrap();
+        let entry = self.live_entry(&mut map, key)?;
+        let value = entry.value.as_bytes()?.clone();
 
-                Ok(e.value.clone())
+        let Some(expiration) = expiration else {
+            return Ok(value);
+        };
+        // Like EXPIREAT, a time in the past deletes the key
+        match expiration {
+            Some(expiration) if expiration <= SystemTime::now() => {
+                map.remove(key);
             },
-            None => Err(DictionaryError::DoesNotExist)
+            _ => {
+                map.set_expiration(key, expiration);
+            }
         }
+
+        Ok(value)
     }
 
-    /// del does not have to check for expiries, because in the future
-    /// i might implement lazy deletion in the background.
-    pub fn del(&mut self, key: &str) -> Result<(), DictionaryError> {
+    /// Missing keys are created, the expiration is kept
+    /// # Returns
+    /// The new length in bytes
+    pub fn append(&mut self, key: &str, value: &[u8]) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let len = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_bytes()?.len(),
+            Err(_) => 0
+        };
+        if len + value.len() > MAX_VALUE_SIZE {
+            return Err(DictionaryError::ValueTooLarge);
+        }
+
+        let current = map.get_or_insert_with(key, || Entry::new(String::new())).value.as_bytes_mut()?;
+        current.extend_from_slice(value);
+        Ok(current.len())
+    }
+
+    /// # Returns
+    /// The length in bytes, 0 for a missing key
+    pub fn strlen(&self, key: &str) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        match self.live_entry(&mut map, key) {
+            Ok(entry) => Ok(entry.value.as_bytes()?.len()),
+            Err(DictionaryError::DoesNotExist) => Ok(0),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Redis style substring, `start` and `end` are inclusive byte offsets,
+    /// negative ones count from the end and out of range ones are clamped
+    /// # Returns
+    /// - The substring, empty for a missing key or an empty range
+    /// - If the range cuts through a character of a text string, Err(DictionaryError::NotCharBoundary)
+    pub fn getrange(&self, key: &str, start: i64, end: i64) -> Result<Vec<u8>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let value = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_bytes()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(Vec::new()),
+            Err(e) => return Err(e)
+        };
+
+        let Some((start, end)) = range_bounds(start, end, value.len()) else {
+            return Ok(Vec::new());
+        };
+        // Binary strings have no characters to cut through
+        if let Ok(text) = std::str::from_utf8(value)
+            && !(text.is_char_boundary(start) && text.is_char_boundary(end + 1)) {
+            return Err(DictionaryError::NotCharBoundary);
+        }
+        Ok(value[start..=end].to_vec())
+    }
+
+    /// Overwrites `key` from the byte `offset` on, padding with NUL characters past the end.
+    /// Missing keys are created unless `value` is empty, the expiration is kept.
+    /// # Returns
+    /// - The new length in bytes
+    /// - If `value` would cut through a character, Err(DictionaryError::NotCharBoundary)
+    pub fn setrange(&mut self, key: &str, offset: usize, value: &[u8]) -> Result<usize, DictionaryError> {
         let mut map = self.map.lock().unwrap();
-        match map.remove(key) {
-            Some(_) => Ok(()),
-            None => Err(DictionaryError::DoesNotExist)
+        let current = match self.live_entry(&mut map, key) {
+            Ok(entry) => Some(entry.value.as_bytes_mut()?),
+            Err(_) => None
+        };
+        let len = current.as_ref().map_or(0, |current| current.len());
+        if value.is_empty() {
+            return Ok(len);
+        }
+
+        let end = offset.checked_add(value.len()).filter(|end| *end <= MAX_VALUE_SIZE).ok_or(DictionaryError::ValueTooLarge)?;
+        // Binary strings have no characters to cut through
+        if let Some(Ok(current)) = current.as_deref().map(|current| std::str::from_utf8(current))
+            && !(current.is_char_boundary(offset.min(len)) && current.is_char_boundary(end.min(len))) {
+            return Err(DictionaryError::NotCharBoundary);
+        }
+
+        let current = match current {
+            Some(current) => current,
+            None => map.get_or_insert_with(key, || Entry::new(String::new())).value.as_bytes_mut()?
+        };
+        if offset > len {
+            current.resize(offset, 0);
         }
+        current.splice(offset..end.min(current.len()), value.iter().copied());
+
+        Ok(current.len())
+    }
+
+    pub fn del(&mut self, key: &str) -> Result<(), DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        self.live_entry(&mut map, key)?;
+
+        map.remove(key);
+        Ok(())
+    }
+
+    /// # Returns
+    /// The type of the value at `key`, "none" if there is none
+    pub fn type_name(&self, key: &str) -> &'static str {
+        let mut map = self.map.lock().unwrap();
+        self.live_entry(&mut map, key).map_or("none", |entry| entry.type_name())
     }
 
     pub fn exists(&self, key: &str) -> bool {
+        let mut map = self.map.lock().unwrap();
+        self.live_entry(&mut map, key).is_ok()
+    }
+
+    /// Deletes every key it can under one lock
+    /// # Returns
+    /// How many keys were deleted
+    pub fn del_many(&mut self, keys: &[String]) -> usize {
+        let mut map = self.map.lock().unwrap();
+        keys.iter().filter(|key| {
+            self.live_entry(&mut map, key).is_ok() && map.remove(key.as_str()).is_some()
+        }).count()
+    }
+
+    /// # Returns
+    /// How many of `keys` exist, repeated keys being counted again
+    pub fn count_existing(&self, keys: &[String]) -> usize {
+        let mut map = self.map.lock().unwrap();
+        keys.iter().filter(|key| self.live_entry(&mut map, key).is_ok()).count()
+    }
+
+    /// # Returns
+    /// One value per key, None for missing keys and keys that don't hold a string
+    pub fn mget(&self, keys: &[String]) -> Vec<Option<Vec<u8>>> {
+        let mut map = self.map.lock().unwrap();
+        keys.iter().map(|key| {
+            self.live_entry(&mut map, key).ok().and_then(|entry| entry.value.as_bytes().ok().cloned())
+        }).collect()
+    }
+
+    /// Sets every pair under one lock, dropping their expirations like SET
+    pub fn mset(&mut self, pairs: Vec<(String, Vec<u8>)>) {
+        let mut map = self.map.lock().unwrap();
+        for (key, value) in pairs {
+            map.insert(key, Entry::new(value));
+        }
+    }
+
+    /// Sets every pair under one lock, only if none of the keys exist
+    /// # Returns
+    /// Whether the pairs were set
+    pub fn msetnx(&mut self, pairs: Vec<(String, Vec<u8>)>) -> bool {
+        let mut map = self.map.lock().unwrap();
+        if pairs.iter().any(|(key, _)| self.live_entry(&mut map, key).is_ok()) {
+            return false;
+        }
+
+        for (key, value) in pairs {
+            map.insert(key, Entry::new(value));
+        }
+        true
+    }
+
+    pub fn expire(&mut self, key: &str, lifetime: Duration) -> Result<(), DictionaryError> {
+        self.expire_at(key, deadline(lifetime)?, None)?;
+        Ok(())
+    }
+
+    /// Sets the expiration of `key` to `at` if `condition` allows it.
+    /// A time in the past deletes the key right away.
+    /// # Returns
+    /// Whether the expiration was set
+    pub fn expire_at(&mut self, key: &str, at: SystemTime, condition: Option<ExpireCondition>) -> Result<bool, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let entry = self.live_entry(&mut map, key)?;
+
+        let allowed = match (condition, entry.expiration) {
+            (None, _) => true,
+            (Some(ExpireCondition::Nx), current) => current.is_none(),
+            (Some(ExpireCondition::Xx), current) => current.is_some(),
+            (Some(ExpireCondition::Gt), current) => current.is_some_and(|current| at > current),
+            (Some(ExpireCondition::Lt), current) => current.is_none_or(|current| at < current)
+        };
+        if !allowed {
+            return Ok(false);
+        }
+
+        if at <= SystemTime::now() {
+            map.remove(key);
+        } else {
+            map.set_expiration(key, Some(at));
+        }
+
+        Ok(true)
+    }
+
+    /// A missing key is not an error here, it's `Ttl::NoKey`
+    pub fn ttl(&self, key: &str) -> Result<Ttl, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let entry = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry,
+            Err(DictionaryError::DoesNotExist) => return Ok(Ttl::NoKey),
+            Err(e) => return Err(e)
+        };
+
+        Ok(match entry.expiration {
+            // Can't have gone by, live_entry just checked
+            Some(expiration) => Ttl::Remaining(expiration.duration_since(SystemTime::now()).unwrap_or_default()),
+            None => Ttl::NoExpiry
+        })
+    }
+
+    /// Removes the expiration of `key`
+    /// # Returns
+    /// Whether there was one to remove, a missing key simply has none
+    pub fn persist(&mut self, key: &str) -> Result<bool, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        match self.live_entry(&mut map, key) {
+            Ok(_) => Ok(map.set_expiration(key, None).is_some()),
+            Err(DictionaryError::DoesNotExist) => Ok(false),
+            Err(e) => Err(e)
+        }
+    }
+
+    pub fn incr(&mut self, key: &str) -> Result<i64, DictionaryError> {
+        self.incr_by(key, 1)
+    }
+
+    pub fn decr(&mut self, key: &str) -> Result<i64, DictionaryError> {
+        self.decr_by(key, 1)
+    }
+
+    /// # Returns
+    /// The new value
+    pub fn incr_by(&mut self, key: &str, by: i64) -> Result<i64, DictionaryError> {
+        self.update_number(key, |value: i64| value.checked_add(by))
+    }
+
+    /// # Returns
+    /// The new value
+    pub fn decr_by(&mut self, key: &str, by: i64) -> Result<i64, DictionaryError> {
+        self.update_number(key, |value: i64| value.checked_sub(by))
+    }
+
+    /// # Returns
+    /// The new value
+    pub fn incr_by_float(&mut self, key: &str, by: f64) -> Result<f64, DictionaryError> {
+        self.update_number(key, |value: f64| Some(value + by).filter(|value| value.is_finite()))
+    }
+
+    /// Replaces the number stored at `key` with `update` of it, under a single lock.
+    /// Missing keys start at 0 and the expiration is kept.
+    /// # Returns
+    /// - The new value
+    /// - If the value is not a number, Err(DictionaryError::NotANumber)
+    /// - If `update` gave up, Err(DictionaryError::Overflow)
+    fn update_number<T>(&mut self, key: &str, update: impl FnOnce(T) -> Option<T>) -> Result<T, DictionaryError>
+    where
+        T: FromStr + Display + Default + Copy
+    {
+        let mut map = self.map.lock().unwrap();
+        let current = match self.live_entry(&mut map, key) {
+            Ok(entry) => std::str::from_utf8(entry.value.as_bytes()?).ok()
+                .and_then(|value| value.parse::<T>().ok())
+                .ok_or(DictionaryError::NotANumber)?,
+            // Expired keys count as absent
+            Err(_) => T::default()
+        };
+
+        let new_value = update(current).ok_or(DictionaryError::Overflow)?;
+        match map.get_mut(key) {
+            Some(entry) => entry.value = Value::from(new_value.to_string()),
+            None => {
+                map.insert(key.to_string(), Entry::new(new_value.to_string()));
+            }
+        }
+
+        Ok(new_value)
+    }
+
+    /// Moves the value and expiration of `from` to `to`, replacing whatever `to` held
+    pub fn rename(&mut self, from: &str, to: String) -> Result<(), DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        self.live_entry(&mut map, from)?;
+
+        let entry = map.remove(from).unwrap();
+        map.insert(to, entry);
+        Ok(())
+    }
+
+    /// RENAME, only if `to` does not exist
+    /// # Returns
+    /// Whether the key was renamed
+    pub fn renamenx(&mut self, from: &str, to: String) -> Result<bool, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        self.live_entry(&mut map, from)?;
+        if self.live_entry(&mut map, &to).is_ok() {
+            return Ok(false);
+        }
+
+        let entry = map.remove(from).unwrap();
+        map.insert(to, entry);
+        Ok(true)
+    }
+
+    /// Copies the value and expiration of `from` to `to`,
+    /// which has to not exist unless `replace` is set
+    /// # Returns
+    /// Whether the key was copied
+    pub fn copy(&mut self, from: &str, to: String, replace: bool) -> bool {
+        let mut map = self.map.lock().unwrap();
+        let entry = match self.live_entry(&mut map, from) {
+            Ok(entry) => entry.clone(),
+            Err(_) => return false
+        };
+        if !replace && self.live_entry(&mut map, &to).is_ok() {
+            return false;
+        }
+
+        map.insert(to, entry);
+        true
+    }
+
+    /// Every live key matching the glob `pattern`, in no particular order
+    pub fn keys(&self, pattern: &str) -> Vec<String> {
         let map = self.map.lock().unwrap();
-        match map.get(key) {
-            None => false,
-            Some(value) => match value.expiration {
-                None => true,
-                Some(expiration) => {
-                    if SystemTime::now() <= expiration {
-                        true
-                    } else {
-                        false
-                    }
-                }
+        map.iter()
+            .filter(|(key, entry)| !entry.is_expired() && glob::matches(pattern, key))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// # Cursor
+    /// Keys are walked in the order of a hash that does not depend on the map,
+    /// the cursor being the hash of the next key to return.
+    /// So a full iteration returns every key that existed throughout it exactly once,
+    /// whatever was added or deleted in between, keys added meanwhile may or may not show up.
+    /// A call only goes over its batch, see `ScanMap`.
+    /// # Returns
+    /// The cursor to continue from, 0 once done, and the keys of this batch
+    /// that match `options.pattern` and `options.type_name`
+    pub fn scan(&self, cursor: u64, options: &ScanOptions) -> (u64, Vec<String>) {
+        let map = self.map.lock().unwrap();
+        let (next_cursor, batch) = map.scan(cursor, options.count);
+
+        let keys = batch.into_iter()
+            .filter(|(key, entry)| {
+                !entry.is_expired()
+                    && options.pattern.as_ref().is_none_or(|pattern| glob::matches(pattern, key))
+                    && options.type_name.as_ref().is_none_or(|type_name| entry.type_name() == type_name)
+            })
+            .map(|(key, _)| key.to_string())
+            .collect();
+
+        (next_cursor, keys)
+    }
+
+    /// Number of keys, including expired ones that have not been removed yet
+    pub fn dbsize(&self) -> usize {
+        self.map.lock().unwrap().len()
+    }
+
+    /// # Returns
+    /// A live key picked at random, None if there are none
+    pub fn random_key(&self) -> Option<String> {
+        let mut map = self.map.lock().unwrap();
+
+        while let Some((key, entry)) = map.random() {
+            if !entry.is_expired() {
+                return Some(key.clone());
             }
+
+            // Every pick gets rid of an expired key, so this ends
+            let key = key.clone();
+            map.remove(&key);
+            self.expiry_stats.expired_keys.fetch_add(1, Ordering::Relaxed);
         }
+
+        None
+    }
+
+    /// Server metrics as `(name, value)` pairs
+    pub fn info(&self) -> Vec<(String, String)> {
+        let keyspace: Vec<usize> = self.databases.iter().map(|db| db.lock().unwrap().len()).collect();
+        let stats = &self.expiry_stats;
+
+        let mut info = vec![
+            ("keys".to_string(), keyspace.iter().sum::<usize>().to_string()),
+            ("expired_keys".to_string(), stats.expired_keys.load(Ordering::Relaxed).to_string()),
+            ("expire_cycles".to_string(), stats.cycles.load(Ordering::Relaxed).to_string()),
+            ("expire_sampled_keys".to_string(), stats.sampled_keys.load(Ordering::Relaxed).to_string())
+        ];
+        // Only databases in use, as Redis does
+        for (index, keys) in keyspace.into_iter().enumerate().filter(|(_, keys)| *keys > 0) {
+            info.push((format!("db{index}"), format!("keys={keys}")));
+        }
+
+        info
+    }
+
+    /// Empties the selected database
+    pub fn clear(&mut self) {
+        let mut guard = self.map.lock().unwrap();
+        guard.clear();
     }
 
-    pub fn expire(&mut self, key: &str, lifetime: Duration) -> Result<(), DictionaryError> {
-        let mut map = self.map.lock().unwrap();
-        match map.get_mut(key) {
-            Some(entry) => {
-                entry.expiration = Some(SystemTime::now() + lifetime);
-                Ok(())
-            },
-            None => Err(DictionaryError::DoesNotExist)
+    /// Empties every database
+    pub fn clear_all(&mut self) {
+        for db in self.databases.iter() {
+            db.lock().unwrap().clear();
         }
     }
 
-    pub fn incr(&mut self, key: &str) -> Result<(), DictionaryError> {
-        let old_val = match self.get(&key).unwrap().parse::<i64>() {
-            Err(_) => {
-                return Err(DictionaryError::InvalidOperationType);
-            },
-            Ok(v) => v
-        };
-
-        let new_val = old_val + 1;
-        self.set(key.to_string(), Entry {
-            value: new_val.to_string(),
-            expiration: None
-        });
+    /// Makes commands on this `Dictionary` run on database `index`, clones are not affected
+    pub fn select(&mut self, index: usize) -> Result<(), DictionaryError> {
+        let db = self.databases.get(index).ok_or(DictionaryError::InvalidDatabase)?;
 
+        self.map = Arc::clone(db);
+        self.selected = index;
         Ok(())
     }
 
-    pub fn decr(&mut self, key: &str) -> Result<(), DictionaryError> {
-        let old_val = match self.get(&key).unwrap().parse::<i64>() {
-            Err(_) => {
-                return Err(DictionaryError::InvalidOperationType);
-            },
-            Ok(v) => v
-        };
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
 
-        let new_val = old_val - 1;
-        self.set(key.to_string(), Entry {
-            value: new_val.to_string(),
-            expiration: None
-        });
+    /// Swaps the contents of two databases,
+    /// clients having selected one of them see the other one from then on
+    pub fn swapdb(&mut self, first: usize, second: usize) -> Result<(), DictionaryError> {
+        if first.max(second) >= self.databases.len() {
+            return Err(DictionaryError::InvalidDatabase);
+        }
+        if first == second {
+            return Ok(());
+        }
 
+        let (mut first, mut second) = self.lock_pair(first, second);
+        std::mem::swap(&mut *first, &mut *second);
         Ok(())
     }
 
-    pub fn clear(&mut self) {
-        let mut guard = self.map.lock().unwrap();
-        guard.clear();
+    /// Moves `key` from the selected database to database `index`, expiration included
+    /// # Returns
+    /// Whether the key was moved, not if it already exists there or `index` is the selected database
+    pub fn move_key(&mut self, key: &str, index: usize) -> Result<bool, DictionaryError> {
+        if index >= self.databases.len() {
+            return Err(DictionaryError::InvalidDatabase);
+        }
+        if index == self.selected {
+            return Ok(false);
+        }
+
+        let (mut from, mut to) = self.lock_pair(self.selected, index);
+        if self.live_entry(&mut from, key).is_err() || self.live_entry(&mut to, key).is_ok() {
+            return Ok(false);
+        }
+
+        let entry = from.remove(key).unwrap();
+        to.insert(key.to_string(), entry);
+        Ok(true)
+    }
+
+    /// Locks two different databases, always in the same order so two callers can't deadlock
+    fn lock_pair(&self, first: usize, second: usize) -> (DatabaseGuard<'_>, DatabaseGuard<'_>) {
+        if first < second {
+            let first = self.databases[first].lock().unwrap();
+            (first, self.databases[second].lock().unwrap())
+        } else {
+            let second = self.databases[second].lock().unwrap();
+            (self.databases[first].lock().unwrap(), second)
+        }
     }
 
     pub fn save(&self, path: PathBuf) {
-        let serializer = Serializer::new(&self, path);
-        thread::spawn(move || {
+        let serializer = Serializer::new(self, path);
+        self.spawn_job(thread::spawn(move || {
             // TODO: error handling
             serializer.save_file_csv().unwrap();
-        });
+        }));
     }
 
     pub fn load(&self, path: PathBuf) {
-        let mut serializer = Serializer::new(&self, path);
-        thread::spawn(move || {
+        let mut serializer = Serializer::new(self, path);
+        self.spawn_job(thread::spawn(move || {
             // TODO: error handling
             serializer.load_file_csv().unwrap();
-        });
+        }));
+    }
+
+    fn spawn_job(&self, job: JoinHandle<()>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|job| !job.is_finished());
+        jobs.push(job);
+    }
+
+    /// Waits for every SAVE/LOAD still running, so they are not cut off on exit
+    pub fn join_background_jobs(&self) {
+        let jobs: Vec<JoinHandle<()>> = self.jobs.lock().unwrap().drain(..).collect();
+        for job in jobs {
+            let _ = job.join();
+        }
     }
 }
 
+/// Helpers shared by the tests of every command module
 #[cfg(test)]
-mod dictionary {
+pub(crate) mod testing {
     use super::*;
 
+    pub(crate) fn run(dict: &mut Dictionary, command: &str) -> Result<CommandResult, DictionaryError> {
+        dict.run_headless(command.parse::<Command>().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod dictionary {
+    use super::{testing::run, *};
+
+    #[test]
+    fn wrong_type() {
+        let mut dict = Dictionary::new();
+        let keys = [("string", "SET string 19"), ("list", "RPUSH list a"), ("hash", "HSET hash a b"), ("set", "SADD set a"), ("zset", "ZADD zset 1 a"), ("stream", "XADD stream * a b"), ("hyperloglog", "PFADD hll a")];
+        for (_, setup) in keys {
+            run(&mut dict, setup).unwrap();
+        }
+
+        let commands = [
+            "GET list", "INCR list", "SET list a GET",
+            "LPUSH string a", "LRANGE string 0 -1",
+            "HSET string a b", "HGET string a", "HINCRBY string a 1",
+            "SADD string a", "SADD zset a", "SINTER set string",
+            "ZADD string 1 a", "ZRANGE string 0 -1",
+            "XADD string * a b", "XRANGE string - +", "XREAD STREAMS string 0",
+            "PFADD string a", "PFCOUNT hll string", "PFMERGE string hll", "GET hll",
+            "SETBIT list 0 1", "BITFIELD list GET u8 0"
+        ];
+        for command in commands {
+            assert_eq!(run(&mut dict, command), Err(DictionaryError::InvalidOperationType), "{command}");
+        }
+
+        // None of them touched the keys
+        assert_eq!(dict.get("string"), Ok(b"19".to_vec()));
+        for (name, setup) in keys {
+            let key = setup.split(' ').nth(1).unwrap();
+            assert_eq!(run(&mut dict, &format!("TYPE {key}")), Ok(CommandResult::Type(name.to_string())), "{key}");
+        }
+    }
+
     #[test]
     fn get_set() {
         let mut dict = Dictionary::new();
@@ -206,9 +1134,9 @@ mod dictionary {
         let set_command = "SET metanoia 19".to_string().parse::<Command>().unwrap();
         let get_command = "GET metanoia".to_string().parse::<Command>().unwrap();
 
-        assert_eq!(dict.run_headless(set_command), Ok(CommandResult::Set));
+        assert_eq!(dict.run_headless(set_command), Ok(CommandResult::Set(true)));
         let got = dict.run_headless(get_command);
-        assert_eq!(got, Ok(CommandResult::Get("19".to_string())));
+        assert_eq!(got, Ok(CommandResult::Get(b"19".to_vec())));
     }
 
     #[test]
@@ -225,15 +1153,57 @@ mod dictionary {
 
         // Not expired yet; should be Some
         let got = dict.run_headless(get_command);
-        assert_eq!(got, Ok(CommandResult::Get("19".to_string())));
+        assert_eq!(got, Ok(CommandResult::Get(b"19".to_vec())));
 
         // sleep for 2 seconds
         std::thread::sleep(Duration::from_secs(2));
 
-        // Expired; should be None
+        // Expired; should be gone
         let get_command = "GET metanoia".to_string().parse::<Command>().unwrap();
         let got = dict.run_headless(get_command);
-        assert_eq!(got, Err(DictionaryError::IsExpired));
+        assert_eq!(got, Err(DictionaryError::DoesNotExist));
+        assert!(dict.map.lock().unwrap().is_empty());
+    }
+
+    fn set_expired(dict: &mut Dictionary, key: &str) {
+        dict.set(key.to_string(), Entry {
+            value: "19".into(),
+            expiration: Some(SystemTime::now() - Duration::from_secs(1))
+        });
+    }
+
+    #[test]
+    fn expired_is_absent_everywhere() {
+        let mut dict = Dictionary::new();
+
+        set_expired(&mut dict, "metanoia");
+        assert!(!dict.exists("metanoia"));
+
+        set_expired(&mut dict, "metanoia");
+        assert_eq!(dict.del("metanoia"), Err(DictionaryError::DoesNotExist));
+
+        set_expired(&mut dict, "metanoia");
+        assert_eq!(dict.expire("metanoia", Duration::from_secs(60)), Err(DictionaryError::DoesNotExist));
+
+        // Every touch deleted the key
+        assert!(dict.map.lock().unwrap().is_empty());
+
+        // Counted from 0, without the old lifetime
+        set_expired(&mut dict, "metanoia");
+        assert_eq!(dict.incr("metanoia"), Ok(1));
+        assert_eq!(dict.ttl("metanoia"), Ok(Ttl::NoExpiry));
+        assert_eq!(dict.expiry_stats.expired_keys.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn report_expired() {
+        let mut dict = Dictionary::new().report_expired(true);
+
+        set_expired(&mut dict, "metanoia");
+        assert_eq!(dict.get("metanoia"), Err(DictionaryError::IsExpired));
+
+        // Deleted by the first touch, so it's just gone now
+        assert_eq!(dict.get("metanoia"), Err(DictionaryError::DoesNotExist));
     }
 
     #[test]
@@ -255,11 +1225,11 @@ mod dictionary {
         dict.run_headless(set_command).unwrap();
 
         // Check that it is indeed that command
-        assert_eq!(dict.run_headless(incr_command), Ok(CommandResult::Incr));
+        assert_eq!(dict.run_headless(incr_command), Ok(CommandResult::Incr(6)));
 
         // Check that it worked (5+1 = 6)
         let get_command = "GET something".parse::<Command>().unwrap();
-        assert_eq!(dict.run_headless(get_command), Ok(CommandResult::Get("6".to_string())));
+        assert_eq!(dict.run_headless(get_command), Ok(CommandResult::Get(b"6".to_vec())));
     }
 
     #[test]
@@ -272,13 +1242,355 @@ mod dictionary {
         dict.run_headless(set_command).unwrap();
 
         // Check that it is indeed that command
-        assert_eq!(dict.run_headless(decr_command), Ok(CommandResult::Decr));
+        assert_eq!(dict.run_headless(decr_command), Ok(CommandResult::Decr(-6)));
 
         // Check that it worked (-5-1 = -6)
         let get_command = "GET something".parse::<Command>().unwrap();
-        assert_eq!(dict.run_headless(get_command), Ok(CommandResult::Get("-6".to_string())));
+        assert_eq!(dict.run_headless(get_command), Ok(CommandResult::Get(b"-6".to_vec())));
+    }
+
+
+    #[test]
+    fn incr_by() {
+        let mut dict = Dictionary::new();
+
+        // Missing keys start at 0
+        assert_eq!(dict.incr_by("counter", 10), Ok(10));
+        assert_eq!(dict.decr_by("counter", 15), Ok(-5));
+        assert_eq!(dict.incr_by_float("counter", 0.5), Ok(-4.5));
+        assert_eq!(dict.get("counter"), Ok(b"-4.5".to_vec()));
+        assert_eq!(dict.incr_by("counter", 1), Err(DictionaryError::NotANumber));
+
+        let incrbyfloat = "INCRBYFLOAT float 2.5".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(incrbyfloat), Ok(CommandResult::IncrByFloat(2.5)));
+        assert_eq!(dict.incr_by_float("float", f64::MAX), Ok(f64::MAX));
+        assert_eq!(dict.incr_by_float("float", f64::MAX), Err(DictionaryError::Overflow));
+    }
+
+    #[test]
+    fn incr_overflow() {
+        let mut dict = Dictionary::new();
+
+        dict.run_headless(format!("SET big {}", i64::MAX).parse::<Command>().unwrap()).unwrap();
+        assert_eq!(dict.incr("big"), Err(DictionaryError::Overflow));
+        assert_eq!(dict.get("big"), Ok(i64::MAX.to_string().into_bytes()));
+
+        assert_eq!(dict.decr_by("small", i64::MIN), Err(DictionaryError::Overflow));
+        assert!(!dict.exists("small"));
+    }
+
+    #[test]
+    fn incr_keeps_ttl() {
+        let mut dict = Dictionary::new();
+
+        dict.run_headless("SET counter 1 EX 60".parse::<Command>().unwrap()).unwrap();
+        assert_eq!(dict.incr("counter"), Ok(2));
+        assert!(matches!(dict.ttl("counter"), Ok(Ttl::Remaining(_))));
+    }
+
+    #[test]
+    fn append_strlen() {
+        let mut dict = Dictionary::new();
+
+        assert_eq!(dict.strlen("log"), Ok(0));
+        assert_eq!(dict.append("log", "héllo".as_bytes()), Ok(6));
+        dict.expire("log", Duration::from_secs(60)).unwrap();
+
+        let append = "APPEND log _world".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(append), Ok(CommandResult::Append(12)));
+        assert_eq!(dict.get("log"), Ok("héllo_world".as_bytes().to_vec()));
+        assert!(matches!(dict.ttl("log"), Ok(Ttl::Remaining(_))));
+    }
+
+    #[test]
+    fn getrange() {
+        let mut dict = Dictionary::new();
+        dict.run_headless("SET greeting héllo_world".parse::<Command>().unwrap()).unwrap();
+
+        assert_eq!(dict.getrange("greeting", 0, 2), Ok("hé".as_bytes().to_vec()));
+        assert_eq!(dict.getrange("greeting", -5, -1), Ok(b"world".to_vec()));
+        assert_eq!(dict.getrange("greeting", 0, 100), Ok("héllo_world".as_bytes().to_vec()));
+        assert_eq!(dict.getrange("greeting", 5, 2), Ok(Vec::new()));
+        assert_eq!(dict.getrange("nope", 0, -1), Ok(Vec::new()));
+        assert_eq!(dict.getrange("greeting", 0, 1), Err(DictionaryError::NotCharBoundary));
+
+        // Binary strings have no characters to cut through
+        dict.set_with("bits".to_string(), vec![0xc3, 0xff], SetOptions::default()).unwrap();
+        assert_eq!(dict.getrange("bits", 0, 0), Ok(vec![0xc3]));
+    }
+
+    #[test]
+    fn setrange() {
+        let mut dict = Dictionary::new();
+        dict.run_headless("SET greeting héllo_world".parse::<Command>().unwrap()).unwrap();
+
+        assert_eq!(dict.setrange("greeting", 7, b"W"), Ok(12));
+        assert_eq!(dict.get("greeting"), Ok("héllo_World".as_bytes().to_vec()));
+        assert_eq!(dict.setrange("greeting", 1, b"e"), Err(DictionaryError::NotCharBoundary));
+        assert_eq!(dict.setrange("greeting", 2, b"e"), Err(DictionaryError::NotCharBoundary));
+        assert_eq!(dict.setrange("greeting", 1, "é".as_bytes()), Ok(12));
+
+        // Padded with NUL
+        assert_eq!(dict.setrange("padded", 2, b"ab"), Ok(4));
+        assert_eq!(dict.get("padded"), Ok(b"\0\0ab".to_vec()));
+        assert_eq!(dict.setrange("empty", 2, b""), Ok(0));
+        assert!(!dict.exists("empty"));
+        assert_eq!(dict.setrange("huge", MAX_VALUE_SIZE, b"a"), Err(DictionaryError::ValueTooLarge));
+    }
+
+    #[test]
+    fn getdel_getex_getset() {
+        let mut dict = Dictionary::new();
+        dict.run_headless("SET metanoia 19 EX 60".parse::<Command>().unwrap()).unwrap();
+
+        let getex = "GETEX metanoia PERSIST".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(getex), Ok(CommandResult::Get(b"19".to_vec())));
+        assert_eq!(dict.ttl("metanoia"), Ok(Ttl::NoExpiry));
+
+        let getex = "GETEX metanoia EX 60".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(getex), Ok(CommandResult::Get(b"19".to_vec())));
+        assert!(matches!(dict.ttl("metanoia"), Ok(Ttl::Remaining(_))));
+        let getex = "GETEX metanoia EX 18446744073709551615".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(getex), Err(DictionaryError::InvalidExpireTime));
+        assert!(matches!(dict.ttl("metanoia"), Ok(Ttl::Remaining(_))));
+
+        // Drops the lifetime like SET
+        let getset = "GETSET metanoia 20".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(getset), Ok(CommandResult::SetGet(true, Some(b"19".to_vec()))));
+        assert_eq!(dict.ttl("metanoia"), Ok(Ttl::NoExpiry));
+
+        let getdel = "GETDEL metanoia".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(getdel), Ok(CommandResult::Get(b"20".to_vec())));
+        assert_eq!(dict.getdel("metanoia"), Err(DictionaryError::DoesNotExist));
+    }
+
+    #[test]
+    fn multiple_keys() {
+        let mut dict = Dictionary::new();
+
+        let mset = "MSET a 1 b 2 c 3".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(mset), Ok(CommandResult::MSet));
+
+        let mget = "MGET a nope c".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(mget), Ok(CommandResult::MGet(vec![Some(b"1".to_vec()), None, Some(b"3".to_vec())])));
+
+        let exists = "EXISTS a a nope b".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(exists), Ok(CommandResult::Exists(3)));
+
+        let del = "DEL a a nope b".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(del), Ok(CommandResult::Del(2)));
+        assert_eq!(dict.mget(&["a".to_string(), "c".to_string()]), vec![None, Some(b"3".to_vec())]);
+    }
+
+    #[test]
+    fn msetnx() {
+        let mut dict = Dictionary::new();
+
+        let msetnx = "MSETNX a 1 b 2".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(msetnx), Ok(CommandResult::MSetNx(true)));
+
+        // All or nothing
+        let msetnx = "MSETNX c 3 b 4".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(msetnx), Ok(CommandResult::MSetNx(false)));
+        assert!(!dict.exists("c"));
+        assert_eq!(dict.get("b"), Ok(b"2".to_vec()));
+    }
+
+    #[test]
+    fn keys_dbsize_randomkey() {
+        let mut dict = Dictionary::new();
+        assert_eq!(dict.random_key(), None);
+
+        dict.run_headless("MSET user:1 a user:2 b other c".parse::<Command>().unwrap()).unwrap();
+        set_expired(&mut dict, "user:3");
+        assert_eq!(dict.dbsize(), 4);
+
+        let mut keys = dict.keys("user:*");
+        keys.sort();
+        assert_eq!(keys, ["user:1", "user:2"]);
+
+        for _ in 0..10 {
+            assert!(matches!(dict.random_key().as_deref(), Some("user:1" | "user:2" | "other")));
+        }
+    }
+
+    #[test]
+    fn scan_while_mutating() {
+        let mut dict = Dictionary::new();
+        for i in 0..100 {
+            dict.run_headless(format!("SET key{i} {i}").parse::<Command>().unwrap()).unwrap();
+        }
+
+        let options = ScanOptions { count: 7, ..Default::default() };
+        let mut seen = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (next, keys) = dict.scan(cursor, &options);
+            seen.extend(keys);
+
+            // Churn the map between calls
+            dict.del(&format!("key{}", seen.len() % 100)).ok();
+            dict.run_headless(format!("SET new{cursor} 1").parse::<Command>().unwrap()).unwrap();
+
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+
+        // Never twice, and every key that stayed is there
+        let unique: std::collections::HashSet<&String> = seen.iter().collect();
+        assert_eq!(unique.len(), seen.len());
+        for key in dict.keys("key*") {
+            assert!(unique.contains(&key));
+        }
+    }
+
+    #[test]
+    fn scan_filters() {
+        let mut dict = Dictionary::new();
+        dict.run_headless("MSET user:1 a user:2 b other c".parse::<Command>().unwrap()).unwrap();
+
+        let options = ScanOptions { pattern: Some("user:*".to_string()), count: 100, ..Default::default() };
+        let (cursor, mut keys) = dict.scan(0, &options);
+        keys.sort();
+        assert_eq!((cursor, keys), (0, vec!["user:1".to_string(), "user:2".to_string()]));
+
+        let options = ScanOptions { count: 100, type_name: Some("list".to_string()), ..Default::default() };
+        assert_eq!(dict.scan(0, &options), (0, vec![]));
+    }
+
+    #[test]
+    fn rename_keeps_ttl() {
+        let mut dict = Dictionary::new();
+        dict.run_headless("SET from 19 EX 60".parse::<Command>().unwrap()).unwrap();
+        dict.run_headless("SET to 20".parse::<Command>().unwrap()).unwrap();
+
+        let rename = "RENAME from to".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(rename), Ok(CommandResult::Rename));
+        assert!(!dict.exists("from"));
+        assert_eq!(dict.get("to"), Ok(b"19".to_vec()));
+        assert!(matches!(dict.ttl("to"), Ok(Ttl::Remaining(_))));
+
+        assert_eq!(dict.rename("from", "to".to_string()), Err(DictionaryError::DoesNotExist));
+        // Renaming onto itself changes nothing
+        assert_eq!(dict.rename("to", "to".to_string()), Ok(()));
+        assert_eq!(dict.get("to"), Ok(b"19".to_vec()));
+    }
+
+    #[test]
+    fn renamenx() {
+        let mut dict = Dictionary::new();
+        dict.run_headless("MSET a 1 b 2".parse::<Command>().unwrap()).unwrap();
+
+        assert_eq!(dict.renamenx("a", "b".to_string()), Ok(false));
+        assert_eq!(dict.renamenx("a", "c".to_string()), Ok(true));
+        assert_eq!(dict.mget(&["a".to_string(), "b".to_string(), "c".to_string()]),
+            vec![None, Some(b"2".to_vec()), Some(b"1".to_vec())]);
+    }
+
+    #[test]
+    fn copy() {
+        let mut dict = Dictionary::new();
+        dict.run_headless("SET a 1 EX 60".parse::<Command>().unwrap()).unwrap();
+        dict.run_headless("SET b 2".parse::<Command>().unwrap()).unwrap();
+
+        assert!(!dict.copy("a", "b".to_string(), false));
+        assert!(!dict.copy("nope", "c".to_string(), true));
+        assert!(dict.copy("a", "c".to_string(), false));
+        assert!(dict.copy("a", "b".to_string(), true));
+
+        assert_eq!(dict.get("a"), Ok(b"1".to_vec()));
+        assert_eq!(dict.get("b"), Ok(b"1".to_vec()));
+        assert!(matches!(dict.ttl("c"), Ok(Ttl::Remaining(_))));
+    }
+
+    #[test]
+    fn select_is_per_clone() {
+        let mut dict = Dictionary::new();
+        let mut other = dict.clone();
+
+        dict.run_headless("SET metanoia 0".parse::<Command>().unwrap()).unwrap();
+        assert_eq!(dict.run_headless("SELECT 1".parse::<Command>().unwrap()), Ok(CommandResult::Select));
+        assert!(!dict.exists("metanoia"));
+        dict.run_headless("SET metanoia 1".parse::<Command>().unwrap()).unwrap();
+
+        assert_eq!(other.get("metanoia"), Ok(b"0".to_vec()));
+        other.select(1).unwrap();
+        assert_eq!(other.get("metanoia"), Ok(b"1".to_vec()));
+
+        assert_eq!(dict.select(DEFAULT_DATABASES), Err(DictionaryError::InvalidDatabase));
+        assert_eq!(dict.selected(), 1);
+    }
+
+    #[test]
+    fn swapdb() {
+        let mut dict = Dictionary::new();
+        let mut other = dict.clone();
+        other.select(2).unwrap();
+
+        dict.run_headless("SET metanoia 0".parse::<Command>().unwrap()).unwrap();
+        assert_eq!(dict.run_headless("SWAPDB 0 2".parse::<Command>().unwrap()), Ok(CommandResult::SwapDb));
+
+        assert!(!dict.exists("metanoia"));
+        assert_eq!(other.get("metanoia"), Ok(b"0".to_vec()));
+        assert_eq!(dict.swapdb(2, 2), Ok(()));
+        assert_eq!(dict.swapdb(0, DEFAULT_DATABASES), Err(DictionaryError::InvalidDatabase));
+    }
+
+    #[test]
+    fn flushdb_flushall() {
+        let mut dict = Dictionary::new();
+
+        dict.run_headless("SET metanoia 0".parse::<Command>().unwrap()).unwrap();
+        dict.select(1).unwrap();
+        dict.run_headless("SET metanoia 1".parse::<Command>().unwrap()).unwrap();
+
+        dict.run_headless("FLUSHDB".parse::<Command>().unwrap()).unwrap();
+        assert!(!dict.exists("metanoia"));
+        dict.select(0).unwrap();
+        assert!(dict.exists("metanoia"));
+
+        dict.run_headless("FLUSHALL".parse::<Command>().unwrap()).unwrap();
+        assert!(dict.databases.iter().all(|db| db.lock().unwrap().is_empty()));
+    }
+
+    #[test]
+    fn move_key() {
+        let mut dict = Dictionary::new();
+
+        dict.run_headless("SET metanoia 0 EX 60".parse::<Command>().unwrap()).unwrap();
+        dict.run_headless("SET taken 0".parse::<Command>().unwrap()).unwrap();
+        assert_eq!(dict.run_headless("MOVE metanoia 3".parse::<Command>().unwrap()), Ok(CommandResult::Move(true)));
+        assert!(!dict.exists("metanoia"));
+        assert_eq!(dict.move_key("metanoia", 3), Ok(false));
+        assert_eq!(dict.move_key("taken", 0), Ok(false));
+        assert_eq!(dict.move_key("taken", DEFAULT_DATABASES), Err(DictionaryError::InvalidDatabase));
+
+        dict.select(3).unwrap();
+        dict.run_headless("SET taken 3".parse::<Command>().unwrap()).unwrap();
+        assert!(matches!(dict.ttl("metanoia"), Ok(Ttl::Remaining(_))));
+        assert_eq!(dict.move_key("taken", 0), Ok(false));
     }
 
+    #[test]
+    fn volatile_index() {
+        let mut dict = Dictionary::new();
+        let volatile = |dict: &Dictionary, index: usize| dict.databases[index].lock().unwrap().volatile_len();
+
+        for command in ["SET a 1 EX 60", "SET b 1", "EXPIRE b 60", "SET c 1 PX 60000", "PERSIST c", "SET d 1 EX 60", "SET d 2", "GETEX c EX 60", "DEL c", "RENAME a e"] {
+            run(&mut dict, command).unwrap();
+        }
+        // a as e, and b
+        assert_eq!(volatile(&dict, 0), 2);
+
+        run(&mut dict, "MOVE e 1").unwrap();
+        run(&mut dict, "SWAPDB 0 2").unwrap();
+        assert_eq!((volatile(&dict, 0), volatile(&dict, 1), volatile(&dict, 2)), (0, 1, 1));
+
+        run(&mut dict, "FLUSHALL").unwrap();
+        assert!((0..DEFAULT_DATABASES).all(|index| volatile(&dict, index) == 0));
+    }
 
     #[test]
     fn call_expire_on_nonexistent() {
@@ -287,6 +1599,137 @@ mod dictionary {
         assert_eq!(dict.expire("blahblah", Duration::from_secs(5)), Err(DictionaryError::DoesNotExist));
     }
 
+    #[test]
+    fn ttl_persist() {
+        let mut dict = Dictionary::new();
+
+        assert_eq!(dict.ttl("metanoia"), Ok(Ttl::NoKey));
+
+        dict.run_headless("SET metanoia 19".parse::<Command>().unwrap()).unwrap();
+        assert_eq!(dict.ttl("metanoia"), Ok(Ttl::NoExpiry));
+
+        dict.run_headless("EXPIRE metanoia 1h".parse::<Command>().unwrap()).unwrap();
+        match dict.ttl("metanoia") {
+            Ok(ttl @ Ttl::Remaining(_)) => {
+                assert_eq!(ttl.as_secs(), 3600);
+                assert!(ttl.as_millis() > 3_599_000);
+            },
+            other => panic!("Expected a remaining lifetime, got {other:?}")
+        }
+
+        assert_eq!(dict.persist("metanoia"), Ok(true));
+        assert_eq!(dict.persist("metanoia"), Ok(false));
+        assert_eq!(dict.persist("blahblah"), Ok(false));
+        assert_eq!(dict.ttl("metanoia"), Ok(Ttl::NoExpiry));
+    }
+
+    #[test]
+    fn set_options() {
+        let mut dict = Dictionary::new();
+
+        let lock = "SET lock me NX EX 60 GET".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(lock), Ok(CommandResult::SetGet(true, None)));
+        let lock = "SET lock you NX EX 60".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(lock), Ok(CommandResult::Set(false)));
+        assert_eq!(dict.get("lock"), Ok(b"me".to_vec()));
+        assert!(matches!(dict.ttl("lock"), Ok(Ttl::Remaining(_))));
+
+        // KEEPTTL keeps the lifetime, a plain SET drops it
+        let keep = "SET lock you XX KEEPTTL GET".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(keep), Ok(CommandResult::SetGet(true, Some(b"me".to_vec()))));
+        assert!(matches!(dict.ttl("lock"), Ok(Ttl::Remaining(_))));
+        dict.run_headless("SET lock them".parse::<Command>().unwrap()).unwrap();
+        assert_eq!(dict.ttl("lock"), Ok(Ttl::NoExpiry));
+
+        let missing = "SET nope 1 XX GET".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(missing), Ok(CommandResult::SetGet(false, None)));
+        assert!(!dict.exists("nope"));
+    }
+
+    #[test]
+    fn set_expiration_overflow() {
+        let mut dict = Dictionary::new();
+
+        let set = "SET lock me EX 18446744073709551615".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(set), Err(DictionaryError::InvalidExpireTime));
+        assert!(!dict.exists("lock"));
+        // Nothing was poisoned
+        dict.run_headless("SET lock me".parse::<Command>().unwrap()).unwrap();
+        assert_eq!(dict.get("lock"), Ok(b"me".to_vec()));
+    }
+
+    #[test]
+    fn set_nx_on_expired() {
+        let mut dict = Dictionary::new().report_expired(true);
+        dict.set("lock".to_string(), Entry { value: "me".into(), expiration: Some(SystemTime::now()) });
+
+        let lock = "SET lock you NX GET".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(lock), Ok(CommandResult::SetGet(true, None)));
+        assert_eq!(dict.get("lock"), Ok(b"you".to_vec()));
+    }
+
+    #[test]
+    fn expire_conditions() {
+        let mut dict = Dictionary::new();
+        let soon = SystemTime::now() + Duration::from_secs(60);
+        let later = SystemTime::now() + Duration::from_secs(120);
+
+        dict.run_headless("SET metanoia 19".parse::<Command>().unwrap()).unwrap();
+
+        // No expiration yet
+        assert_eq!(dict.expire_at("metanoia", soon, Some(ExpireCondition::Xx)), Ok(false));
+        assert_eq!(dict.expire_at("metanoia", soon, Some(ExpireCondition::Gt)), Ok(false));
+        assert_eq!(dict.expire_at("metanoia", later, Some(ExpireCondition::Lt)), Ok(true));
+
+        // Expires later
+        assert_eq!(dict.expire_at("metanoia", soon, Some(ExpireCondition::Nx)), Ok(false));
+        assert_eq!(dict.expire_at("metanoia", soon, Some(ExpireCondition::Gt)), Ok(false));
+        assert_eq!(dict.expire_at("metanoia", soon, Some(ExpireCondition::Lt)), Ok(true));
+        assert_eq!(dict.expire_at("metanoia", later, Some(ExpireCondition::Gt)), Ok(true));
+        assert!(matches!(dict.ttl("metanoia"), Ok(Ttl::Remaining(left)) if left > Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn expire_at_past_deletes() {
+        let mut dict = Dictionary::new();
+
+        dict.run_headless("SET metanoia 19".parse::<Command>().unwrap()).unwrap();
+        let expireat = "EXPIREAT metanoia 2001-01-01T00:00:00Z".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(expireat), Ok(CommandResult::Expire(true)));
+        assert!(!dict.exists("metanoia"));
+    }
+
+    #[test]
+    fn expire_overflow() {
+        let mut dict = Dictionary::new();
+        dict.run_headless("SET metanoia 19".parse::<Command>().unwrap()).unwrap();
+
+        let expire = "EXPIRE metanoia 18446744073709551615".parse::<Command>().unwrap();
+        assert_eq!(dict.run_headless(expire), Err(DictionaryError::InvalidExpireTime));
+        assert_eq!(dict.expire("metanoia", Duration::MAX), Err(DictionaryError::InvalidExpireTime));
+        // Nothing was poisoned
+        assert_eq!(dict.ttl("metanoia"), Ok(Ttl::NoExpiry));
+    }
+
+    #[test]
+    fn ttl_human_readable() {
+        let ttl = CommandResult::Ttl(Ttl::Remaining(Duration::from_millis(90_400)));
+        assert_eq!(ttl.to_string(), "1m 30s");
+
+        let pttl = CommandResult::Pttl(Ttl::Remaining(Duration::from_millis(90_400)));
+        assert_eq!(pttl.to_string(), "1m 30s 400ms");
+
+        assert_eq!(CommandResult::Ttl(Ttl::NoKey).to_string(), "Key does not exist.");
+    }
+
+    #[test]
+    fn call_incr_on_nonexistent() {
+        let mut dict = Dictionary::new();
+
+        // Created at 0 first
+        assert_eq!(dict.incr("blahblah"), Ok(1));
+    }
+
     #[test]
     fn call_del_on_nonexistent() {
         let mut dict = Dictionary::new();
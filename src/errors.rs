@@ -4,7 +4,13 @@ use std::fmt::Display;
 pub enum ParseError {
     InvalidParameters,
     NotACommand,
-    IsEmpty
+    IsEmpty,
+    /// Malformed RESP framing or an inline command that isn't UTF-8,
+    /// the stream can not be trusted anymore
+    Protocol,
+    /// An inline command past `connection::MAX_INLINE_LEN`, the connection is closed
+    /// since the rest of the line would read as the next command
+    LineTooLong
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -12,15 +18,41 @@ pub enum DictionaryError {
     DoesNotExist,
     IsExpired,
     InvalidOperationType,
+    /// The value is not an integer or a float as the operation expects
+    NotANumber,
+    /// The result of an increment or decrement is out of range
+    Overflow,
+    /// A byte range cuts through a UTF-8 character
+    NotCharBoundary,
+    /// See `dictionary::MAX_VALUE_SIZE`
+    ValueTooLarge,
+    /// An expiration past what `SystemTime` can hold
+    InvalidExpireTime,
+    /// A count asking for more elements than a reply may hold
+    CountOutOfRange,
+    /// No database with that index
+    InvalidDatabase,
+    /// XADD IDs must be greater than the last one of the stream, and than 0-0
+    StreamIdTooSmall,
+    /// The stream or its consumer group does not exist
+    NoGroup,
+    /// XGROUP CREATE on an existing group
+    GroupExists,
 
     IOError(SerializationError)
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConnectionError {
+    MaxClients
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum SerializationError {
     KeyRead,
     ValueRead,
     TimestampRead,
+    DatabaseRead,
 
     IORead,
     IOWrite
@@ -42,7 +74,9 @@ impl Display for ParseError  {
         match self {
             ParseError::NotACommand => write!(f, "Not a command!"),
             ParseError::InvalidParameters => write!(f, "Command parameters are invalid!"),
-            ParseError::IsEmpty => write!(f, "Empty")
+            ParseError::IsEmpty => write!(f, "Empty"),
+            ParseError::Protocol => write!(f, "Protocol error."),
+            ParseError::LineTooLong => write!(f, "Inline command is too long, send large values over RESP.")
         }
     }
 }
@@ -53,17 +87,36 @@ impl Display for DictionaryError  {
             DictionaryError::DoesNotExist => write!(f, "Key does not exist."),
             DictionaryError::IsExpired => write!(f, "Key has expired."),
             DictionaryError::InvalidOperationType => write!(f, "This operation is not defined on value type."),
+            DictionaryError::NotANumber => write!(f, "Value is not a number."),
+            DictionaryError::Overflow => write!(f, "Increment or decrement would overflow."),
+            DictionaryError::NotCharBoundary => write!(f, "Offset is not on a UTF-8 character boundary."),
+            DictionaryError::ValueTooLarge => write!(f, "Value would exceed the maximum size."),
+            DictionaryError::InvalidExpireTime => write!(f, "Invalid expire time."),
+            DictionaryError::CountOutOfRange => write!(f, "Count is out of range."),
+            DictionaryError::InvalidDatabase => write!(f, "Database index is out of range."),
+            DictionaryError::StreamIdTooSmall => write!(f, "Stream ID is equal or smaller than the last one."),
+            DictionaryError::NoGroup => write!(f, "No such key or consumer group."),
+            DictionaryError::GroupExists => write!(f, "Consumer group already exists."),
             DictionaryError::IOError(e) => write!(f, "{e}")
         }
     }
 }
 
+impl Display for ConnectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionError::MaxClients => write!(f, "Max number of clients reached.")
+        }
+    }
+}
+
 impl Display for SerializationError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SerializationError::KeyRead => write!(f, "Key could not be read."),
             SerializationError::ValueRead => write!(f, "Value could not be read."),
             SerializationError::TimestampRead => write!(f, "Expiration timestamp could not be read."),
+            SerializationError::DatabaseRead => write!(f, "Database index could not be read."),
             SerializationError::IORead => write!(f, "IO read failed."),
             SerializationError::IOWrite => write!(f, "IO write failed.")
         }
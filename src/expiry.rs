@@ -0,0 +1,197 @@
+//! Active expiration
+//! Without it, expired keys only go away once they are accessed.
+
+use std::{sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc}, thread::{self, JoinHandle}, time::{Duration, Instant, SystemTime}};
+
+use crate::dictionary::Dictionary;
+
+pub const DEFAULT_HZ: u32 = 10;
+pub const DEFAULT_SAMPLE_SIZE: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct ReaperConfig {
+    /// Cycles per second
+    pub hz: u32,
+    /// Keys checked per round
+    pub sample_size: usize,
+    /// A cycle runs another round right away while more than this share of a sample had expired
+    pub repeat_threshold: f64,
+    /// Share of the time between two cycles a cycle may spend reaping
+    pub time_budget: f64
+}
+
+impl Default for ReaperConfig {
+    /// Same trade-offs as Redis
+    fn default() -> Self {
+        ReaperConfig {
+            hz: DEFAULT_HZ,
+            sample_size: DEFAULT_SAMPLE_SIZE,
+            repeat_threshold: 0.25,
+            time_budget: 0.25
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ExpiryStats {
+    /// Keys removed because their lifetime was over, by the reaper or on access
+    pub expired_keys: AtomicU64,
+    /// Cycles run by the reaper
+    pub cycles: AtomicU64,
+    /// Keys checked by the reaper
+    pub sampled_keys: AtomicU64
+}
+
+/// Background thread removing expired keys, stopped when dropped
+pub struct Reaper {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>
+}
+
+impl Reaper {
+    /// # Panics
+    /// If `config.hz` or `config.sample_size` is zero
+    pub fn start(dict: &Dictionary, config: ReaperConfig) -> Self {
+        assert!(config.hz > 0 && config.sample_size > 0, "The reaper needs a frequency and a sample size");
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = Arc::clone(&stop);
+            let dict = dict.clone();
+            thread::Builder::new()
+                .name("kvdis-reaper".to_string())
+                .spawn(move || reap(dict, config, stop))
+                .expect("Reaper thread could not be spawned")
+        };
+
+        Reaper { stop, handle: Some(handle) }
+    }
+}
+
+impl Drop for Reaper {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+/// # Adaptive sampling
+/// Every cycle checks `sample_size` keys with an expiration at a time, picked at random,
+/// going for another round while a large share of them turned out expired,
+/// until the cycle runs out of time.
+/// Rounds go over the databases one after the other, see `Keyspace`.
+fn reap(dict: Dictionary, config: ReaperConfig, stop: Arc<AtomicBool>) {
+    let period = Duration::from_secs(1) / config.hz;
+    let budget = period.mul_f64(config.time_budget);
+    // Database the next round starts looking from
+    let mut index = 0;
+
+    while !stop.load(Ordering::SeqCst) {
+        let started = Instant::now();
+        dict.expiry_stats.cycles.fetch_add(1, Ordering::Relaxed);
+
+        loop {
+            let (sampled, expired) = reap_round(&dict, &mut index, config.sample_size);
+
+            if (expired as f64) <= sampled as f64 * config.repeat_threshold
+                || started.elapsed() >= budget {
+                break;
+            }
+        }
+
+        thread::park_timeout(period.saturating_sub(started.elapsed()));
+    }
+}
+
+/// Removes the expired keys among up to `sample_size` random keys with an expiration
+/// of the first database from `index` on that has any
+/// # Returns
+/// How many keys were sampled, and how many of them were expired
+fn reap_round(dict: &Dictionary, index: &mut usize, sample_size: usize) -> (usize, usize) {
+    for _ in 0..dict.databases.len() {
+        let mut map = dict.databases[*index].lock().unwrap();
+        *index = (*index + 1) % dict.databases.len();
+
+        let sampled = sample_size.min(map.volatile_len());
+        if sampled == 0 {
+            continue;
+        }
+
+        let now = SystemTime::now();
+        let mut expired = 0;
+        // Picks may repeat, like Redis
+        for _ in 0..sampled {
+            let Some((key, entry)) = map.random_volatile() else {
+                break;
+            };
+            if entry.expiration.is_some_and(|expiration| expiration <= now) {
+                let key = key.clone();
+                map.remove(&key);
+                expired += 1;
+            }
+        }
+
+        dict.expiry_stats.sampled_keys.fetch_add(sampled as u64, Ordering::Relaxed);
+        dict.expiry_stats.expired_keys.fetch_add(expired as u64, Ordering::Relaxed);
+        return (sampled, expired);
+    }
+
+    (0, 0)
+}
+
+#[cfg(test)]
+mod expiry {
+    use crate::dictionary::Entry;
+
+    use super::*;
+
+    #[test]
+    fn reaps_expired_keys() {
+        let mut dict = Dictionary::new();
+        let past = SystemTime::now() - Duration::from_secs(1);
+        let future = SystemTime::now() + Duration::from_secs(60);
+        for i in 0..100 {
+            dict.set(format!("expired{i}"), Entry { value: i.to_string().into(), expiration: Some(past) });
+        }
+        dict.set("alive".to_string(), Entry { value: "1".into(), expiration: Some(future) });
+        dict.set("forever".to_string(), Entry { value: "1".into(), expiration: None });
+        let mut other = dict.clone();
+        other.select(1).unwrap();
+        other.set("expired".to_string(), Entry { value: "1".into(), expiration: Some(past) });
+
+        let reaper = Reaper::start(&dict, ReaperConfig { hz: 100, ..Default::default() });
+        thread::sleep(Duration::from_millis(300));
+        drop(reaper);
+
+        let map = dict.map.lock().unwrap();
+        assert_eq!(map.len(), 2);
+        assert!(map.contains_key("alive"));
+        assert!(map.contains_key("forever"));
+        assert!(other.map.lock().unwrap().is_empty());
+        assert_eq!(dict.expiry_stats.expired_keys.load(Ordering::SeqCst), 101);
+        assert!(dict.expiry_stats.sampled_keys.load(Ordering::SeqCst) >= 102);
+    }
+
+    #[test]
+    fn reaps_a_few_expired_keys_among_many() {
+        let mut dict = Dictionary::new();
+        let past = SystemTime::now() - Duration::from_secs(1);
+        for i in 0..100_000 {
+            dict.set(format!("forever{i}"), Entry::new("1"));
+        }
+        for i in 0..50 {
+            dict.set(format!("expired{i}"), Entry { value: "1".into(), expiration: Some(past) });
+        }
+
+        // Walking every key would take minutes at this pace, the persistent ones are never sampled
+        let reaper = Reaper::start(&dict, ReaperConfig { hz: 20, ..Default::default() });
+        thread::sleep(Duration::from_millis(300));
+        drop(reaper);
+
+        assert_eq!(dict.map.lock().unwrap().len(), 100_000);
+        assert_eq!(dict.expiry_stats.expired_keys.load(Ordering::SeqCst), 50);
+    }
+}
@@ -0,0 +1,123 @@
+//! Redis style glob patterns for KEYS and SCAN
+//! - `*` matches any run of characters, `?` a single one
+//! - `[abc]`, `[a-z]` and `[^a]` match a set of characters, `[!a]` works too
+//! - `\` makes the next character literal
+
+/// Whether `s` matches `pattern` as a whole
+pub fn matches(pattern: &str, s: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = s.chars().collect();
+
+    let (mut p, mut i) = (0, 0);
+    // Where the last `*` was, and where in `s` it stops matching for now
+    let mut star: Option<(usize, usize)> = None;
+
+    while i < s.len() {
+        if pattern.get(p) == Some(&'*') {
+            star = Some((p, i));
+            p += 1;
+        } else if let Some(next) = match_one(&pattern, p, s[i]) {
+            p = next;
+            i += 1;
+        } else if let Some((star_p, star_i)) = star {
+            // Let the `*` eat one more character
+            p = star_p + 1;
+            i = star_i + 1;
+            star = Some((star_p, star_i + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Matches `c` against the token at `p`, which is not a `*`
+/// # Returns
+/// Where the next token starts if it matched
+fn match_one(pattern: &[char], p: usize, c: char) -> Option<usize> {
+    match pattern.get(p)? {
+        '?' => Some(p + 1),
+        '\\' if p + 1 < pattern.len() => (pattern[p + 1] == c).then_some(p + 2),
+        '[' => match match_class(pattern, p, c) {
+            Some((matched, next)) => matched.then_some(next),
+            // No closing bracket, it's just a character
+            None => (c == '[').then_some(p + 1)
+        },
+        literal => (*literal == c).then_some(p + 1)
+    }
+}
+
+/// # Returns
+/// Whether `c` is in the class opening at `p` and where the class ends,
+/// None if it is never closed
+fn match_class(pattern: &[char], p: usize, c: char) -> Option<(bool, usize)> {
+    let mut i = p + 1;
+    let negated = matches!(pattern.get(i), Some('^' | '!'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    loop {
+        match *pattern.get(i)? {
+            ']' => return Some((matched != negated, i + 1)),
+            '\\' => {
+                matched |= *pattern.get(i + 1)? == c;
+                i += 2;
+            },
+            first => match (pattern.get(i + 1), pattern.get(i + 2)) {
+                (Some('-'), Some(&last)) if last != ']' => {
+                    matched |= (first.min(last)..=first.max(last)).contains(&c);
+                    i += 3;
+                },
+                _ => {
+                    matched |= first == c;
+                    i += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod glob {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*", ""));
+        assert!(matches("*", "metanoia"));
+        assert!(matches("meta*", "metanoia"));
+        assert!(matches("*noia", "metanoia"));
+        assert!(matches("m*a*a", "metanoia"));
+        assert!(matches("m?ta*", "metanoia"));
+        assert!(!matches("m?ta", "metanoia"));
+        assert!(!matches("*x*", "metanoia"));
+        assert!(matches("user:*:name", "user:19:name"));
+        assert!(!matches("user:*:name", "user:19:mail"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(matches("h[ae]llo", "hello"));
+        assert!(!matches("h[ae]llo", "hillo"));
+        assert!(matches("h[^e]llo", "hallo"));
+        assert!(!matches("h[!e]llo", "hello"));
+        assert!(matches("key[0-9]", "key7"));
+        assert!(matches("key[9-0]", "key7"));
+        assert!(!matches("key[0-9]", "keyx"));
+        assert!(matches("é[é-ê]", "éê"));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches("what\\?", "what?"));
+        assert!(!matches("what\\?", "whats"));
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+        assert!(matches("[\\]]", "]"));
+        // Unclosed classes are literal
+        assert!(matches("a[b", "a[b"));
+    }
+}
@@ -0,0 +1,228 @@
+//! Hash commands
+//! Hashes are created by setting a field of a missing key and deleted once emptied.
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{command::ScanOptions, dictionary::Dictionary, errors::DictionaryError, glob, scan_map::ScanMap, value::Value};
+
+impl Dictionary {
+    /// # Returns
+    /// How many of the fields are new
+    pub fn hset(&mut self, key: &str, pairs: Vec<(String, String)>) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let hash = self.live_entry_or_insert(&mut map, key, || Value::Hash(ScanMap::new())).value.as_hash_mut()?;
+
+        Ok(pairs.into_iter().map(|(field, value)| hash.insert(field, value)).filter(Option::is_none).count())
+    }
+
+    /// # Returns
+    /// Whether the field was set, not if it already exists
+    pub fn hsetnx(&mut self, key: &str, field: String, value: String) -> Result<bool, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let hash = self.live_entry_or_insert(&mut map, key, || Value::Hash(ScanMap::new())).value.as_hash_mut()?;
+
+        if hash.contains_key(&field) {
+            return Ok(false);
+        }
+        hash.insert(field, value);
+        Ok(true)
+    }
+
+    /// # Returns
+    /// The value of `field`, None if the field or the key is missing
+    pub fn hget(&self, key: &str, field: &str) -> Result<Option<String>, DictionaryError> {
+        Ok(self.hmget(key, &[field.to_string()])?.remove(0))
+    }
+
+    /// # Returns
+    /// One value per field, None for missing fields
+    pub fn hmget(&self, key: &str, fields: &[String]) -> Result<Vec<Option<String>>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let hash = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_hash()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(vec![None; fields.len()]),
+            Err(e) => return Err(e)
+        };
+
+        Ok(fields.iter().map(|field| hash.get(field).cloned()).collect())
+    }
+
+    /// # Returns
+    /// How many fields were deleted
+    pub fn hdel(&mut self, key: &str, fields: &[String]) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let hash = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_hash_mut()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(0),
+            Err(e) => return Err(e)
+        };
+
+        let deleted = fields.iter().filter(|field| hash.remove(field.as_str()).is_some()).count();
+        if hash.is_empty() {
+            map.remove(key);
+        }
+
+        Ok(deleted)
+    }
+
+    /// Every field and value, in no particular order, none for a missing key
+    pub fn hgetall(&self, key: &str) -> Result<Vec<(String, String)>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        match self.live_entry(&mut map, key) {
+            Ok(entry) => Ok(entry.value.as_hash()?.iter().map(|(field, value)| (field.clone(), value.clone())).collect()),
+            Err(DictionaryError::DoesNotExist) => Ok(Vec::new()),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// # Returns
+    /// The number of fields, 0 for a missing key
+    pub fn hlen(&self, key: &str) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        match self.live_entry(&mut map, key) {
+            Ok(entry) => Ok(entry.value.as_hash()?.len()),
+            Err(DictionaryError::DoesNotExist) => Ok(0),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// # Returns
+    /// The new value of `field`, which starts at 0 when missing
+    pub fn hincr_by(&mut self, key: &str, field: &str, by: i64) -> Result<i64, DictionaryError> {
+        self.update_field(key, field, |value: i64| value.checked_add(by))
+    }
+
+    /// # Returns
+    /// The new value of `field`, which starts at 0 when missing
+    pub fn hincr_by_float(&mut self, key: &str, field: &str, by: f64) -> Result<f64, DictionaryError> {
+        self.update_field(key, field, |value: f64| Some(value + by).filter(|value| value.is_finite()))
+    }
+
+    /// `Dictionary::update_number` for a field
+    fn update_field<T>(&mut self, key: &str, field: &str, update: impl FnOnce(T) -> Option<T>) -> Result<T, DictionaryError>
+    where
+        T: FromStr + Display + Default + Copy
+    {
+        let mut map = self.map.lock().unwrap();
+        let current = match self.live_entry(&mut map, key) {
+            Ok(entry) => match entry.value.as_hash()?.get(field) {
+                Some(value) => value.parse::<T>().map_err(|_e| DictionaryError::NotANumber)?,
+                None => T::default()
+            },
+            // Expired keys count as absent
+            Err(_) => T::default()
+        };
+        let new_value = update(current).ok_or(DictionaryError::Overflow)?;
+
+        let hash = self.live_entry_or_insert(&mut map, key, || Value::Hash(ScanMap::new())).value.as_hash_mut()?;
+        hash.insert(field.to_string(), new_value.to_string());
+        Ok(new_value)
+    }
+
+    /// SCAN over the fields of a hash, `options.type_name` is ignored
+    /// # Returns
+    /// The cursor to continue from, 0 once done, and the matching fields each followed by its value
+    pub fn hscan(&self, key: &str, cursor: u64, options: &ScanOptions) -> Result<(u64, Vec<String>), DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let hash = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_hash()?,
+            Err(DictionaryError::DoesNotExist) => return Ok((0, Vec::new())),
+            Err(e) => return Err(e)
+        };
+
+        let (next_cursor, batch) = hash.scan(cursor, options.count);
+        let fields = batch.into_iter()
+            .filter(|(field, _)| options.pattern.as_ref().is_none_or(|pattern| glob::matches(pattern, field)))
+            .flat_map(|(field, value)| [field.clone(), value.clone()])
+            .collect();
+
+        Ok((next_cursor, fields))
+    }
+}
+
+#[cfg(test)]
+mod hash {
+    use crate::{command::CommandResult, dictionary::testing::run};
+
+    use super::*;
+
+    #[test]
+    fn hset_hget_hdel() {
+        let mut dict = Dictionary::new();
+
+        assert_eq!(run(&mut dict, "HSET user:42 name alex age 19"), Ok(CommandResult::HSet(2)));
+        assert_eq!(run(&mut dict, "HSET user:42 age 20 mail a@b"), Ok(CommandResult::HSet(1)));
+        assert_eq!(run(&mut dict, "HGET user:42 age"), Ok(CommandResult::HGet(Some("20".to_string()))));
+        assert_eq!(run(&mut dict, "HGET user:42 nope"), Ok(CommandResult::HGet(None)));
+        assert_eq!(run(&mut dict, "HMGET user:42 name nope"), Ok(CommandResult::MGet(vec![Some(b"alex".to_vec()), None])));
+        assert_eq!(run(&mut dict, "HEXISTS user:42 mail"), Ok(CommandResult::HExists(true)));
+        assert_eq!(run(&mut dict, "HLEN user:42"), Ok(CommandResult::HLen(3)));
+
+        assert_eq!(run(&mut dict, "HSETNX user:42 name sam"), Ok(CommandResult::HSetNx(false)));
+        assert_eq!(run(&mut dict, "HSETNX user:42 nick sam"), Ok(CommandResult::HSetNx(true)));
+
+        assert_eq!(run(&mut dict, "HDEL user:42 name age nope"), Ok(CommandResult::HDel(2)));
+        assert_eq!(run(&mut dict, "HDEL user:42 mail nick"), Ok(CommandResult::HDel(2)));
+        // Emptied hashes are gone
+        assert!(!dict.exists("user:42"));
+        assert_eq!(dict.hget("user:42", "name"), Ok(None));
+
+        assert_eq!(run(&mut dict, "HMSET user:42 name alex age 19"), Ok(CommandResult::HMSet));
+        assert_eq!(dict.hlen("user:42"), Ok(2));
+    }
+
+    #[test]
+    fn hgetall_hkeys_hvals() {
+        let mut dict = Dictionary::new();
+        run(&mut dict, "HSET user:42 name alex").unwrap();
+
+        assert_eq!(run(&mut dict, "HGETALL user:42"), Ok(CommandResult::HGetAll(vec![("name".to_string(), "alex".to_string())])));
+        assert_eq!(run(&mut dict, "HKEYS user:42"), Ok(CommandResult::List(vec!["name".to_string()])));
+        assert_eq!(run(&mut dict, "HVALS user:42"), Ok(CommandResult::List(vec!["alex".to_string()])));
+        assert_eq!(dict.hgetall("nope"), Ok(vec![]));
+    }
+
+    #[test]
+    fn hincrby() {
+        let mut dict = Dictionary::new();
+
+        assert_eq!(run(&mut dict, "HINCRBY stats visits 5"), Ok(CommandResult::Incr(5)));
+        assert_eq!(run(&mut dict, "HINCRBY stats visits -2"), Ok(CommandResult::Incr(3)));
+        assert_eq!(run(&mut dict, "HINCRBYFLOAT stats ratio 0.5"), Ok(CommandResult::IncrByFloat(0.5)));
+        assert_eq!(dict.hincr_by("stats", "ratio", 1), Err(DictionaryError::NotANumber));
+
+        dict.hset("stats", vec![("big".to_string(), i64::MAX.to_string())]).unwrap();
+        assert_eq!(dict.hincr_by("stats", "big", 1), Err(DictionaryError::Overflow));
+        // Infinite results are refused
+        assert_eq!(dict.hincr_by_float("other", "ratio", f64::MAX), Ok(f64::MAX));
+        assert_eq!(dict.hincr_by_float("other", "ratio", f64::MAX), Err(DictionaryError::Overflow));
+    }
+
+    #[test]
+    fn hscan() {
+        let mut dict = Dictionary::new();
+        for i in 0..50 {
+            dict.hset("hash", vec![(format!("field{i}"), i.to_string())]).unwrap();
+        }
+
+        let options = ScanOptions { count: 7, ..Default::default() };
+        let mut seen = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (next, fields) = dict.hscan("hash", cursor, &options).unwrap();
+            seen.extend(fields.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())));
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+
+        seen.sort();
+        let mut all = dict.hgetall("hash").unwrap();
+        all.sort();
+        assert_eq!(seen, all);
+
+        let options = ScanOptions { pattern: Some("field4?".to_string()), count: 100, ..Default::default() };
+        assert_eq!(dict.hscan("hash", 0, &options).map(|(cursor, fields)| (cursor, fields.len())), Ok((0, 20)));
+    }
+}
@@ -0,0 +1,154 @@
+//! The map behind a database
+//! A `ScanMap` of entries that also indexes the keys with an expiration,
+//! so the reaper can sample those without going over persistent keys.
+
+use std::{collections::HashMap, ops::Deref, time::SystemTime};
+
+use crate::{dictionary::{random_index, Entry}, scan_map::ScanMap};
+
+/// Reads go through `Deref`, writes through the methods below so the index stays in sync.
+/// Expirations are only changed through `set_expiration`.
+#[derive(Debug, Default, Clone)]
+pub struct Keyspace {
+    entries: ScanMap<Entry>,
+    volatile: Volatile
+}
+
+impl Deref for Keyspace {
+    type Target = ScanMap<Entry>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl Keyspace {
+    pub fn new() -> Self {
+        Keyspace::default()
+    }
+
+    /// The expiration of the entry must not be changed through it, see `set_expiration`
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Entry> {
+        self.entries.get_mut(key)
+    }
+
+    /// # Returns
+    /// The previous entry
+    pub fn insert(&mut self, key: String, entry: Entry) -> Option<Entry> {
+        self.volatile.track(&key, entry.expiration);
+        self.entries.insert(key, entry)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Entry> {
+        self.volatile.remove(key);
+        self.entries.remove(key)
+    }
+
+    pub fn get_or_insert_with(&mut self, key: &str, default: impl FnOnce() -> Entry) -> &mut Entry {
+        if !self.entries.contains_key(key) {
+            self.insert(key.to_string(), default());
+        }
+        self.entries.get_mut(key).unwrap()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.volatile = Volatile::default();
+    }
+
+    /// # Returns
+    /// The previous expiration, None for a missing key as well
+    pub fn set_expiration(&mut self, key: &str, expiration: Option<SystemTime>) -> Option<SystemTime> {
+        let entry = self.entries.get_mut(key)?;
+        let previous = std::mem::replace(&mut entry.expiration, expiration);
+
+        self.volatile.track(key, expiration);
+        previous
+    }
+
+    /// How many keys have an expiration
+    pub fn volatile_len(&self) -> usize {
+        self.volatile.keys.len()
+    }
+
+    /// # Returns
+    /// A key with an expiration picked at random, None if there is none
+    pub fn random_volatile(&self) -> Option<(&String, &Entry)> {
+        if self.volatile.keys.is_empty() {
+            return None;
+        }
+
+        let key = &self.volatile.keys[random_index(self.volatile.keys.len())];
+        self.entries.get_key_value(key)
+    }
+}
+
+/// Keys with an expiration, like the `expires` dict of Redis.
+/// A `Vec` so picking one at random is O(1), positions so removing one is too.
+#[derive(Debug, Default, Clone)]
+struct Volatile {
+    keys: Vec<String>,
+    positions: HashMap<String, usize>
+}
+
+impl Volatile {
+    fn track(&mut self, key: &str, expiration: Option<SystemTime>) {
+        match expiration {
+            Some(_) => self.insert(key),
+            None => self.remove(key)
+        }
+    }
+
+    fn insert(&mut self, key: &str) {
+        if !self.positions.contains_key(key) {
+            self.positions.insert(key.to_string(), self.keys.len());
+            self.keys.push(key.to_string());
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        let Some(position) = self.positions.remove(key) else {
+            return;
+        };
+
+        self.keys.swap_remove(position);
+        if let Some(moved) = self.keys.get(position) {
+            self.positions.insert(moved.clone(), position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod keyspace {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn index_follows_writes() {
+        let mut keyspace = Keyspace::new();
+        let later = SystemTime::now() + Duration::from_secs(60);
+        for i in 0..10 {
+            keyspace.insert(i.to_string(), Entry { value: "v".into(), expiration: (i % 2 == 0).then_some(later) });
+        }
+        assert_eq!(keyspace.volatile_len(), 5);
+
+        // Overwriting drops the expiration, like SET
+        keyspace.insert("0".to_string(), Entry::new("v"));
+        keyspace.remove("2");
+        assert_eq!(keyspace.set_expiration("4", None), Some(later));
+        assert_eq!(keyspace.set_expiration("1", Some(later)), None);
+        assert_eq!(keyspace.set_expiration("missing", Some(later)), None);
+        keyspace.get_or_insert_with("new", || Entry { value: "v".into(), expiration: Some(later) }).value = "w".into();
+
+        let mut volatile = keyspace.volatile.keys.clone();
+        volatile.sort();
+        assert_eq!(volatile, ["1", "6", "8", "new"]);
+        assert!(keyspace.volatile.positions.iter().all(|(key, &position)| keyspace.volatile.keys[position] == *key));
+        assert!(keyspace.random_volatile().is_some_and(|(key, _)| volatile.contains(key)));
+
+        keyspace.clear();
+        assert_eq!(keyspace.volatile_len(), 0);
+        assert!(keyspace.random_volatile().is_none());
+    }
+}
@@ -2,20 +2,138 @@
 //! A relational map of Strings
 //! - Still haven't decided how text GET and EXISTS calls should process or do whatever.
 //! # Command set
-//! #### SET \<key\> \<value\>
+//! #### SET \<key\> \<value\> \[NX|XX\] \[EX s|PX ms|EXAT ts|PXAT ms|KEEPTTL\] \[GET\]
 //! #### GET \<key\>
-//! #### DEL \<key\>
-//! #### EXISTS \<key\>
-//! #### EXPIRE \<key\> \<duration in humantime format\>
+//! #### GETSET \<key\> \<value\>
+//! #### GETDEL \<key\>
+//! #### GETEX \<key\> \[EX s|PX ms|EXAT ts|PXAT ms|PERSIST\]
+//! #### APPEND \<key\> \<value\>
+//! #### STRLEN \<key\>
+//! #### GETRANGE \<key\> \<start\> \<end\>
+//! #### SETRANGE \<key\> \<offset\> \<value\>
+//! #### DEL \<key\> \[key ...\]
+//! #### EXISTS \<key\> \[key ...\]
+//! #### RENAME \<key\> \<newkey\>
+//! #### RENAMENX \<key\> \<newkey\>
+//! #### COPY \<key\> \<newkey\> \[REPLACE\]
+//! #### MGET \<key\> \[key ...\]
+//! #### MSET \<key\> \<value\> \[key value ...\]
+//! #### MSETNX \<key\> \<value\> \[key value ...\]
+//! #### EXPIRE \<key\> \<duration in humantime format\> \[NX|XX|GT|LT\]
+//! #### EXPIREAT \<key\> \<RFC 3339 timestamp or unix seconds\> \[NX|XX|GT|LT\]
+//! #### PEXPIREAT \<key\> \<unix milliseconds\> \[NX|XX|GT|LT\]
+//! #### TTL \<key\>
+//! #### PTTL \<key\>
+//! #### PERSIST \<key\>
 //! #### INCR \<key\>
 //! #### DECR \<key\>
+//! #### INCRBY \<key\> \<increment\>
+//! #### DECRBY \<key\> \<decrement\>
+//! #### INCRBYFLOAT \<key\> \<increment\>
 //! #### CLEAR
+//! #### FLUSHALL
+//! #### FLUSHDB
+//! #### SELECT \<index\>
+//! #### SWAPDB \<index\> \<index\>
+//! #### MOVE \<key\> \<index\>
 //! #### SAVE
 //! #### LOAD
+//! #### PING \[message\]
+//! #### HELLO \[2|3\]
+//! #### SHUTDOWN \[SAVE|NOSAVE\]
+//! #### INFO
+//! #### TYPE \<key\>
+//! #### LPUSH \<key\> \<value\> \[value ...\]
+//! #### RPUSH \<key\> \<value\> \[value ...\]
+//! #### LPOP \<key\> \[count\]
+//! #### RPOP \<key\> \[count\]
+//! #### LRANGE \<key\> \<start\> \<stop\>
+//! #### LLEN \<key\>
+//! #### LINDEX \<key\> \<index\>
+//! #### LTRIM \<key\> \<start\> \<stop\>
+//! #### HSET \<key\> \<field\> \<value\> \[field value ...\]
+//! #### HMSET \<key\> \<field\> \<value\> \[field value ...\]
+//! #### HSETNX \<key\> \<field\> \<value\>
+//! #### HGET \<key\> \<field\>
+//! #### HMGET \<key\> \<field\> \[field ...\]
+//! #### HDEL \<key\> \<field\> \[field ...\]
+//! #### HEXISTS \<key\> \<field\>
+//! #### HLEN \<key\>
+//! #### HKEYS \<key\>
+//! #### HVALS \<key\>
+//! #### HGETALL \<key\>
+//! #### HINCRBY \<key\> \<field\> \<increment\>
+//! #### HINCRBYFLOAT \<key\> \<field\> \<increment\>
+//! #### HSCAN \<key\> \<cursor\> \[MATCH pattern\] \[COUNT n\]
+//! #### SADD \<key\> \<member\> \[member ...\]
+//! #### SREM \<key\> \<member\> \[member ...\]
+//! #### SMEMBERS \<key\>
+//! #### SISMEMBER \<key\> \<member\>
+//! #### SCARD \<key\>
+//! #### SINTER \<key\> \[key ...\]
+//! #### SUNION \<key\> \[key ...\]
+//! #### SDIFF \<key\> \[key ...\]
+//! #### SINTERSTORE \<destination\> \<key\> \[key ...\]
+//! #### SUNIONSTORE \<destination\> \<key\> \[key ...\]
+//! #### SDIFFSTORE \<destination\> \<key\> \[key ...\]
+//! #### SRANDMEMBER \<key\> \[count\]
+//! #### SPOP \<key\> \[count\]
+//! #### ZADD \<key\> \[NX|XX\] \[GT|LT\] \[CH\] \[INCR\] \<score\> \<member\> \[score member ...\]
+//! #### ZINCRBY \<key\> \<increment\> \<member\>
+//! #### ZRANGE \<key\> \<start\> \<stop\> \[BYSCORE|BYLEX\] \[REV\] \[LIMIT offset count\] \[WITHSCORES\]
+//! #### ZRANGEBYSCORE \<key\> \<min\> \<max\> \[WITHSCORES\] \[LIMIT offset count\]
+//! #### ZRANK \<key\> \<member\>
+//! #### ZSCORE \<key\> \<member\>
+//! #### ZCARD \<key\>
+//! #### ZREM \<key\> \<member\> \[member ...\]
+//! #### ZPOPMIN \<key\> \[count\]
+//! #### ZPOPMAX \<key\> \[count\]
+//! #### XADD \<key\> \[MAXLEN \[~\] n\] \<*|ms-*|id\> \<field\> \<value\> \[field value ...\]
+//! #### XLEN \<key\>
+//! #### XRANGE \<key\> \<start\> \<end\> \[COUNT n\]
+//! #### XREVRANGE \<key\> \<end\> \<start\> \[COUNT n\]
+//! #### XREAD \[COUNT n\] STREAMS \<key\> \[key ...\] \<id|$\> \[id ...\]
+//! #### XGROUP CREATE \<key\> \<group\> \<id|$\> \[MKSTREAM\]
+//! #### XGROUP DESTROY \<key\> \<group\>
+//! #### XREADGROUP GROUP \<group\> \<consumer\> \[COUNT n\] STREAMS \<key\> \[key ...\] \<>|id\> \[id ...\]
+//! #### XACK \<key\> \<group\> \<id\> \[id ...\]
+//! #### XPENDING \<key\> \<group\> \[\<start\> \<end\> \<count\> \[consumer\]\]
+//! #### XCLAIM \<key\> \<group\> \<consumer\> \<min-idle-ms\> \<id\> \[id ...\]
+//! #### SETBIT \<key\> \<offset\> \<0|1\>
+//! #### GETBIT \<key\> \<offset\>
+//! #### BITCOUNT \<key\> \[\<start\> \<end\> \[BYTE|BIT\]\]
+//! #### BITPOS \<key\> \<0|1\> \[\<start\> \[\<end\> \[BYTE|BIT\]\]\]
+//! #### BITOP \<AND|OR|XOR|NOT\> \<destkey\> \<key\> \[key ...\]
+//! #### BITFIELD \<key\> \[GET type offset\] \[SET type offset value\] \[INCRBY type offset increment\] \[OVERFLOW WRAP|SAT|FAIL\] ...
+//! #### PFADD \<key\> \[element ...\]
+//! #### PFCOUNT \<key\> \[key ...\]
+//! #### PFMERGE \<destkey\> \[sourcekey ...\]
+//! #### KEYS \<pattern\>
+//! #### SCAN \<cursor\> \[MATCH pattern\] \[COUNT n\] \[TYPE type\]
+//! #### DBSIZE
+//! #### RANDOMKEY
+//! # Protocol
+//! Commands are sent either inline, one per line, or as RESP arrays of bulk strings.
+//! The first command of a connection decides whether replies are plain text or RESP.
 
 pub mod command;
 pub mod parsing;
 pub mod errors;
 pub mod dictionary;
+pub mod scan_map;
+pub mod keyspace;
 pub mod connection;
+pub mod glob;
+pub mod value;
+pub mod list;
+pub mod hash;
+pub mod set;
+pub mod zset;
+pub mod stream;
+pub mod bitmap;
+pub mod hyperloglog;
+pub mod pool;
 pub mod persistence;
+pub mod expiry;
+pub mod resp;
+pub mod shutdown;
@@ -0,0 +1,174 @@
+//! List commands
+//! Lists are created by pushing to a missing key and deleted once emptied,
+//! so a key never holds an empty list.
+
+use std::collections::VecDeque;
+
+use crate::{command::ListEnd, dictionary::{range_bounds, Dictionary}, errors::DictionaryError, value::Value};
+
+impl Dictionary {
+    /// Pushes `values` one after the other, so LPUSH reverses them
+    /// # Returns
+    /// The new length of the list
+    pub fn push(&mut self, key: &str, values: Vec<String>, end: ListEnd) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let list = self.live_entry_or_insert(&mut map, key, || Value::List(VecDeque::new())).value.as_list_mut()?;
+        for value in values {
+            match end {
+                ListEnd::Left => list.push_front(value),
+                ListEnd::Right => list.push_back(value)
+            }
+        }
+
+        Ok(list.len())
+    }
+
+    /// Pops up to `count` values
+    /// # Returns
+    /// - The values, in the order they were popped
+    /// - If the key does not exist, Err(DictionaryError::DoesNotExist)
+    pub fn pop(&mut self, key: &str, count: usize, end: ListEnd) -> Result<Vec<String>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let list = self.live_entry(&mut map, key)?.value.as_list_mut()?;
+
+        let count = count.min(list.len());
+        let popped = match end {
+            ListEnd::Left => list.drain(..count).collect(),
+            ListEnd::Right => list.drain(list.len() - count..).rev().collect()
+        };
+        if list.is_empty() {
+            map.remove(key);
+        }
+
+        Ok(popped)
+    }
+
+    /// Redis style inclusive range, see `dictionary::range_bounds`
+    /// # Returns
+    /// The values in range, none for a missing key
+    pub fn lrange(&self, key: &str, start: i64, end: i64) -> Result<Vec<String>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let list = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_list()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(Vec::new()),
+            Err(e) => return Err(e)
+        };
+
+        Ok(match range_bounds(start, end, list.len()) {
+            Some((start, end)) => list.range(start..=end).cloned().collect(),
+            None => Vec::new()
+        })
+    }
+
+    /// # Returns
+    /// The length of the list, 0 for a missing key
+    pub fn llen(&self, key: &str) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        match self.live_entry(&mut map, key) {
+            Ok(entry) => Ok(entry.value.as_list()?.len()),
+            Err(DictionaryError::DoesNotExist) => Ok(0),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// # Returns
+    /// The value at `index`, negative ones counting from the end, None if out of range
+    pub fn lindex(&self, key: &str, index: i64) -> Result<Option<String>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let list = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_list()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(None),
+            Err(e) => return Err(e)
+        };
+
+        let index = if index < 0 { list.len() as i64 + index } else { index };
+        Ok(usize::try_from(index).ok().and_then(|index| list.get(index)).cloned())
+    }
+
+    /// Keeps only the values in range, deleting the key if none are
+    pub fn ltrim(&mut self, key: &str, start: i64, end: i64) -> Result<(), DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let list = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_list_mut()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(()),
+            Err(e) => return Err(e)
+        };
+
+        match range_bounds(start, end, list.len()) {
+            Some((start, end)) => {
+                list.truncate(end + 1);
+                list.drain(..start);
+            },
+            None => {
+                map.remove(key);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod list {
+    use crate::{command::CommandResult, dictionary::testing::run};
+
+    use super::*;
+
+    #[test]
+    fn push_pop() {
+        let mut dict = Dictionary::new();
+
+        assert_eq!(run(&mut dict, "RPUSH queue b c"), Ok(CommandResult::Push(2)));
+        assert_eq!(run(&mut dict, "LPUSH queue a z"), Ok(CommandResult::Push(4)));
+        assert_eq!(dict.lrange("queue", 0, -1), Ok(vec!["z".to_string(), "a".to_string(), "b".to_string(), "c".to_string()]));
+
+        assert_eq!(run(&mut dict, "LPOP queue"), Ok(CommandResult::Get(b"z".to_vec())));
+        assert_eq!(run(&mut dict, "RPOP queue 2"), Ok(CommandResult::List(vec!["c".to_string(), "b".to_string()])));
+        assert_eq!(run(&mut dict, "LPOP queue 5"), Ok(CommandResult::List(vec!["a".to_string()])));
+
+        // Emptied lists are gone
+        assert!(!dict.exists("queue"));
+        assert_eq!(run(&mut dict, "LPOP queue"), Err(DictionaryError::DoesNotExist));
+    }
+
+    #[test]
+    fn lrange_llen_lindex() {
+        let mut dict = Dictionary::new();
+        run(&mut dict, "RPUSH list a b c d e").unwrap();
+
+        assert_eq!(dict.lrange("list", 1, 2), Ok(vec!["b".to_string(), "c".to_string()]));
+        assert_eq!(dict.lrange("list", -2, 100), Ok(vec!["d".to_string(), "e".to_string()]));
+        assert_eq!(dict.lrange("list", 3, 1), Ok(vec![]));
+        assert_eq!(dict.lrange("nope", 0, -1), Ok(vec![]));
+
+        assert_eq!(run(&mut dict, "LLEN list"), Ok(CommandResult::LLen(5)));
+        assert_eq!(dict.llen("nope"), Ok(0));
+
+        assert_eq!(run(&mut dict, "LINDEX list -1"), Ok(CommandResult::LIndex(Some("e".to_string()))));
+        assert_eq!(dict.lindex("list", 0), Ok(Some("a".to_string())));
+        assert_eq!(dict.lindex("list", 5), Ok(None));
+        assert_eq!(dict.lindex("list", -6), Ok(None));
+    }
+
+    #[test]
+    fn ltrim() {
+        let mut dict = Dictionary::new();
+        run(&mut dict, "RPUSH list a b c d e").unwrap();
+
+        assert_eq!(run(&mut dict, "LTRIM list 1 -2"), Ok(CommandResult::LTrim));
+        assert_eq!(dict.lrange("list", 0, -1), Ok(vec!["b".to_string(), "c".to_string(), "d".to_string()]));
+
+        dict.ltrim("list", 5, 10).unwrap();
+        assert!(!dict.exists("list"));
+    }
+
+    #[test]
+    fn set_overwrites_any_type() {
+        let mut dict = Dictionary::new();
+        run(&mut dict, "RPUSH list a").unwrap();
+
+        assert_eq!(run(&mut dict, "SET list a"), Ok(CommandResult::Set(true)));
+        assert_eq!(run(&mut dict, "TYPE list"), Ok(CommandResult::Type("string".to_string())));
+        assert_eq!(run(&mut dict, "TYPE nope"), Ok(CommandResult::Type("none".to_string())));
+    }
+}
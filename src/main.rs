@@ -1,11 +1,22 @@
-use std::{io, process};
-use kvdis::{command::Command, connection::{bind, run, DEFAULT_PORT}, dictionary::{Dictionary}};
+use std::{io, net::IpAddr, path::PathBuf, process, sync::Arc};
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use kvdis::connection::bind_unix;
+use kvdis::{command::Command, connection::{bind, run, Config, DEFAULT_BIND, DEFAULT_MAX_CLIENTS, DEFAULT_PORT}, dictionary::{Dictionary, DEFAULT_DATABASES}, expiry::{Reaper, ReaperConfig}, persistence::{Serializer, DEFAULT_STORAGE_PATH}, shutdown::Shutdown};
 use sap::{Parser, Argument};
 
 fn main() -> io::Result<()> {
     let mut parser = Parser::from_env().unwrap();
 
     let mut port: u16 = DEFAULT_PORT;
+    let mut addr: IpAddr = DEFAULT_BIND;
+    let mut unix_socket: Option<PathBuf> = None;
+    let mut unix_socket_perm: Option<u32> = None;
+    let mut config = Config::default();
+    let mut reaper_config = ReaperConfig::default();
+    let mut report_expired = false;
+    let mut databases = DEFAULT_DATABASES;
 
     while let Some(arg) = parser.forward().unwrap() {
         match arg {
@@ -16,6 +27,60 @@ fn main() -> io::Result<()> {
                 });
             }
 
+            Argument::Long("bind") => {
+                addr = parser.value().unwrap().parse().unwrap_or_else(|_e| {
+                    eprintln!("Bind address could not be parsed, reverting to default...");
+                    DEFAULT_BIND
+                });
+            }
+
+            Argument::Long("unix-socket") => {
+                unix_socket = Some(PathBuf::from(parser.value().unwrap()));
+            }
+
+            Argument::Long("unix-socket-perm") => {
+                // Octal, as with chmod
+                unix_socket_perm = match u32::from_str_radix(&parser.value().unwrap(), 8) {
+                    Ok(mode) if mode <= 0o777 => Some(mode),
+                    _ => {
+                        eprintln!("Unix socket permissions could not be parsed, keeping the default...");
+                        None
+                    }
+                };
+            }
+
+            Argument::Long("hz") => {
+                // 0 turns active expiration off
+                reaper_config.hz = parser.value().unwrap().parse().unwrap_or_else(|_e| {
+                    eprintln!("Expiration frequency could not be parsed, reverting to default...");
+                    ReaperConfig::default().hz
+                });
+            }
+
+            Argument::Long("report-expired") => {
+                report_expired = true;
+            }
+
+            Argument::Long("databases") => {
+                databases = match parser.value().unwrap().parse() {
+                    Ok(0) | Err(_) => {
+                        eprintln!("Database count could not be parsed, reverting to default...");
+                        DEFAULT_DATABASES
+                    },
+                    Ok(count) => count
+                };
+            }
+
+            Argument::Long("max-clients") => {
+                config.max_clients = match parser.value().unwrap().parse() {
+                    Ok(0) | Err(_) => {
+                        eprintln!("Max clients could not be parsed, reverting to default...");
+                        DEFAULT_MAX_CLIENTS
+                    },
+                    Ok(max) => max
+                };
+            }
+
             _ => {
                 eprintln!("Invalid arguments! Exiting...");
                 process::exit(-1);
@@ -23,8 +88,42 @@ fn main() -> io::Result<()> {
         }
     }
 
-    let mut dict = Dictionary::new();
-    run(&mut dict, &bind(Some(port)))?;
+    let dict = Dictionary::with_databases(databases).report_expired(report_expired);
+    let shutdown = Arc::new(Shutdown::default());
+    #[cfg(unix)]
+    shutdown.register_signals()?;
+
+    let reaper = match reaper_config.hz {
+        0 => None,
+        _ => Some(Reaper::start(&dict, reaper_config))
+    };
+
+    match unix_socket {
+        #[cfg(unix)]
+        Some(path) => {
+            run(&dict, &bind_unix(&path, unix_socket_perm), &config, &shutdown)?;
+            let _ = fs::remove_file(&path);
+        },
+        #[cfg(not(unix))]
+        Some(_) => {
+            eprintln!("Unix sockets are not supported on this platform! Exiting...");
+            process::exit(-1);
+        },
+        None => run(&dict, &bind(addr, Some(port)), &config, &shutdown)?
+    }
+
+    drop(reaper);
+
+    // A SAVE still in flight must not be cut off
+    dict.join_background_jobs();
+
+    if shutdown.should_save() {
+        let serializer = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH));
+        if let Err(e) = serializer.save_file_csv() {
+            eprintln!("Final snapshot could not be written: {e}");
+            process::exit(1);
+        }
+    }
 
     Ok(())
 }
@@ -41,7 +140,7 @@ fn _cli(dict: &mut Dictionary) {
             Ok(_n_read) => {
                 match line.parse::<Command>() {
                     Err(e) => {
-                        eprintln!("{}", e.to_string());
+                        eprintln!("{e}");
                         continue;
                     }
 
@@ -1,50 +1,609 @@
+use std::ops::Bound;
 use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use crate::errors::ParseError;
-use crate::command::Command;
+use crate::stream::StreamId;
+use crate::command::{BitFieldOperation, BitFieldOverflow, BitFieldType, BitOperation, BitRange, Command, ExpireCondition, GetExOption, ListEnd, ScanOptions, SetCondition, SetExpiration, SetOperation, SetOptions, StreamStart, XAddId, ZAddComparison, ZAddOptions, ZPopEnd, ZRangeBy, ZRangeOptions};
 
 impl FromStr for Command {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let words: Vec<&str> = s.split_whitespace().collect();
-        if words.len() == 0 {
+        Command::from_words(&words)
+    }
+}
+
+impl Command {
+    /// Parses the words of a RESP request, only string values may be binary
+    /// # Returns
+    /// - If any other word is not valid UTF-8, Err(ParseError::InvalidParameters)
+    pub fn from_request(mut words: Vec<Vec<u8>>) -> Result<Self, ParseError> {
+        let name = words.first().map(|name| name.to_ascii_uppercase());
+        let binary: fn(usize) -> bool = match name.as_deref() {
+            Some(b"SET" | b"GETSET" | b"APPEND") => |i| i == 2,
+            Some(b"SETRANGE") => |i| i == 3,
+            Some(b"MSET" | b"MSETNX") => |i| i >= 2 && i.is_multiple_of(2),
+            _ => |_| false
+        };
+
+        // Values are parsed as empty placeholders and swapped back in afterwards
+        let values: Vec<Vec<u8>> = words.iter_mut().enumerate()
+            .filter(|(i, _)| binary(*i))
+            .map(|(_, word)| std::mem::take(word))
+            .collect();
+        let text = words.iter()
+            .map(|word| std::str::from_utf8(word))
+            .collect::<Result<Vec<&str>, _>>()
+            .map_err(|_e| ParseError::InvalidParameters)?;
+
+        let mut command = Command::from_words(&text)?;
+        let slots: Vec<&mut Vec<u8>> = match &mut command {
+            Command::Set(_, value, _) | Command::Append(_, value) | Command::SetRange(_, _, value) => vec![value],
+            Command::MSet(pairs) | Command::MSetNx(pairs) => pairs.iter_mut().map(|(_, value)| value).collect(),
+            _ => Vec::new()
+        };
+        for (slot, value) in slots.into_iter().zip(values) {
+            *slot = value;
+        }
+        Ok(command)
+    }
+
+    /// Parses an already split command, e.g. the elements of a RESP array.
+    /// The command name is case-insensitive.
+    pub fn from_words(words: &[&str]) -> Result<Self, ParseError> {
+        if words.is_empty() {
             return Err(ParseError::IsEmpty);
         }
 
         use Command::*;
-        match words[0] {
+        match words[0].to_ascii_uppercase().as_str() {
             "SET" => {
-                if words.len() != 3 {
-                    return Err(ParseError::InvalidParameters);
+                if words.len() < 3 {
+                    Err(ParseError::InvalidParameters)
                 } else {
-                    Ok(Set(words[1].to_string(), words[2].to_string()))
+                    Ok(Set(words[1].to_string(), words[2].as_bytes().to_vec(), parse_set_options(&words[3..])?))
                 }
             },
             "GET" => {
                 if words.len() != 2 {
-                    return Err(ParseError::InvalidParameters);
+                    Err(ParseError::InvalidParameters)
                 } else {
                     Ok(Get(words[1].to_string()))
                 }
             },
-            "DEL" => {
+            // Same as SET with GET
+            "GETSET" => {
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Set(words[1].to_string(), words[2].as_bytes().to_vec(), SetOptions { get: true, ..Default::default() }))
+                }
+            },
+            "GETDEL" => {
                 if words.len() != 2 {
-                    return Err(ParseError::InvalidParameters);
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(GetDel(words[1].to_string()))
+                }
+            },
+            "GETEX" => {
+                if words.len() < 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(GetEx(words[1].to_string(), parse_getex_option(&words[2..])?))
+                }
+            },
+            "APPEND" => {
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Append(words[1].to_string(), words[2].as_bytes().to_vec()))
+                }
+            },
+            "STRLEN" => {
+                if words.len() != 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Strlen(words[1].to_string()))
+                }
+            },
+            "GETRANGE" => {
+                if words.len() != 4 {
+                    Err(ParseError::InvalidParameters)
                 } else {
-                    Ok(Del(words[1].to_string()))
+                    let start = words[2].parse::<i64>().map_err(|_e| ParseError::InvalidParameters)?;
+                    let end = words[3].parse::<i64>().map_err(|_e| ParseError::InvalidParameters)?;
+                    Ok(GetRange(words[1].to_string(), start, end))
+                }
+            },
+            "SETRANGE" => {
+                if words.len() != 4 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let offset = words[2].parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?;
+                    Ok(SetRange(words[1].to_string(), offset, words[3].as_bytes().to_vec()))
+                }
+            },
+            "DEL" => {
+                if words.len() < 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Del(to_keys(&words[1..])))
                 }
             },
             "EXISTS" => {
+                if words.len() < 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Exists(to_keys(&words[1..])))
+                }
+            },
+            "RENAME" | "RENAMENX" => {
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    match words[0].eq_ignore_ascii_case("RENAME") {
+                        true => Ok(Rename(words[1].to_string(), words[2].to_string())),
+                        false => Ok(RenameNx(words[1].to_string(), words[2].to_string()))
+                    }
+                }
+            },
+            "COPY" => {
+                let replace = words.len() == 4 && words[3].eq_ignore_ascii_case("REPLACE");
+                // A key can't be copied onto itself
+                if !(words.len() == 3 || replace) || words[1] == words[2] {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Copy(words[1].to_string(), words[2].to_string(), replace))
+                }
+            },
+            "TYPE" => {
                 if words.len() != 2 {
-                    return Err(ParseError::InvalidParameters);
+                    Err(ParseError::InvalidParameters)
                 } else {
-                    Ok(Exists(words[1].to_string()))
+                    Ok(Type(words[1].to_string()))
                 }
-            }
+            },
+            "LPUSH" | "RPUSH" => {
+                if words.len() < 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Push(words[1].to_string(), to_keys(&words[2..]), parse_list_end(words[0])))
+                }
+            },
+            "LPOP" | "RPOP" => {
+                match words.len() {
+                    2 => Ok(Pop(words[1].to_string(), None, parse_list_end(words[0]))),
+                    3 => {
+                        let count = words[2].parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?;
+                        Ok(Pop(words[1].to_string(), Some(count), parse_list_end(words[0])))
+                    },
+                    _ => Err(ParseError::InvalidParameters)
+                }
+            },
+            "LRANGE" | "LTRIM" => {
+                if words.len() != 4 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let start = words[2].parse::<i64>().map_err(|_e| ParseError::InvalidParameters)?;
+                    let end = words[3].parse::<i64>().map_err(|_e| ParseError::InvalidParameters)?;
+                    match words[0].eq_ignore_ascii_case("LRANGE") {
+                        true => Ok(LRange(words[1].to_string(), start, end)),
+                        false => Ok(LTrim(words[1].to_string(), start, end))
+                    }
+                }
+            },
+            "LLEN" => {
+                if words.len() != 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(LLen(words[1].to_string()))
+                }
+            },
+            "LINDEX" => {
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(LIndex(words[1].to_string(), words[2].parse::<i64>().map_err(|_e| ParseError::InvalidParameters)?))
+                }
+            },
+            "HSET" | "HMSET" => {
+                if words.len() < 4 || !words.len().is_multiple_of(2) {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let pairs = words[2..].chunks(2).map(|pair| (pair[0].to_string(), pair[1].to_string())).collect();
+                    match words[0].eq_ignore_ascii_case("HSET") {
+                        true => Ok(HSet(words[1].to_string(), pairs)),
+                        false => Ok(HMSet(words[1].to_string(), pairs))
+                    }
+                }
+            },
+            "HSETNX" => {
+                if words.len() != 4 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(HSetNx(words[1].to_string(), words[2].to_string(), words[3].to_string()))
+                }
+            },
+            "HGET" | "HEXISTS" => {
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    match words[0].eq_ignore_ascii_case("HGET") {
+                        true => Ok(HGet(words[1].to_string(), words[2].to_string())),
+                        false => Ok(HExists(words[1].to_string(), words[2].to_string()))
+                    }
+                }
+            },
+            "HMGET" | "HDEL" => {
+                if words.len() < 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    match words[0].eq_ignore_ascii_case("HMGET") {
+                        true => Ok(HMGet(words[1].to_string(), to_keys(&words[2..]))),
+                        false => Ok(HDel(words[1].to_string(), to_keys(&words[2..])))
+                    }
+                }
+            },
+            "HLEN" | "HKEYS" | "HVALS" | "HGETALL" => {
+                if words.len() != 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let key = words[1].to_string();
+                    match words[0].to_ascii_uppercase().as_str() {
+                        "HLEN" => Ok(HLen(key)),
+                        "HKEYS" => Ok(HKeys(key)),
+                        "HVALS" => Ok(HVals(key)),
+                        _ => Ok(HGetAll(key))
+                    }
+                }
+            },
+            "HINCRBY" => {
+                if words.len() != 4 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let by = words[3].parse::<i64>().map_err(|_e| ParseError::InvalidParameters)?;
+                    Ok(HIncrBy(words[1].to_string(), words[2].to_string(), by))
+                }
+            },
+            "HINCRBYFLOAT" => {
+                if words.len() != 4 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    match words[3].parse::<f64>() {
+                        Ok(by) if by.is_finite() => Ok(HIncrByFloat(words[1].to_string(), words[2].to_string(), by)),
+                        _ => Err(ParseError::InvalidParameters)
+                    }
+                }
+            },
+            "HSCAN" => {
+                if words.len() < 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let cursor = words[2].parse::<u64>().map_err(|_e| ParseError::InvalidParameters)?;
+                    let options = parse_scan_options(&words[3..])?;
+                    match options.type_name {
+                        Some(_) => Err(ParseError::InvalidParameters),
+                        None => Ok(HScan(words[1].to_string(), cursor, options))
+                    }
+                }
+            },
+            "SADD" | "SREM" => {
+                if words.len() < 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    match words[0].eq_ignore_ascii_case("SADD") {
+                        true => Ok(SAdd(words[1].to_string(), to_keys(&words[2..]))),
+                        false => Ok(SRem(words[1].to_string(), to_keys(&words[2..])))
+                    }
+                }
+            },
+            "SMEMBERS" | "SCARD" => {
+                if words.len() != 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    match words[0].eq_ignore_ascii_case("SMEMBERS") {
+                        true => Ok(SMembers(words[1].to_string())),
+                        false => Ok(SCard(words[1].to_string()))
+                    }
+                }
+            },
+            "SISMEMBER" => {
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(SIsMember(words[1].to_string(), words[2].to_string()))
+                }
+            },
+            "SINTER" | "SUNION" | "SDIFF" => {
+                if words.len() < 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(SetOp(parse_set_operation(words[0]), to_keys(&words[1..])))
+                }
+            },
+            "SINTERSTORE" | "SUNIONSTORE" | "SDIFFSTORE" => {
+                if words.len() < 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(SetOpStore(parse_set_operation(words[0]), words[1].to_string(), to_keys(&words[2..])))
+                }
+            },
+            "SRANDMEMBER" => {
+                match words.len() {
+                    2 => Ok(SRandMember(words[1].to_string(), None)),
+                    3 => {
+                        let count = words[2].parse::<i64>().map_err(|_e| ParseError::InvalidParameters)?;
+                        Ok(SRandMember(words[1].to_string(), Some(count)))
+                    },
+                    _ => Err(ParseError::InvalidParameters)
+                }
+            },
+            "SPOP" => {
+                match words.len() {
+                    2 => Ok(SPop(words[1].to_string(), None)),
+                    3 => {
+                        let count = words[2].parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?;
+                        Ok(SPop(words[1].to_string(), Some(count)))
+                    },
+                    _ => Err(ParseError::InvalidParameters)
+                }
+            },
+            "ZADD" => {
+                if words.len() < 4 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    parse_zadd(words[1], &words[2..])
+                }
+            },
+            "ZINCRBY" => {
+                if words.len() != 4 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(ZIncrBy(words[1].to_string(), parse_score(words[2])?, words[3].to_string()))
+                }
+            },
+            "ZRANGE" => {
+                if words.len() < 4 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(ZRange(words[1].to_string(), parse_zrange_options(words[2], words[3], &words[4..])?))
+                }
+            },
+            // Same as ZRANGE with BYSCORE
+            "ZRANGEBYSCORE" => {
+                if words.len() < 4 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let options = [&["BYSCORE"], &words[4..]].concat();
+                    Ok(ZRange(words[1].to_string(), parse_zrange_options(words[2], words[3], &options)?))
+                }
+            },
+            "ZRANK" | "ZSCORE" => {
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    match words[0].eq_ignore_ascii_case("ZRANK") {
+                        true => Ok(ZRank(words[1].to_string(), words[2].to_string())),
+                        false => Ok(ZScore(words[1].to_string(), words[2].to_string()))
+                    }
+                }
+            },
+            "ZCARD" => {
+                if words.len() != 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(ZCard(words[1].to_string()))
+                }
+            },
+            "ZREM" => {
+                if words.len() < 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(ZRem(words[1].to_string(), to_keys(&words[2..])))
+                }
+            },
+            "ZPOPMIN" | "ZPOPMAX" => {
+                let end = match words[0].eq_ignore_ascii_case("ZPOPMIN") {
+                    true => ZPopEnd::Min,
+                    false => ZPopEnd::Max
+                };
+                match words.len() {
+                    2 => Ok(ZPop(words[1].to_string(), 1, end)),
+                    3 => {
+                        let count = words[2].parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?;
+                        Ok(ZPop(words[1].to_string(), count, end))
+                    },
+                    _ => Err(ParseError::InvalidParameters)
+                }
+            },
+            "XADD" => {
+                if words.len() < 5 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    parse_xadd(words[1], &words[2..])
+                }
+            },
+            "XLEN" => {
+                if words.len() != 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(XLen(words[1].to_string()))
+                }
+            },
+            "XRANGE" | "XREVRANGE" => {
+                let count = match words.len() {
+                    4 => None,
+                    6 if words[4].eq_ignore_ascii_case("COUNT") => Some(words[5].parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?),
+                    _ => return Err(ParseError::InvalidParameters)
+                };
+                // XREVRANGE takes the end first
+                let rev = words[0].eq_ignore_ascii_case("XREVRANGE");
+                let (start, end) = if rev { (words[3], words[2]) } else { (words[2], words[3]) };
+                Ok(XRange(words[1].to_string(), parse_stream_bound(start, 0)?, parse_stream_bound(end, u64::MAX)?, count, rev))
+            },
+            "XREAD" => {
+                let (count, streams) = parse_xread_options(&words[1..])?;
+                if streams.iter().any(|(_, start)| *start == StreamStart::New) {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(XRead(streams, count))
+                }
+            },
+            "XREADGROUP" => {
+                if words.len() < 4 || !words[1].eq_ignore_ascii_case("GROUP") {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let (count, streams) = parse_xread_options(&words[4..])?;
+                    if streams.iter().any(|(_, start)| *start == StreamStart::Last) {
+                        Err(ParseError::InvalidParameters)
+                    } else {
+                        Ok(XReadGroup(words[2].to_string(), words[3].to_string(), streams, count))
+                    }
+                }
+            },
+            "XGROUP" => {
+                match (words.get(1).map(|word| word.to_ascii_uppercase()).as_deref(), words.len()) {
+                    (Some("CREATE"), 5 | 6) => {
+                        let make_stream = match words.get(5) {
+                            Some(word) if word.eq_ignore_ascii_case("MKSTREAM") => true,
+                            Some(_) => return Err(ParseError::InvalidParameters),
+                            None => false
+                        };
+                        let start = match parse_stream_start(words[4])? {
+                            StreamStart::New => return Err(ParseError::InvalidParameters),
+                            start => start
+                        };
+                        Ok(XGroupCreate(words[2].to_string(), words[3].to_string(), start, make_stream))
+                    },
+                    (Some("DESTROY"), 4) => Ok(XGroupDestroy(words[2].to_string(), words[3].to_string())),
+                    _ => Err(ParseError::InvalidParameters)
+                }
+            },
+            "XACK" => {
+                if words.len() < 4 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(XAck(words[1].to_string(), words[2].to_string(), parse_stream_ids(&words[3..])?))
+                }
+            },
+            "XPENDING" => {
+                match words.len() {
+                    3 => Ok(XPending(words[1].to_string(), words[2].to_string())),
+                    6 | 7 => {
+                        let start = parse_stream_bound(words[3], 0)?;
+                        let end = parse_stream_bound(words[4], u64::MAX)?;
+                        let count = words[5].parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?;
+                        let consumer = words.get(6).map(|consumer| consumer.to_string());
+                        Ok(XPendingRange(words[1].to_string(), words[2].to_string(), start, end, count, consumer))
+                    },
+                    _ => Err(ParseError::InvalidParameters)
+                }
+            },
+            "XCLAIM" => {
+                if words.len() < 6 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let min_idle = Duration::from_millis(words[4].parse::<u64>().map_err(|_e| ParseError::InvalidParameters)?);
+                    Ok(XClaim(words[1].to_string(), words[2].to_string(), words[3].to_string(), min_idle, parse_stream_ids(&words[5..])?))
+                }
+            },
+            "SETBIT" => {
+                if words.len() != 4 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let offset = words[2].parse::<u64>().map_err(|_e| ParseError::InvalidParameters)?;
+                    Ok(SetBit(words[1].to_string(), offset, parse_bit(words[3])?))
+                }
+            },
+            "GETBIT" => {
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let offset = words[2].parse::<u64>().map_err(|_e| ParseError::InvalidParameters)?;
+                    Ok(GetBit(words[1].to_string(), offset))
+                }
+            },
+            "BITCOUNT" => {
+                if words.len() < 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(BitCount(words[1].to_string(), parse_bit_range(&words[2..], true)?))
+                }
+            },
+            "BITPOS" => {
+                if words.len() < 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(BitPos(words[1].to_string(), parse_bit(words[2])?, parse_bit_range(&words[3..], false)?))
+                }
+            },
+            "BITOP" => {
+                let operation = match words.get(1).map(|word| word.to_ascii_uppercase()).as_deref() {
+                    Some("AND") => BitOperation::And,
+                    Some("OR") => BitOperation::Or,
+                    Some("XOR") => BitOperation::Xor,
+                    Some("NOT") if words.len() == 4 => BitOperation::Not,
+                    _ => return Err(ParseError::InvalidParameters)
+                };
+                if words.len() < 4 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(BitOp(operation, words[2].to_string(), to_keys(&words[3..])))
+                }
+            },
+            "BITFIELD" => {
+                if words.len() < 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(BitField(words[1].to_string(), parse_bitfield(&words[2..])?))
+                }
+            },
+            "PFADD" => {
+                if words.len() < 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(PfAdd(words[1].to_string(), to_keys(&words[2..])))
+                }
+            },
+            "PFCOUNT" => {
+                if words.len() < 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(PfCount(to_keys(&words[1..])))
+                }
+            },
+            "PFMERGE" => {
+                if words.len() < 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(PfMerge(words[1].to_string(), to_keys(&words[2..])))
+                }
+            },
+            "MGET" => {
+                if words.len() < 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(MGet(to_keys(&words[1..])))
+                }
+            },
+            "MSET" | "MSETNX" => {
+                if words.len() < 3 || words.len().is_multiple_of(2) {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let pairs = words[1..].chunks(2).map(|pair| (pair[0].to_string(), pair[1].as_bytes().to_vec())).collect();
+                    match words[0].eq_ignore_ascii_case("MSET") {
+                        true => Ok(MSet(pairs)),
+                        false => Ok(MSetNx(pairs))
+                    }
+                }
+            },
             "EXPIRE" => {
                 // NOTE: humantime format is standard.
+                let (words, condition) = split_expire_condition(words);
                 if words.len() < 3 || words.len() > 5 {
-                    return Err(ParseError::InvalidParameters);
+                    Err(ParseError::InvalidParameters)
                 } else {
                     let humantime_part = match words.get(2..) {
                         Some(humantime_part) => humantime_part,
@@ -54,33 +613,174 @@ impl FromStr for Command {
                     };
                     let humantime_string = humantime_part.join(" ");
 
+                    // Plain integers are seconds, as sent by Redis clients
+                    if let Ok(seconds) = humantime_string.parse::<u64>() {
+                        return Ok(Expire(words[1].to_string(), Duration::from_secs(seconds), condition));
+                    }
+
                     Ok(Expire(words[1].to_string(), humantime_string.parse::<humantime::Duration>().map_err(|_e| {
                         ParseError::InvalidParameters
-                    })?.into()))
+                    })?.into(), condition))
                 }
-            }
+            },
+            "EXPIREAT" => {
+                let (words, condition) = split_expire_condition(words);
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(ExpireAt(words[1].to_string(), parse_timestamp(words[2])?, condition))
+                }
+            },
+            "PEXPIREAT" => {
+                let (words, condition) = split_expire_condition(words);
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(ExpireAt(words[1].to_string(), parse_unix_millis(words[2])?, condition))
+                }
+            },
             "INCR" => {
                 if words.len() != 2 {
-                    return Err(ParseError::InvalidParameters);
+                    Err(ParseError::InvalidParameters)
                 } else {
                     Ok(Incr(words[1].to_string()))
                 }
             }
             "DECR" => {
                 if words.len() != 2 {
-                    return Err(ParseError::InvalidParameters);
+                    Err(ParseError::InvalidParameters)
                 } else {
                     Ok(Decr(words[1].to_string()))
                 }
             },
-            "CLEAR" => {
+            "INCRBY" | "DECRBY" => {
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let by = words[2].parse::<i64>().map_err(|_e| ParseError::InvalidParameters)?;
+                    match words[0].eq_ignore_ascii_case("INCRBY") {
+                        true => Ok(IncrBy(words[1].to_string(), by)),
+                        false => Ok(DecrBy(words[1].to_string(), by))
+                    }
+                }
+            },
+            "INCRBYFLOAT" => {
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    match words[2].parse::<f64>() {
+                        Ok(by) if by.is_finite() => Ok(IncrByFloat(words[1].to_string(), by)),
+                        _ => Err(ParseError::InvalidParameters)
+                    }
+                }
+            },
+            "CLEAR" | "FLUSHALL" => {
                 Ok(Clear)
             },
+            "FLUSHDB" => {
+                Ok(FlushDb)
+            },
+            "SELECT" => {
+                if words.len() != 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Select(words[1].parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?))
+                }
+            },
+            "SWAPDB" => {
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let first = words[1].parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?;
+                    let second = words[2].parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?;
+                    Ok(SwapDb(first, second))
+                }
+            },
+            "MOVE" => {
+                if words.len() != 3 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Move(words[1].to_string(), words[2].parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?))
+                }
+            },
             "SAVE" => {
                 Ok(Save)
             },
             "LOAD" => {
                 Ok(Load)
+            },
+            "PING" => {
+                match words.len() {
+                    1 => Ok(Ping(None)),
+                    2 => Ok(Ping(Some(words[1].to_string()))),
+                    _ => Err(ParseError::InvalidParameters)
+                }
+            },
+            "HELLO" => {
+                match words.len() {
+                    1 => Ok(Hello(None)),
+                    2 => match words[1].parse::<u8>() {
+                        Ok(version @ (2 | 3)) => Ok(Hello(Some(version))),
+                        _ => Err(ParseError::InvalidParameters)
+                    },
+                    _ => Err(ParseError::InvalidParameters)
+                }
+            },
+            "SHUTDOWN" => {
+                match words.len() {
+                    1 => Ok(Shutdown(true)),
+                    2 => match words[1].to_ascii_uppercase().as_str() {
+                        "SAVE" => Ok(Shutdown(true)),
+                        "NOSAVE" => Ok(Shutdown(false)),
+                        _ => Err(ParseError::InvalidParameters)
+                    },
+                    _ => Err(ParseError::InvalidParameters)
+                }
+            },
+            "INFO" => {
+                Ok(Info)
+            },
+            "KEYS" => {
+                if words.len() != 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Keys(words[1].to_string()))
+                }
+            },
+            "SCAN" => {
+                if words.len() < 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    let cursor = words[1].parse::<u64>().map_err(|_e| ParseError::InvalidParameters)?;
+                    Ok(Scan(cursor, parse_scan_options(&words[2..])?))
+                }
+            },
+            "DBSIZE" => {
+                Ok(DbSize)
+            },
+            "RANDOMKEY" => {
+                Ok(RandomKey)
+            },
+            "TTL" => {
+                if words.len() != 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Ttl(words[1].to_string()))
+                }
+            },
+            "PTTL" => {
+                if words.len() != 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Pttl(words[1].to_string()))
+                }
+            },
+            "PERSIST" => {
+                if words.len() != 2 {
+                    Err(ParseError::InvalidParameters)
+                } else {
+                    Ok(Persist(words[1].to_string()))
+                }
             }
 
             _ => Err(ParseError::NotACommand)
@@ -88,17 +788,456 @@ impl FromStr for Command {
     }
 }
 
+/// Options following `SCAN cursor`, in any order
+fn parse_scan_options(words: &[&str]) -> Result<ScanOptions, ParseError> {
+    let mut options = ScanOptions::default();
+
+    for pair in words.chunks(2) {
+        let [option, argument] = pair else {
+            return Err(ParseError::InvalidParameters);
+        };
+
+        match option.to_ascii_uppercase().as_str() {
+            "MATCH" => options.pattern = Some(argument.to_string()),
+            "COUNT" => {
+                options.count = match argument.parse::<usize>() {
+                    Ok(count) if count > 0 => count,
+                    _ => return Err(ParseError::InvalidParameters)
+                };
+            },
+            "TYPE" => options.type_name = Some(argument.to_ascii_lowercase()),
+            _ => return Err(ParseError::InvalidParameters)
+        }
+    }
+
+    Ok(options)
+}
+
+/// From the first letter of the command name, e.g. LPUSH or RPOP
+fn parse_list_end(name: &str) -> ListEnd {
+    match name.starts_with(['L', 'l']) {
+        true => ListEnd::Left,
+        false => ListEnd::Right
+    }
+}
+
+/// ZADD options followed by `score member` pairs, a single one with INCR
+fn parse_zadd(key: &str, words: &[&str]) -> Result<Command, ParseError> {
+    let mut options = ZAddOptions::default();
+    let mut incr = false;
+
+    let mut rest = words;
+    while let Some((word, tail)) = rest.split_first() {
+        let (condition, comparison) = (options.condition, options.comparison);
+        match word.to_ascii_uppercase().as_str() {
+            "NX" if condition.is_none() && comparison.is_none() => options.condition = Some(SetCondition::Nx),
+            "XX" if condition.is_none() => options.condition = Some(SetCondition::Xx),
+            "GT" if comparison.is_none() && condition != Some(SetCondition::Nx) => options.comparison = Some(ZAddComparison::Gt),
+            "LT" if comparison.is_none() && condition != Some(SetCondition::Nx) => options.comparison = Some(ZAddComparison::Lt),
+            "CH" => options.changed = true,
+            "INCR" => incr = true,
+            _ => break
+        }
+        rest = tail;
+    }
+
+    if rest.is_empty() || !rest.len().is_multiple_of(2) {
+        return Err(ParseError::InvalidParameters);
+    }
+    let pairs = rest.chunks(2)
+        .map(|pair| Ok((parse_score(pair[0])?, pair[1].to_string())))
+        .collect::<Result<Vec<_>, ParseError>>()?;
+
+    match incr {
+        true if pairs.len() == 1 => {
+            let (by, member) = pairs.into_iter().next().unwrap();
+            Ok(Command::ZAddIncr(key.to_string(), by, member, options))
+        },
+        true => Err(ParseError::InvalidParameters),
+        false => Ok(Command::ZAdd(key.to_string(), pairs, options))
+    }
+}
+
+/// Anything `f64` parses but NaN, `inf`, `+inf` and `-inf` included
+fn parse_score(word: &str) -> Result<f64, ParseError> {
+    match word.parse::<f64>() {
+        Ok(score) if !score.is_nan() => Ok(score),
+        _ => Err(ParseError::InvalidParameters)
+    }
+}
+
+/// The options following `ZRANGE key start stop`
+fn parse_zrange_options(start: &str, stop: &str, words: &[&str]) -> Result<ZRangeOptions, ParseError> {
+    let (mut by_score, mut by_lex, mut rev, mut with_scores) = (false, false, false, false);
+    let mut limit = None;
+
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        match word.to_ascii_uppercase().as_str() {
+            "BYSCORE" => by_score = true,
+            "BYLEX" => by_lex = true,
+            "REV" => rev = true,
+            "WITHSCORES" => with_scores = true,
+            "LIMIT" => {
+                let (Some(offset), Some(count)) = (words.next(), words.next()) else {
+                    return Err(ParseError::InvalidParameters);
+                };
+                let offset = offset.parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?;
+                // A negative count means every member from the offset on
+                let count = count.parse::<i64>().map_err(|_e| ParseError::InvalidParameters)?;
+                limit = Some((offset, usize::try_from(count).unwrap_or(usize::MAX)));
+            },
+            _ => return Err(ParseError::InvalidParameters)
+        }
+    }
+
+    // Lex ranges have no scores to show
+    if by_lex && with_scores {
+        return Err(ParseError::InvalidParameters);
+    }
+
+    // Scores and lex ranges are given from max to min with REV
+    let (min, max) = if rev { (stop, start) } else { (start, stop) };
+    let by = match (by_score, by_lex) {
+        (true, false) => ZRangeBy::Score(parse_score_bound(min)?, parse_score_bound(max)?),
+        (false, true) => ZRangeBy::Lex(parse_lex_bound(min)?, parse_lex_bound(max)?),
+        (false, false) if limit.is_none() => {
+            let start = start.parse::<i64>().map_err(|_e| ParseError::InvalidParameters)?;
+            let stop = stop.parse::<i64>().map_err(|_e| ParseError::InvalidParameters)?;
+            ZRangeBy::Rank(start, stop)
+        },
+        _ => return Err(ParseError::InvalidParameters)
+    };
+
+    Ok(ZRangeOptions { by, rev, limit, with_scores })
+}
+
+/// `(` makes a score exclusive
+fn parse_score_bound(word: &str) -> Result<Bound<f64>, ParseError> {
+    match word.strip_prefix('(') {
+        Some(score) => Ok(Bound::Excluded(parse_score(score)?)),
+        None => Ok(Bound::Included(parse_score(word)?))
+    }
+}
+
+/// `[member` or `(member`, `-` and `+` leaving that end open
+fn parse_lex_bound(word: &str) -> Result<Bound<String>, ParseError> {
+    match word.split_at_checked(1) {
+        Some(("-" | "+", "")) => Ok(Bound::Unbounded),
+        Some(("[", member)) => Ok(Bound::Included(member.to_string())),
+        Some(("(", member)) => Ok(Bound::Excluded(member.to_string())),
+        _ => Err(ParseError::InvalidParameters)
+    }
+}
+
+/// `[MAXLEN [~|=] n] id field value [field value ...]` following `XADD key`,
+/// an approximate MAXLEN trims exactly
+fn parse_xadd(key: &str, words: &[&str]) -> Result<Command, ParseError> {
+    let mut words = words;
+    let mut max_len = None;
+    if words[0].eq_ignore_ascii_case("MAXLEN") {
+        words = match words.get(1) {
+            Some(&"~" | &"=") => &words[2..],
+            _ => &words[1..]
+        };
+        let (len, rest) = words.split_first().ok_or(ParseError::InvalidParameters)?;
+        max_len = Some(len.parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?);
+        words = rest;
+    }
+
+    let Some((id, fields)) = words.split_first() else {
+        return Err(ParseError::InvalidParameters);
+    };
+    let id = match (*id, id.strip_suffix("-*")) {
+        ("*", _) => XAddId::Auto,
+        (_, Some(ms)) => XAddId::AutoSeq(ms.parse::<u64>().map_err(|_e| ParseError::InvalidParameters)?),
+        (id, None) => XAddId::Explicit(StreamId::parse(id, 0).ok_or(ParseError::InvalidParameters)?)
+    };
+
+    if fields.is_empty() || !fields.len().is_multiple_of(2) {
+        return Err(ParseError::InvalidParameters);
+    }
+    let fields = fields.chunks(2).map(|pair| (pair[0].to_string(), pair[1].to_string())).collect();
+    Ok(Command::XAdd(key.to_string(), id, fields, max_len))
+}
+
+/// `-` and `+` leave that end open, `(` makes an ID exclusive,
+/// an ID without a sequence number gets `default_seq`
+fn parse_stream_bound(word: &str, default_seq: u64) -> Result<Bound<StreamId>, ParseError> {
+    let parse = |id: &str| StreamId::parse(id, default_seq).ok_or(ParseError::InvalidParameters);
+    match word {
+        "-" | "+" => Ok(Bound::Unbounded),
+        _ => match word.strip_prefix('(') {
+            Some(id) => Ok(Bound::Excluded(parse(id)?)),
+            None => Ok(Bound::Included(parse(word)?))
+        }
+    }
+}
+
+/// An ID, `$` or `>`
+fn parse_stream_start(word: &str) -> Result<StreamStart, ParseError> {
+    match word {
+        "$" => Ok(StreamStart::Last),
+        ">" => Ok(StreamStart::New),
+        id => Ok(StreamStart::After(StreamId::parse(id, 0).ok_or(ParseError::InvalidParameters)?))
+    }
+}
+
+fn parse_stream_ids(words: &[&str]) -> Result<Vec<StreamId>, ParseError> {
+    words.iter().map(|word| StreamId::parse(word, 0).ok_or(ParseError::InvalidParameters)).collect()
+}
+
+/// Each key read by XREAD or XREADGROUP with where to read from
+type StreamStarts = Vec<(String, StreamStart)>;
+
+/// `[COUNT n] STREAMS key [key ...] id [id ...]`, BLOCK is not supported
+/// # Returns
+/// COUNT and the streams
+fn parse_xread_options(words: &[&str]) -> Result<(Option<usize>, StreamStarts), ParseError> {
+    let mut count = None;
+    let mut words = words;
+    loop {
+        match words.first().map(|word| word.to_ascii_uppercase()).as_deref() {
+            Some("COUNT") if words.len() > 1 => {
+                count = Some(words[1].parse::<usize>().map_err(|_e| ParseError::InvalidParameters)?);
+                words = &words[2..];
+            },
+            Some("STREAMS") => break,
+            _ => return Err(ParseError::InvalidParameters)
+        }
+    }
+
+    let streams = &words[1..];
+    if streams.is_empty() || !streams.len().is_multiple_of(2) {
+        return Err(ParseError::InvalidParameters);
+    }
+    let (keys, starts) = streams.split_at(streams.len() / 2);
+    let streams = keys.iter().zip(starts)
+        .map(|(key, start)| Ok((key.to_string(), parse_stream_start(start)?)))
+        .collect::<Result<_, ParseError>>()?;
+
+    Ok((count, streams))
+}
+
+fn parse_bit(word: &str) -> Result<bool, ParseError> {
+    match word {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(ParseError::InvalidParameters)
+    }
+}
+
+/// `[start [end [BYTE|BIT]]]`, BITCOUNT requires an end with a start
+fn parse_bit_range(words: &[&str], end_required: bool) -> Result<Option<BitRange>, ParseError> {
+    let number = |word: &str| word.parse::<i64>().map_err(|_e| ParseError::InvalidParameters);
+    let bits = match words.get(2).map(|word| word.to_ascii_uppercase()).as_deref() {
+        None | Some("BYTE") => false,
+        Some("BIT") => true,
+        Some(_) => return Err(ParseError::InvalidParameters)
+    };
+
+    match words {
+        [] => Ok(None),
+        [start] if !end_required => Ok(Some(BitRange { start: number(start)?, end: None, bits })),
+        [start, end] | [start, end, _] => Ok(Some(BitRange { start: number(start)?, end: Some(number(end)?), bits })),
+        _ => Err(ParseError::InvalidParameters)
+    }
+}
+
+/// The sub-commands following `BITFIELD key`, there may be none
+fn parse_bitfield(words: &[&str]) -> Result<Vec<BitFieldOperation>, ParseError> {
+    let mut operations = Vec::new();
+    let mut words = words;
+    while let Some((name, rest)) = words.split_first() {
+        let name = name.to_ascii_uppercase();
+        let (operation, consumed) = match name.as_str() {
+            "GET" if rest.len() >= 2 => {
+                let field = parse_bitfield_type(rest[0])?;
+                (BitFieldOperation::Get(field, parse_bitfield_offset(rest[1], field)?), 2)
+            },
+            "SET" | "INCRBY" if rest.len() >= 3 => {
+                let field = parse_bitfield_type(rest[0])?;
+                let offset = parse_bitfield_offset(rest[1], field)?;
+                let value = rest[2].parse::<i64>().map_err(|_e| ParseError::InvalidParameters)?;
+                match name.as_str() {
+                    "SET" => (BitFieldOperation::Set(field, offset, value), 3),
+                    _ => (BitFieldOperation::IncrBy(field, offset, value), 3)
+                }
+            },
+            "OVERFLOW" if !rest.is_empty() => {
+                let overflow = match rest[0].to_ascii_uppercase().as_str() {
+                    "WRAP" => BitFieldOverflow::Wrap,
+                    "SAT" => BitFieldOverflow::Sat,
+                    "FAIL" => BitFieldOverflow::Fail,
+                    _ => return Err(ParseError::InvalidParameters)
+                };
+                (BitFieldOperation::Overflow(overflow), 1)
+            },
+            _ => return Err(ParseError::InvalidParameters)
+        };
+
+        operations.push(operation);
+        words = &rest[consumed..];
+    }
+
+    Ok(operations)
+}
+
+/// `i1` to `i64` or `u1` to `u63`
+fn parse_bitfield_type(word: &str) -> Result<BitFieldType, ParseError> {
+    let (signed, bits) = match word.split_at_checked(1) {
+        Some(("i" | "I", bits)) => (true, bits),
+        Some(("u" | "U", bits)) => (false, bits),
+        _ => return Err(ParseError::InvalidParameters)
+    };
+    let max_bits = if signed { 64 } else { 63 };
+    match bits.parse::<u32>() {
+        Ok(bits) if (1..=max_bits).contains(&bits) => Ok(BitFieldType { signed, bits }),
+        _ => Err(ParseError::InvalidParameters)
+    }
+}
+
+/// A bit offset, or `#n` for the n-th field of that type
+fn parse_bitfield_offset(word: &str, field: BitFieldType) -> Result<u64, ParseError> {
+    let offset = match word.strip_prefix('#') {
+        Some(index) => index.parse::<u64>().ok().and_then(|index| index.checked_mul(field.bits as u64)),
+        None => word.parse::<u64>().ok()
+    };
+
+    offset.ok_or(ParseError::InvalidParameters)
+}
+
+/// From the command name, e.g. SINTER or SDIFFSTORE
+fn parse_set_operation(name: &str) -> SetOperation {
+    match name[1..].to_ascii_uppercase() {
+        name if name.starts_with("INTER") => SetOperation::Inter,
+        name if name.starts_with("UNION") => SetOperation::Union,
+        _ => SetOperation::Diff
+    }
+}
+
+fn to_keys(words: &[&str]) -> Vec<String> {
+    words.iter().map(|word| word.to_string()).collect()
+}
+
+/// Options following `SET key value`, in any order
+fn parse_set_options(words: &[&str]) -> Result<SetOptions, ParseError> {
+    let mut options = SetOptions::default();
+    let mut words = words.iter();
+
+    while let Some(word) = words.next() {
+        let option = word.to_ascii_uppercase();
+        match option.as_str() {
+            "NX" | "XX" => {
+                if options.condition.is_some() {
+                    return Err(ParseError::InvalidParameters);
+                }
+                options.condition = Some(match option.as_str() {
+                    "NX" => SetCondition::Nx,
+                    _ => SetCondition::Xx
+                });
+            },
+            "GET" => options.get = true,
+            "KEEPTTL" | "EX" | "PX" | "EXAT" | "PXAT" => {
+                if options.expiration.is_some() {
+                    return Err(ParseError::InvalidParameters);
+                }
+                if option == "KEEPTTL" {
+                    options.expiration = Some(SetExpiration::Keep);
+                    continue;
+                }
+
+                let argument = words.next().ok_or(ParseError::InvalidParameters)?;
+                options.expiration = Some(parse_expiration(&option, argument)?);
+            },
+            _ => return Err(ParseError::InvalidParameters)
+        }
+    }
+
+    Ok(options)
+}
+
+/// GETEX \[EX s|PX ms|EXAT ts|PXAT ms|PERSIST\]
+fn parse_getex_option(words: &[&str]) -> Result<Option<GetExOption>, ParseError> {
+    match words {
+        [] => Ok(None),
+        [option] if option.eq_ignore_ascii_case("PERSIST") => Ok(Some(GetExOption::Persist)),
+        [option, argument] => {
+            let option = option.to_ascii_uppercase();
+            match option.as_str() {
+                "EX" | "PX" | "EXAT" | "PXAT" => Ok(Some(GetExOption::Expire(parse_expiration(&option, argument)?))),
+                _ => Err(ParseError::InvalidParameters)
+            }
+        },
+        _ => Err(ParseError::InvalidParameters)
+    }
+}
+
+/// The argument of an uppercase EX, PX, EXAT or PXAT `option`
+fn parse_expiration(option: &str, argument: &str) -> Result<SetExpiration, ParseError> {
+    match option {
+        "EX" => Ok(SetExpiration::In(parse_set_lifetime(argument, Duration::from_secs)?)),
+        "PX" => Ok(SetExpiration::In(parse_set_lifetime(argument, Duration::from_millis)?)),
+        "EXAT" => Ok(SetExpiration::At(parse_timestamp(argument)?)),
+        "PXAT" => Ok(SetExpiration::At(parse_unix_millis(argument)?)),
+        _ => Err(ParseError::InvalidParameters)
+    }
+}
+
+/// A positive integer in `unit`, or a single humantime word such as `10m`
+fn parse_set_lifetime(s: &str, unit: fn(u64) -> Duration) -> Result<Duration, ParseError> {
+    let lifetime = match s.parse::<u64>() {
+        Ok(amount) => unit(amount),
+        Err(_) => s.parse::<humantime::Duration>().map_err(|_e| ParseError::InvalidParameters)?.into()
+    };
+
+    match lifetime.is_zero() {
+        true => Err(ParseError::InvalidParameters),
+        false => Ok(lifetime)
+    }
+}
+
+/// Splits a trailing NX, XX, GT or LT off of an expiration command
+fn split_expire_condition<'a>(words: &'a [&'a str]) -> (&'a [&'a str], Option<ExpireCondition>) {
+    let condition = match words.last().map(|word| word.to_ascii_uppercase()) {
+        Some(word) if word == "NX" => ExpireCondition::Nx,
+        Some(word) if word == "XX" => ExpireCondition::Xx,
+        Some(word) if word == "GT" => ExpireCondition::Gt,
+        Some(word) if word == "LT" => ExpireCondition::Lt,
+        _ => return (words, None)
+    };
+
+    (&words[..words.len() - 1], Some(condition))
+}
+
+/// An RFC 3339 timestamp, as written by `Serializer`, or unix seconds
+fn parse_timestamp(s: &str) -> Result<SystemTime, ParseError> {
+    if let Ok(seconds) = s.parse::<u64>() {
+        return UNIX_EPOCH.checked_add(Duration::from_secs(seconds)).ok_or(ParseError::InvalidParameters);
+    }
+
+    match s.parse::<humantime::Timestamp>() {
+        Ok(timestamp) => Ok(timestamp.into()),
+        Err(_) => Err(ParseError::InvalidParameters)
+    }
+}
+
+/// Unix milliseconds, within what `SystemTime` can hold
+fn parse_unix_millis(s: &str) -> Result<SystemTime, ParseError> {
+    let millis = s.parse::<u64>().map_err(|_e| ParseError::InvalidParameters)?;
+    UNIX_EPOCH.checked_add(Duration::from_millis(millis)).ok_or(ParseError::InvalidParameters)
+}
+
 #[cfg(test)]
 mod parsing {
     use super::*;
-    use std::time::Duration;
 
     #[test]
     fn expire_single_var() {
         let s = "EXPIRE alex 3s";
         let com = s.parse::<Command>();
 
-        assert_eq!(com, Ok(Command::Expire("alex".to_string(), Duration::from_secs(3))));
+        assert_eq!(com, Ok(Command::Expire("alex".to_string(), Duration::from_secs(3), None)));
     }
 
     #[test]
@@ -109,7 +1248,7 @@ mod parsing {
         let com = String::from("SET ") + key + " " + value;
         let com = com.parse::<Command>();
 
-        assert_eq!(com, Ok(Command::Set("metanoia".to_string(), "19".to_string())));
+        assert_eq!(com, Ok(Command::Set("metanoia".to_string(), b"19".to_vec(), SetOptions::default())));
     }
 
     #[test]
@@ -129,7 +1268,7 @@ mod parsing {
         let com = String::from("DEL ") + key;
         let com = com.parse::<Command>();
 
-        assert_eq!(com, Ok(Command::Del("metanoia".to_string())));
+        assert_eq!(com, Ok(Command::Del(vec!["metanoia".to_string()])));
     }
 
     #[test]
@@ -139,7 +1278,7 @@ mod parsing {
         let com = String::from("EXISTS ") + key;
         let com = com.parse::<Command>();
 
-        assert_eq!(com, Ok(Command::Exists("metanoia".to_string())));
+        assert_eq!(com, Ok(Command::Exists(vec!["metanoia".to_string()])));
     }
 
     #[test]
@@ -150,7 +1289,7 @@ mod parsing {
         let com = com.parse::<Command>();
 
         // 1h 27m 13s is 5223 seconds
-        assert_eq!(com, Ok(Command::Expire("metanoia".to_string(), std::time::Duration::from_secs(5_233))));
+        assert_eq!(com, Ok(Command::Expire("metanoia".to_string(), std::time::Duration::from_secs(5_233), None)));
     }
 
     #[test]
@@ -172,4 +1311,458 @@ mod parsing {
 
         assert_eq!(com, Ok(Command::Decr("metanoia".to_string())));
     }
+
+    #[test]
+    fn case_insensitive() {
+        let com = "get metanoia".parse::<Command>();
+
+        assert_eq!(com, Ok(Command::Get("metanoia".to_string())));
+    }
+
+    #[test]
+    fn expire_at_overflow() {
+        assert_eq!("EXPIREAT metanoia 18446744073709551615".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert!("PEXPIREAT metanoia 18446744073709551615".parse::<Command>().is_ok());
+    }
+
+    #[test]
+    fn expire_plain_seconds() {
+        let com = "EXPIRE metanoia 10".parse::<Command>();
+
+        assert_eq!(com, Ok(Command::Expire("metanoia".to_string(), Duration::from_secs(10), None)));
+    }
+
+    #[test]
+    fn from_words_keeps_whitespace() {
+        let com = Command::from_words(&["SET", "greeting", "hello world"]);
+
+        assert_eq!(com, Ok(Command::Set("greeting".to_string(), b"hello world".to_vec(), SetOptions::default())));
+    }
+
+    #[test]
+    fn from_request_binary_values() {
+        let words = |words: &[&[u8]]| words.iter().map(|word| word.to_vec()).collect::<Vec<_>>();
+
+        assert_eq!(
+            Command::from_request(words(&[b"SET", b"bits", b"\xff\x00", b"GET"])),
+            Ok(Command::Set("bits".to_string(), vec![0xff, 0x00], SetOptions { get: true, ..Default::default() }))
+        );
+        assert_eq!(
+            Command::from_request(words(&[b"setrange", b"bits", b"1", b"\xfe"])),
+            Ok(Command::SetRange("bits".to_string(), 1, vec![0xfe]))
+        );
+        assert_eq!(
+            Command::from_request(words(&[b"MSET", b"a", b"\xff", b"b", b"\x80"])),
+            Ok(Command::MSet(vec![("a".to_string(), vec![0xff]), ("b".to_string(), vec![0x80])]))
+        );
+
+        // Keys, names and options stay text
+        assert_eq!(Command::from_request(words(&[b"GET", b"\xff"])), Err(ParseError::InvalidParameters));
+        assert_eq!(Command::from_request(words(&[b"MSET", b"\xff", b"a"])), Err(ParseError::InvalidParameters));
+        assert_eq!(Command::from_request(words(&[b"SET", b"a", b"b", b"\xff"])), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn hello() {
+        assert_eq!("HELLO".parse::<Command>(), Ok(Command::Hello(None)));
+        assert_eq!("HELLO 3".parse::<Command>(), Ok(Command::Hello(Some(3))));
+        assert_eq!("HELLO 4".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn shutdown() {
+        assert_eq!("SHUTDOWN".parse::<Command>(), Ok(Command::Shutdown(true)));
+        assert_eq!("SHUTDOWN nosave".parse::<Command>(), Ok(Command::Shutdown(false)));
+        assert_eq!("SHUTDOWN NOW".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn ttl_pttl_persist() {
+        assert_eq!("TTL metanoia".parse::<Command>(), Ok(Command::Ttl("metanoia".to_string())));
+        assert_eq!("PTTL metanoia".parse::<Command>(), Ok(Command::Pttl("metanoia".to_string())));
+        assert_eq!("PERSIST metanoia".parse::<Command>(), Ok(Command::Persist("metanoia".to_string())));
+        assert_eq!("TTL".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn expire_condition() {
+        let com = "EXPIRE metanoia 1h 30m nx".parse::<Command>();
+        assert_eq!(com, Ok(Command::Expire("metanoia".to_string(), Duration::from_secs(5_400), Some(ExpireCondition::Nx))));
+
+        let com = "EXPIRE metanoia 10 GT".parse::<Command>();
+        assert_eq!(com, Ok(Command::Expire("metanoia".to_string(), Duration::from_secs(10), Some(ExpireCondition::Gt))));
+    }
+
+    #[test]
+    fn expireat() {
+        let at = UNIX_EPOCH + Duration::from_secs(4_102_444_800);
+
+        let com = "EXPIREAT metanoia 2100-01-01T00:00:00Z".parse::<Command>();
+        assert_eq!(com, Ok(Command::ExpireAt("metanoia".to_string(), at, None)));
+
+        let com = "EXPIREAT metanoia 4102444800 XX".parse::<Command>();
+        assert_eq!(com, Ok(Command::ExpireAt("metanoia".to_string(), at, Some(ExpireCondition::Xx))));
+
+        let com = "PEXPIREAT metanoia 4102444800000 LT".parse::<Command>();
+        assert_eq!(com, Ok(Command::ExpireAt("metanoia".to_string(), at, Some(ExpireCondition::Lt))));
+
+        assert_eq!("EXPIREAT metanoia tomorrow".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn set_options() {
+        let com = "SET lock me nx px 500 GET".parse::<Command>();
+        assert_eq!(com, Ok(Command::Set("lock".to_string(), b"me".to_vec(), SetOptions {
+            condition: Some(SetCondition::Nx),
+            expiration: Some(SetExpiration::In(Duration::from_millis(500))),
+            get: true
+        })));
+
+        let com = "SET cache hit EX 10m XX".parse::<Command>();
+        assert_eq!(com, Ok(Command::Set("cache".to_string(), b"hit".to_vec(), SetOptions {
+            condition: Some(SetCondition::Xx),
+            expiration: Some(SetExpiration::In(Duration::from_secs(600))),
+            get: false
+        })));
+
+        let com = "SET cache hit EXAT 4102444800".parse::<Command>();
+        assert_eq!(com, Ok(Command::Set("cache".to_string(), b"hit".to_vec(), SetOptions {
+            expiration: Some(SetExpiration::At(UNIX_EPOCH + Duration::from_secs(4_102_444_800))),
+            ..Default::default()
+        })));
+
+        let com = "SET cache hit KEEPTTL".parse::<Command>();
+        assert_eq!(com, Ok(Command::Set("cache".to_string(), b"hit".to_vec(), SetOptions {
+            expiration: Some(SetExpiration::Keep),
+            ..Default::default()
+        })));
+
+        assert_eq!("SET cache hit NX XX".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("SET cache hit EX 10 KEEPTTL".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("SET cache hit EX".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("SET cache hit EX 0".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("SET cache hit FOREVER".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn incrby_decrby() {
+        assert_eq!("INCRBY metanoia 19".parse::<Command>(), Ok(Command::IncrBy("metanoia".to_string(), 19)));
+        assert_eq!("decrby metanoia -19".parse::<Command>(), Ok(Command::DecrBy("metanoia".to_string(), -19)));
+        assert_eq!("INCRBYFLOAT metanoia 1.5e1".parse::<Command>(), Ok(Command::IncrByFloat("metanoia".to_string(), 15.0)));
+
+        assert_eq!("INCRBY metanoia 1.5".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("INCRBY metanoia 9223372036854775808".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("INCRBYFLOAT metanoia inf".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("INCRBYFLOAT metanoia".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn string_commands() {
+        assert_eq!("GETSET metanoia 19".parse::<Command>(), Ok(Command::Set("metanoia".to_string(), b"19".to_vec(), SetOptions {
+            get: true,
+            ..Default::default()
+        })));
+        assert_eq!("GETDEL metanoia".parse::<Command>(), Ok(Command::GetDel("metanoia".to_string())));
+        assert_eq!("APPEND metanoia 19".parse::<Command>(), Ok(Command::Append("metanoia".to_string(), b"19".to_vec())));
+        assert_eq!("STRLEN metanoia".parse::<Command>(), Ok(Command::Strlen("metanoia".to_string())));
+        assert_eq!("GETRANGE metanoia 0 -1".parse::<Command>(), Ok(Command::GetRange("metanoia".to_string(), 0, -1)));
+        assert_eq!("SETRANGE metanoia 5 19".parse::<Command>(), Ok(Command::SetRange("metanoia".to_string(), 5, b"19".to_vec())));
+
+        assert_eq!("SETRANGE metanoia -1 19".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("GETRANGE metanoia 0".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn getex() {
+        assert_eq!("GETEX metanoia".parse::<Command>(), Ok(Command::GetEx("metanoia".to_string(), None)));
+        assert_eq!("GETEX metanoia persist".parse::<Command>(), Ok(Command::GetEx("metanoia".to_string(), Some(GetExOption::Persist))));
+        assert_eq!("GETEX metanoia PX 1500".parse::<Command>(), Ok(Command::GetEx("metanoia".to_string(),
+            Some(GetExOption::Expire(SetExpiration::In(Duration::from_millis(1_500)))))));
+
+        assert_eq!("GETEX metanoia KEEPTTL".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("GETEX metanoia EX 10 PERSIST".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn multiple_keys() {
+        let keys = vec!["metanoia".to_string(), "alex".to_string()];
+        assert_eq!("DEL metanoia alex".parse::<Command>(), Ok(Command::Del(keys.clone())));
+        assert_eq!("EXISTS metanoia alex".parse::<Command>(), Ok(Command::Exists(keys.clone())));
+        assert_eq!("MGET metanoia alex".parse::<Command>(), Ok(Command::MGet(keys)));
+
+        let pairs = vec![("metanoia".to_string(), b"19".to_vec()), ("alex".to_string(), b"20".to_vec())];
+        assert_eq!("MSET metanoia 19 alex 20".parse::<Command>(), Ok(Command::MSet(pairs.clone())));
+        assert_eq!("MSETNX metanoia 19 alex 20".parse::<Command>(), Ok(Command::MSetNx(pairs)));
+
+        assert_eq!("MSET metanoia 19 alex".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("MGET".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("DEL".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn scan() {
+        assert_eq!("SCAN 0".parse::<Command>(), Ok(Command::Scan(0, ScanOptions::default())));
+        assert_eq!("scan 19 count 100 MATCH user:* type STRING".parse::<Command>(), Ok(Command::Scan(19, ScanOptions {
+            pattern: Some("user:*".to_string()),
+            count: 100,
+            type_name: Some("string".to_string())
+        })));
+
+        assert_eq!("SCAN".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("SCAN -1".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("SCAN 0 COUNT 0".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("SCAN 0 MATCH".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("KEYS".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn rename_copy() {
+        assert_eq!("RENAME a b".parse::<Command>(), Ok(Command::Rename("a".to_string(), "b".to_string())));
+        assert_eq!("RENAMENX a b".parse::<Command>(), Ok(Command::RenameNx("a".to_string(), "b".to_string())));
+        assert_eq!("COPY a b".parse::<Command>(), Ok(Command::Copy("a".to_string(), "b".to_string(), false)));
+        assert_eq!("COPY a b replace".parse::<Command>(), Ok(Command::Copy("a".to_string(), "b".to_string(), true)));
+
+        assert_eq!("COPY a a".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("COPY a b KEEP".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("RENAME a".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn databases() {
+        assert_eq!("SELECT 3".parse::<Command>(), Ok(Command::Select(3)));
+        assert_eq!("SWAPDB 0 1".parse::<Command>(), Ok(Command::SwapDb(0, 1)));
+        assert_eq!("MOVE metanoia 2".parse::<Command>(), Ok(Command::Move("metanoia".to_string(), 2)));
+        assert_eq!("FLUSHDB".parse::<Command>(), Ok(Command::FlushDb));
+        assert_eq!("FLUSHALL".parse::<Command>(), Ok(Command::Clear));
+
+        assert_eq!("SELECT -1".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("SWAPDB 0".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn lists() {
+        let values = vec!["a".to_string(), "b".to_string()];
+        assert_eq!("LPUSH queue a b".parse::<Command>(), Ok(Command::Push("queue".to_string(), values.clone(), ListEnd::Left)));
+        assert_eq!("rpush queue a b".parse::<Command>(), Ok(Command::Push("queue".to_string(), values, ListEnd::Right)));
+        assert_eq!("LPOP queue".parse::<Command>(), Ok(Command::Pop("queue".to_string(), None, ListEnd::Left)));
+        assert_eq!("RPOP queue 3".parse::<Command>(), Ok(Command::Pop("queue".to_string(), Some(3), ListEnd::Right)));
+        assert_eq!("LRANGE queue 0 -1".parse::<Command>(), Ok(Command::LRange("queue".to_string(), 0, -1)));
+        assert_eq!("LTRIM queue 1 -1".parse::<Command>(), Ok(Command::LTrim("queue".to_string(), 1, -1)));
+        assert_eq!("LLEN queue".parse::<Command>(), Ok(Command::LLen("queue".to_string())));
+        assert_eq!("LINDEX queue -2".parse::<Command>(), Ok(Command::LIndex("queue".to_string(), -2)));
+        assert_eq!("TYPE queue".parse::<Command>(), Ok(Command::Type("queue".to_string())));
+
+        assert_eq!("LPUSH queue".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("LPOP queue -1".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("LRANGE queue 0".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn hashes() {
+        let pairs = vec![("name".to_string(), "alex".to_string()), ("age".to_string(), "19".to_string())];
+        let fields = vec!["name".to_string(), "age".to_string()];
+        assert_eq!("HSET user name alex age 19".parse::<Command>(), Ok(Command::HSet("user".to_string(), pairs.clone())));
+        assert_eq!("HMSET user name alex age 19".parse::<Command>(), Ok(Command::HMSet("user".to_string(), pairs)));
+        assert_eq!("HSETNX user name alex".parse::<Command>(), Ok(Command::HSetNx("user".to_string(), "name".to_string(), "alex".to_string())));
+        assert_eq!("hget user name".parse::<Command>(), Ok(Command::HGet("user".to_string(), "name".to_string())));
+        assert_eq!("HMGET user name age".parse::<Command>(), Ok(Command::HMGet("user".to_string(), fields.clone())));
+        assert_eq!("HDEL user name age".parse::<Command>(), Ok(Command::HDel("user".to_string(), fields)));
+        assert_eq!("HEXISTS user name".parse::<Command>(), Ok(Command::HExists("user".to_string(), "name".to_string())));
+        assert_eq!("HLEN user".parse::<Command>(), Ok(Command::HLen("user".to_string())));
+        assert_eq!("HGETALL user".parse::<Command>(), Ok(Command::HGetAll("user".to_string())));
+        assert_eq!("HINCRBY user age -1".parse::<Command>(), Ok(Command::HIncrBy("user".to_string(), "age".to_string(), -1)));
+        assert_eq!("HINCRBYFLOAT user age 0.5".parse::<Command>(), Ok(Command::HIncrByFloat("user".to_string(), "age".to_string(), 0.5)));
+        assert_eq!(
+            "HSCAN user 0 MATCH n* COUNT 5".parse::<Command>(),
+            Ok(Command::HScan("user".to_string(), 0, ScanOptions { pattern: Some("n*".to_string()), count: 5, type_name: None }))
+        );
+
+        assert_eq!("HSET user name".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("HSET user name alex age".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("HGET user".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("HINCRBY user age x".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("HINCRBYFLOAT user age inf".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("HSCAN user 0 TYPE string".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn sets() {
+        let members = vec!["a".to_string(), "b".to_string()];
+        assert_eq!("SADD tags a b".parse::<Command>(), Ok(Command::SAdd("tags".to_string(), members.clone())));
+        assert_eq!("SREM tags a b".parse::<Command>(), Ok(Command::SRem("tags".to_string(), members.clone())));
+        assert_eq!("SMEMBERS tags".parse::<Command>(), Ok(Command::SMembers("tags".to_string())));
+        assert_eq!("SISMEMBER tags a".parse::<Command>(), Ok(Command::SIsMember("tags".to_string(), "a".to_string())));
+        assert_eq!("SCARD tags".parse::<Command>(), Ok(Command::SCard("tags".to_string())));
+        assert_eq!("sinter a b".parse::<Command>(), Ok(Command::SetOp(SetOperation::Inter, members.clone())));
+        assert_eq!("SUNION a b".parse::<Command>(), Ok(Command::SetOp(SetOperation::Union, members.clone())));
+        assert_eq!("SDIFFSTORE dest a b".parse::<Command>(), Ok(Command::SetOpStore(SetOperation::Diff, "dest".to_string(), members)));
+        assert_eq!("SRANDMEMBER tags -2".parse::<Command>(), Ok(Command::SRandMember("tags".to_string(), Some(-2))));
+        assert_eq!("SPOP tags".parse::<Command>(), Ok(Command::SPop("tags".to_string(), None)));
+
+        assert_eq!("SADD tags".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("SINTER".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("SUNIONSTORE dest".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("SPOP tags -1".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn sorted_sets() {
+        let options = ZAddOptions { condition: Some(SetCondition::Xx), comparison: Some(ZAddComparison::Gt), changed: true };
+        assert_eq!(
+            "ZADD board XX GT CH 10 alex -inf sam".parse::<Command>(),
+            Ok(Command::ZAdd("board".to_string(), vec![(10.0, "alex".to_string()), (f64::NEG_INFINITY, "sam".to_string())], options))
+        );
+        assert_eq!(
+            "zadd board incr 1.5 alex".parse::<Command>(),
+            Ok(Command::ZAddIncr("board".to_string(), 1.5, "alex".to_string(), ZAddOptions::default()))
+        );
+        assert_eq!("ZINCRBY board 2 alex".parse::<Command>(), Ok(Command::ZIncrBy("board".to_string(), 2.0, "alex".to_string())));
+        assert_eq!(
+            "ZRANGE board 0 -1 REV WITHSCORES".parse::<Command>(),
+            Ok(Command::ZRange("board".to_string(), ZRangeOptions { by: ZRangeBy::Rank(0, -1), rev: true, limit: None, with_scores: true }))
+        );
+        assert_eq!(
+            "ZRANGE board +inf (5 BYSCORE REV LIMIT 2 -1".parse::<Command>(),
+            Ok(Command::ZRange("board".to_string(), ZRangeOptions {
+                by: ZRangeBy::Score(Bound::Excluded(5.0), Bound::Included(f64::INFINITY)),
+                rev: true,
+                limit: Some((2, usize::MAX)),
+                with_scores: false
+            }))
+        );
+        assert_eq!(
+            "ZRANGEBYSCORE board 1 2 WITHSCORES".parse::<Command>(),
+            Ok(Command::ZRange("board".to_string(), ZRangeOptions {
+                by: ZRangeBy::Score(Bound::Included(1.0), Bound::Included(2.0)),
+                rev: false,
+                limit: None,
+                with_scores: true
+            }))
+        );
+        assert_eq!(
+            "ZRANGE names [a + BYLEX".parse::<Command>(),
+            Ok(Command::ZRange("names".to_string(), ZRangeOptions {
+                by: ZRangeBy::Lex(Bound::Included("a".to_string()), Bound::Unbounded),
+                rev: false,
+                limit: None,
+                with_scores: false
+            }))
+        );
+        assert_eq!("ZPOPMAX board 3".parse::<Command>(), Ok(Command::ZPop("board".to_string(), 3, ZPopEnd::Max)));
+        assert_eq!("ZREM board a b".parse::<Command>(), Ok(Command::ZRem("board".to_string(), vec!["a".to_string(), "b".to_string()])));
+
+        assert_eq!("ZADD board NX GT 1 a".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("ZADD board NX XX 1 a".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("ZADD board INCR 1 a 2 b".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("ZADD board nan a".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("ZADD board 1".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("ZRANGE board 0 -1 LIMIT 0 1".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("ZRANGE board 0 -1 BYSCORE BYLEX".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("ZRANGE names a z BYLEX".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("ZRANGE names - + BYLEX WITHSCORES".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn streams() {
+        let fields = vec![("level".to_string(), "info".to_string())];
+        assert_eq!("XADD log * level info".parse::<Command>(), Ok(Command::XAdd("log".to_string(), XAddId::Auto, fields.clone(), None)));
+        assert_eq!("XADD log MAXLEN ~ 10 5-* level info".parse::<Command>(), Ok(Command::XAdd("log".to_string(), XAddId::AutoSeq(5), fields.clone(), Some(10))));
+        assert_eq!(
+            "xadd log maxlen 10 5-1 level info".parse::<Command>(),
+            Ok(Command::XAdd("log".to_string(), XAddId::Explicit(StreamId { ms: 5, seq: 1 }), fields, Some(10)))
+        );
+        assert_eq!(
+            "XRANGE log (5 + COUNT 2".parse::<Command>(),
+            Ok(Command::XRange("log".to_string(), Bound::Excluded(StreamId { ms: 5, seq: 0 }), Bound::Unbounded, Some(2), false))
+        );
+        assert_eq!(
+            "XREVRANGE log 5 -".parse::<Command>(),
+            Ok(Command::XRange("log".to_string(), Bound::Unbounded, Bound::Included(StreamId { ms: 5, seq: u64::MAX }), None, true))
+        );
+        assert_eq!(
+            "XREAD COUNT 2 STREAMS a b 1-1 $".parse::<Command>(),
+            Ok(Command::XRead(vec![("a".to_string(), StreamStart::After(StreamId { ms: 1, seq: 1 })), ("b".to_string(), StreamStart::Last)], Some(2)))
+        );
+        assert_eq!(
+            "XREADGROUP GROUP workers alex STREAMS jobs >".parse::<Command>(),
+            Ok(Command::XReadGroup("workers".to_string(), "alex".to_string(), vec![("jobs".to_string(), StreamStart::New)], None))
+        );
+        assert_eq!(
+            "XGROUP CREATE jobs workers $ MKSTREAM".parse::<Command>(),
+            Ok(Command::XGroupCreate("jobs".to_string(), "workers".to_string(), StreamStart::Last, true))
+        );
+        assert_eq!(
+            "XACK jobs workers 1-0 2".parse::<Command>(),
+            Ok(Command::XAck("jobs".to_string(), "workers".to_string(), vec![StreamId { ms: 1, seq: 0 }, StreamId { ms: 2, seq: 0 }]))
+        );
+        assert_eq!("XPENDING jobs workers".parse::<Command>(), Ok(Command::XPending("jobs".to_string(), "workers".to_string())));
+        assert_eq!(
+            "XPENDING jobs workers - + 10 alex".parse::<Command>(),
+            Ok(Command::XPendingRange("jobs".to_string(), "workers".to_string(), Bound::Unbounded, Bound::Unbounded, 10, Some("alex".to_string())))
+        );
+        assert_eq!(
+            "XCLAIM jobs workers sam 1000 1-0".parse::<Command>(),
+            Ok(Command::XClaim("jobs".to_string(), "workers".to_string(), "sam".to_string(), Duration::from_secs(1), vec![StreamId { ms: 1, seq: 0 }]))
+        );
+
+        assert_eq!("XADD log * level".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("XADD log MAXLEN x * level info".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("XADD log x-1 level info".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("XREAD STREAMS a b 0".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("XREAD BLOCK 0 STREAMS a 0".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("XREAD STREAMS a >".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("XREADGROUP GROUP workers alex STREAMS jobs $".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("XGROUP CREATE jobs workers >".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn bitmaps() {
+        let u8 = BitFieldType { signed: false, bits: 8 };
+        assert_eq!("SETBIT active 7 1".parse::<Command>(), Ok(Command::SetBit("active".to_string(), 7, true)));
+        assert_eq!("BITCOUNT active".parse::<Command>(), Ok(Command::BitCount("active".to_string(), None)));
+        assert_eq!(
+            "BITCOUNT active 0 -1 bit".parse::<Command>(),
+            Ok(Command::BitCount("active".to_string(), Some(BitRange { start: 0, end: Some(-1), bits: true })))
+        );
+        assert_eq!(
+            "BITPOS active 0 2".parse::<Command>(),
+            Ok(Command::BitPos("active".to_string(), false, Some(BitRange { start: 2, end: None, bits: false })))
+        );
+        assert_eq!(
+            "BITOP NOT dest src".parse::<Command>(),
+            Ok(Command::BitOp(BitOperation::Not, "dest".to_string(), vec!["src".to_string()]))
+        );
+        assert_eq!(
+            "BITFIELD key GET u8 #2 overflow fail INCRBY i64 0 -1".parse::<Command>(),
+            Ok(Command::BitField("key".to_string(), vec![
+                BitFieldOperation::Get(u8, 16),
+                BitFieldOperation::Overflow(BitFieldOverflow::Fail),
+                BitFieldOperation::IncrBy(BitFieldType { signed: true, bits: 64 }, 0, -1)
+            ]))
+        );
+        assert_eq!("BITFIELD key".parse::<Command>(), Ok(Command::BitField("key".to_string(), vec![])));
+
+        assert_eq!("SETBIT active -1 1".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("SETBIT active 0 2".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("BITCOUNT active 0".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("BITPOS active 1 0 1 WORD".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("BITOP NOT dest a b".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("BITOP NAND dest a".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("BITFIELD key GET u64 0".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("BITFIELD key GET i0 0".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("BITFIELD key SET u8 0".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("BITFIELD key OVERFLOW MAYBE".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
+
+    #[test]
+    fn hyperloglogs() {
+        assert_eq!("PFADD visitors".parse::<Command>(), Ok(Command::PfAdd("visitors".to_string(), vec![])));
+        assert_eq!("pfadd visitors alex".parse::<Command>(), Ok(Command::PfAdd("visitors".to_string(), vec!["alex".to_string()])));
+        assert_eq!("PFCOUNT a b".parse::<Command>(), Ok(Command::PfCount(vec!["a".to_string(), "b".to_string()])));
+        assert_eq!("PFMERGE week".parse::<Command>(), Ok(Command::PfMerge("week".to_string(), vec![])));
+
+        assert_eq!("PFADD".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("PFCOUNT".parse::<Command>(), Err(ParseError::InvalidParameters));
+        assert_eq!("PFMERGE".parse::<Command>(), Err(ParseError::InvalidParameters));
+    }
 }
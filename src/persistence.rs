@@ -1,17 +1,28 @@
-use crate::{dictionary::{Dictionary, Entry}, errors::SerializationError};
-use std::{collections::HashMap, fs, path::PathBuf, sync::{Arc, Mutex}};
+use crate::{dictionary::{Database, Dictionary, Entry}, errors::SerializationError, hyperloglog::{HyperLogLog, Registers, DENSE_LEN}, scan_map::ScanMap, stream::{ConsumerGroup, PendingEntry, Stream, StreamId}, value::Value};
+use std::{fs, iter, path::PathBuf, sync::{Arc, MutexGuard}, time::{Duration, UNIX_EPOCH}};
 
 pub const DEFAULT_STORAGE_PATH: &str = "./db.csv";
+
+/// # Format
+/// One `key,value[,expiration]` line per plain string key.
+/// Other keys get a `key,value,[expiration],type` line where the key and value are escaped,
+/// see `escape`, list elements, set members, hash `field=value` and sorted set `member=score` pairs being separated by `;`.
+/// A stream is its last ID followed by its entries as `id=field=value...`,
+/// then each consumer group as `>group=last_delivered_id`
+/// with its pending entries as `!id=consumer=deliveries=delivered_unix_ms`.
+/// Keys of database 0 come first, every other database in use
+/// starts with a `[index]` line, so snapshots from before databases and types still load.
 pub struct Serializer {
-    map: Arc<Mutex<HashMap<String, Entry>>>,
+    databases: Arc<Vec<Database>>,
     path: PathBuf
 }
 
 impl Serializer {
+    /// Covers every database of `dict`, not just the selected one
     pub fn new(dict: &Dictionary, path: PathBuf) -> Self {
         Serializer {
-            map: Arc::clone(&dict.map),
-            path: PathBuf::from(path)
+            databases: Arc::clone(&dict.databases),
+            path
         }
     }
 
@@ -26,25 +37,44 @@ impl Serializer {
         self.set_from_csv(&csv)
     }
 
+    /// Writes to a temporary file first and moves it in place,
+    /// so an interrupted save never leaves a truncated snapshot behind
     pub fn save_file_csv(&self) -> Result<(), SerializationError> {
         let csv = self.get_as_csv();
-        match fs::write(&self.path, &csv) {
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        match fs::write(&tmp, &csv).and_then(|_| fs::rename(&tmp, &self.path)) {
             Ok(_) => Ok(()),
             Err(_) => Err(SerializationError::IOWrite)
         }
     }
 
     pub fn set_from_csv(&mut self, csv: &str) -> Result<(), SerializationError> {
-        // NOTE: possible poisoning
-        let mut guard = self.map.lock().unwrap();
-        guard.clear();
-        drop(guard);
+        for db in self.databases.iter() {
+            // NOTE: possible poisoning
+            db.lock().unwrap().clear();
+        }
 
+        let mut db = &self.databases[0];
         for line in csv.lines() {
+            // Keys starting with `[` are escaped, but snapshots from before that may still hold some
+            if let Some(index) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']'))
+                && !index.is_empty() && index.bytes().all(|byte| byte.is_ascii_digit()) {
+                db = index.parse::<usize>().ok()
+                    .and_then(|index| self.databases.get(index))
+                    .ok_or(SerializationError::DatabaseRead)?;
+                continue;
+            }
+
             let parts: Vec<&str> = line.split(',').collect();
-            let key = parts.get(0).ok_or(SerializationError::KeyRead)?;
+            let key = parts.first().ok_or(SerializationError::KeyRead)?;
             let value = parts.get(1).ok_or(SerializationError::ValueRead)?;
-            let expiration = match parts.get(2) {
+            let (key, value) = match parts.get(3) {
+                Some(type_name) => (unescape(key).ok_or(SerializationError::KeyRead)?, decode_value(type_name, value)?),
+                None => (key.to_string(), Value::from(*value))
+            };
+            let expiration = match parts.get(2).filter(|exp| !exp.is_empty()) {
                 Some(exp) => {
                     match exp.parse::<humantime::Timestamp>() {
                         Ok(exp) => Some(exp),
@@ -57,51 +87,275 @@ impl Serializer {
             };
 
             // NOTE: possible poisoning
-            let mut guard = self.map.lock().unwrap();
-            guard.insert(key.to_string(), Entry {
-                value: value.to_string(), 
-                expiration: match expiration {
-                    Some(exp) => Some(exp.into()),
-                    None => None
-                }
+            let mut guard = db.lock().unwrap();
+            guard.insert(key, Entry {
+                value,
+                expiration: expiration.map(|exp| exp.into())
             });
         }
 
         Ok(())
     }
 
-    /// Locks the map and creates a csv String from it
+    /// Locks every database and creates a csv String from them
     pub fn get_as_csv(&self) -> String {
-        let map = self.map.lock().unwrap();
+        // All at once, for a consistent snapshot
+        let databases: Vec<MutexGuard<_>> = self.databases.iter().map(|db| db.lock().unwrap()).collect();
 
         let mut s = String::new();
+        for (index, map) in databases.iter().enumerate() {
+            if index > 0 && !map.is_empty() {
+                s.push_str(&format!("[{index}]\n"));
+            }
+            Self::push_csv(&mut s, map);
+        }
+
+        s
+    }
+
+    fn push_csv(s: &mut String, map: &ScanMap<Entry>) {
         for (key, entry) in map.iter() {
+            let expiration = entry.expiration.map(|exp| humantime::format_rfc3339(exp).to_string());
+
+            // The original format, as long as the value is text and nothing needs escaping
+            let plain = entry.value.as_bytes().ok()
+                .and_then(|value| std::str::from_utf8(value).ok())
+                .filter(|value| !needs_escape(key) && !needs_escape(value));
+
             let mut line = String::new();
-            line.push_str(key);
-            line.push(',');
-            line.push_str(&entry.value);
-            match entry.expiration {
-                Some(exp) => {
+            match plain {
+                Some(value) => {
+                    line.push_str(key);
                     line.push(',');
-                    let exp = humantime::format_rfc3339(exp).to_string();
-                    line.push_str(&exp);
+                    line.push_str(value);
+                    if let Some(exp) = expiration {
+                        line.push(',');
+                        line.push_str(&exp);
+                    }
                 },
-                None => {}
+                None => {
+                    line.push_str(&escape(key));
+                    line.push(',');
+                    line.push_str(&encode_value(&entry.value));
+                    line.push(',');
+                    line.push_str(expiration.as_deref().unwrap_or_default());
+                    line.push(',');
+                    line.push_str(entry.value.type_name());
+                }
             }
 
             s.push_str(&line);
             s.push('\n');
         }
+    }
+}
 
-        s
+/// Characters with a meaning in the format, `[` starting a database header
+const RESERVED: [char; 7] = ['%', ',', ';', '=', '[', '\n', '\r'];
+
+fn needs_escape(s: &str) -> bool {
+    s.contains(RESERVED)
+}
+
+/// Percent-encodes the reserved characters
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match RESERVED.contains(&c) {
+            true => escaped.push_str(&format!("%{:02X}", c as u32)),
+            false => escaped.push(c)
+        }
     }
+
+    escaped
+}
+
+/// `escape`, also percent-encoding the bytes that aren't UTF-8
+fn escape_bytes(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        escaped.push_str(&escape(chunk.valid()));
+        for byte in chunk.invalid() {
+            escaped.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    escaped
+}
+
+/// # Returns
+/// None if an escape sequence is broken or the result isn't UTF-8
+fn unescape(s: &str) -> Option<String> {
+    String::from_utf8(unescape_bytes(s)?).ok()
+}
+
+/// # Returns
+/// None if an escape sequence is broken
+fn unescape_bytes(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    Some(bytes)
+}
+
+fn encode_value(value: &Value) -> String {
+    match value {
+        Value::String(bytes) => escape_bytes(bytes),
+        Value::List(list) => list.iter().map(|element| escape(element)).collect::<Vec<String>>().join(";"),
+        Value::Set(set) => set.iter().map(|member| escape(member)).collect::<Vec<String>>().join(";"),
+        Value::Hash(hash) => hash.iter().map(|(field, value)| format!("{}={}", escape(field), escape(value))).collect::<Vec<String>>().join(";"),
+        // Scores are written so that they parse back to the same `f64`
+        Value::ZSet(zset) => zset.iter().map(|(member, score)| format!("{}={score}", escape(member))).collect::<Vec<String>>().join(";"),
+        Value::Stream(stream) => encode_stream(stream),
+        Value::HyperLogLog(hll) => encode_hyperloglog(hll)
+    }
+}
+
+fn decode_value(type_name: &str, s: &str) -> Result<Value, SerializationError> {
+    match type_name {
+        "string" => Ok(Value::String(unescape_bytes(s).ok_or(SerializationError::ValueRead)?)),
+        // Lists are never empty
+        "list" => Ok(Value::List(s.split(';').map(unescape).collect::<Option<_>>().ok_or(SerializationError::ValueRead)?)),
+        "set" => Ok(Value::Set(s.split(';').map(unescape).collect::<Option<_>>().ok_or(SerializationError::ValueRead)?)),
+        "hash" => Ok(Value::Hash(decode_pairs(s, unescape).ok_or(SerializationError::ValueRead)?)),
+        "zset" => {
+            let score = |s: &str| s.parse::<f64>().ok().filter(|score| !score.is_nan());
+            Ok(Value::ZSet(decode_pairs(s, score).ok_or(SerializationError::ValueRead)?))
+        },
+        "stream" => Ok(Value::Stream(decode_stream(s).ok_or(SerializationError::ValueRead)?)),
+        "hyperloglog" => Ok(Value::HyperLogLog(decode_hyperloglog(s).ok_or(SerializationError::ValueRead)?)),
+        _ => Err(SerializationError::ValueRead)
+    }
+}
+
+fn encode_stream(stream: &Stream) -> String {
+    let mut items = vec![stream.last_id.to_string()];
+    for (id, fields) in &stream.entries {
+        let fields = fields.iter().flat_map(|(field, value)| [escape(field), escape(value)]);
+        items.push(iter::once(id.to_string()).chain(fields).collect::<Vec<String>>().join("="));
+    }
+
+    for (name, group) in &stream.groups {
+        items.push(format!(">{}={}", escape(name), group.last_delivered));
+        for (id, pending) in &group.pending {
+            let delivered = pending.delivered.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+            items.push(format!("!{id}={}={}={delivered}", escape(&pending.consumer), pending.deliveries));
+        }
+    }
+
+    items.join(";")
+}
+
+/// # Returns
+/// None if any item is broken
+fn decode_stream(s: &str) -> Option<Stream> {
+    let mut items = s.split(';');
+    let mut stream = Stream { last_id: StreamId::parse(items.next()?, 0)?, ..Default::default() };
+
+    // Pending entries belong to the group before them
+    let mut group_name = None;
+    for item in items {
+        if let Some(group) = item.strip_prefix('>') {
+            let (name, last_delivered) = group.split_once('=')?;
+            let name = unescape(name)?;
+            let last_delivered = StreamId::parse(last_delivered, 0)?;
+            stream.groups.insert(name.clone(), ConsumerGroup { last_delivered, ..Default::default() });
+            group_name = Some(name);
+        } else if let Some(pending) = item.strip_prefix('!') {
+            let group = stream.groups.get_mut(group_name.as_ref()?)?;
+            let [id, consumer, deliveries, delivered] = pending.split('=').collect::<Vec<_>>()[..] else {
+                return None;
+            };
+            group.pending.insert(StreamId::parse(id, 0)?, PendingEntry {
+                consumer: unescape(consumer)?,
+                delivered: UNIX_EPOCH + Duration::from_millis(delivered.parse::<u64>().ok()?),
+                deliveries: deliveries.parse::<u64>().ok()?
+            });
+        } else {
+            let mut parts = item.split('=');
+            let id = StreamId::parse(parts.next()?, 0)?;
+            let parts: Vec<String> = parts.map(unescape).collect::<Option<_>>()?;
+            if parts.is_empty() || !parts.len().is_multiple_of(2) {
+                return None;
+            }
+            stream.entries.insert(id, parts.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect());
+        }
+    }
+
+    Some(stream)
+}
+
+/// `sparse;index=value;...` or `dense;` followed by the registers in hex
+fn encode_hyperloglog(hll: &HyperLogLog) -> String {
+    match &hll.registers {
+        Registers::Sparse(registers) => {
+            iter::once("sparse".to_string())
+                .chain(registers.iter().map(|(index, value)| format!("{index}={value}")))
+                .collect::<Vec<String>>()
+                .join(";")
+        },
+        Registers::Dense(bytes) => format!("dense;{}", bytes.iter().map(|byte| format!("{byte:02x}")).collect::<String>())
+    }
+}
+
+/// # Returns
+/// None if the encoding is unknown or a register is out of range
+fn decode_hyperloglog(s: &str) -> Option<HyperLogLog> {
+    let mut items = s.split(';');
+    let registers = match items.next()? {
+        "sparse" => {
+            let registers = items
+                .map(|item| {
+                    let (index, value) = item.split_once('=')?;
+                    Some((index.parse::<u16>().ok()?, value.parse::<u8>().ok()?))
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Registers::Sparse(registers)
+        },
+        "dense" => {
+            let hex = items.next()?;
+            if hex.len() != DENSE_LEN * 2 || items.next().is_some() {
+                return None;
+            }
+            let bytes = (0..hex.len()).step_by(2)
+                .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+                .collect::<Option<Vec<u8>>>()?;
+            Registers::Dense(bytes)
+        },
+        _ => return None
+    };
+
+    let hll = HyperLogLog { registers };
+    hll.is_valid().then_some(hll)
+}
+
+/// `escaped=value` pairs separated by `;`
+/// # Returns
+/// None if any pair is broken
+fn decode_pairs<T, C: FromIterator<(String, T)>>(s: &str, decode: impl Fn(&str) -> Option<T>) -> Option<C> {
+    s.split(';')
+        .map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            Some((unescape(name)?, decode(value)?))
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod persistence {
-    use std::{time::{Duration, SystemTime}};
+    use std::{ops::Bound, time::{Duration, SystemTime}};
 
-    use crate::errors::DictionaryError;
+    use crate::{command::{Ttl, ZAddOptions}, errors::DictionaryError};
 
     use super::*;
 
@@ -109,13 +363,13 @@ mod persistence {
     fn csv_str() {
         let mut dict = Dictionary::new();
         dict.set("enjoy".to_string(), Entry {
-            value: "yourself".to_string(),
+            value: "yourself".into(),
             expiration: None
         });
 
         let time = SystemTime::now() + Duration::from_secs(15);
         dict.set("liar".to_string(), Entry {
-            value: "pants_on_fire".to_string(),
+            value: "pants_on_fire".into(),
             expiration: Some(time)
         });
         
@@ -135,11 +389,11 @@ mod persistence {
 
         // NOTE: Second argument to Serializer::new is useless
         let mut sd = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH));
-        sd.set_from_csv(&csv).unwrap();
+        sd.set_from_csv(csv).unwrap();
 
-        assert_eq!(dict.get("1").unwrap(), "one".to_string());
-        assert_eq!(dict.get("2").unwrap(), "two".to_string());
-        assert_eq!(dict.get("3").unwrap(), "three".to_string());
+        assert_eq!(dict.get("1").unwrap(), b"one".to_vec());
+        assert_eq!(dict.get("2").unwrap(), b"two".to_vec());
+        assert_eq!(dict.get("3").unwrap(), b"three".to_vec());
     }
 
     #[test]
@@ -151,11 +405,11 @@ mod persistence {
 
         // NOTE: Second argument to Serializer::new is useless
         let mut sd = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH));
-        sd.set_from_csv(&csv).unwrap();
+        sd.set_from_csv(csv).unwrap();
 
-        assert_eq!(dict.get("1").unwrap(), "one".to_string());
-        assert_eq!(dict.get("2").unwrap(), "two".to_string());
-        assert_eq!(dict.get("3").unwrap(), "three".to_string());
+        assert_eq!(dict.get("1").unwrap(), b"one".to_vec());
+        assert_eq!(dict.get("2").unwrap(), b"two".to_vec());
+        assert_eq!(dict.get("3").unwrap(), b"three".to_vec());
         assert_eq!(dict.exists("3").to_string(), "true");
     }
 
@@ -168,14 +422,180 @@ mod persistence {
 
         // NOTE: Second argument to Serializer::new is useless
         let mut sd = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH));
-        sd.set_from_csv(&csv).unwrap();
+        sd.set_from_csv(csv).unwrap();
 
-        assert_eq!(dict.get("1").unwrap(), "one".to_string());
-        assert_eq!(dict.get("2").unwrap(), "two".to_string());
-        assert_eq!(dict.get("3"), Err(DictionaryError::IsExpired));
+        assert_eq!(dict.get("1").unwrap(), b"one".to_vec());
+        assert_eq!(dict.get("2").unwrap(), b"two".to_vec());
+        assert_eq!(dict.get("3"), Err(DictionaryError::DoesNotExist));
         assert_eq!(dict.exists("3").to_string(), "false");
     }
 
+    #[test]
+    fn csv_databases() {
+        let mut dict = Dictionary::new();
+        dict.run_headless("SET zero 0".parse().unwrap()).unwrap();
+        dict.select(3).unwrap();
+        dict.run_headless("SET three 3".parse().unwrap()).unwrap();
+
+        let s = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH)).get_as_csv();
+        assert_eq!(s, "zero,0\n[3]\nthree,3\n");
+
+        let mut loaded = Dictionary::new();
+        Serializer::new(&loaded, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv(&s).unwrap();
+        assert!(!loaded.exists("three"));
+        loaded.select(3).unwrap();
+        assert_eq!(loaded.get("three"), Ok(b"3".to_vec()));
+    }
+
+    #[test]
+    fn csv_bracket_key() {
+        let mut dict = Dictionary::new();
+        dict.set("[a".to_string(), Entry::new("b]"));
+        dict.select(2).unwrap();
+        dict.set("[7]".to_string(), Entry::new("seven"));
+
+        let s = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH)).get_as_csv();
+        assert!(s.contains("%5Ba,b],,string\n"));
+
+        let mut loaded = Dictionary::new();
+        Serializer::new(&loaded, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv(&s).unwrap();
+        assert_eq!(loaded.get("[a"), Ok(b"b]".to_vec()));
+        loaded.select(2).unwrap();
+        assert_eq!(loaded.get("[7]"), Ok(b"seven".to_vec()));
+
+        // Written before `[` was escaped
+        Serializer::new(&loaded, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv("[a,b]\n").unwrap();
+        loaded.select(0).unwrap();
+        assert_eq!(loaded.get("[a"), Ok(b"b]".to_vec()));
+    }
+
+    #[test]
+    fn csv_types() {
+        let mut dict = Dictionary::new();
+        dict.run_headless("RPUSH list a ,; b%".parse().unwrap()).unwrap();
+        dict.set("comma,key".to_string(), Entry::new("new\nline"));
+        let time = SystemTime::now() + Duration::from_secs(15);
+        dict.expire("list", Duration::from_secs(15)).unwrap();
+
+        let s = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH)).get_as_csv();
+        assert!(s.contains("comma%2Ckey,new%0Aline,,string\n"));
+        assert!(s.contains("list,a;%2C%3B;b%25,"));
+        assert!(s.contains(",list\n"));
+
+        let loaded = Dictionary::new();
+        Serializer::new(&loaded, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv(&s).unwrap();
+        assert_eq!(loaded.lrange("list", 0, -1), Ok(vec!["a".to_string(), ",;".to_string(), "b%".to_string()]));
+        assert!(matches!(loaded.ttl("list"), Ok(Ttl::Remaining(left)) if left <= time.duration_since(SystemTime::now()).unwrap() + Duration::from_secs(1)));
+        assert_eq!(loaded.get("comma,key"), Ok(b"new\nline".to_vec()));
+    }
+
+    #[test]
+    fn csv_collections() {
+        let mut dict = Dictionary::new();
+        dict.hset("user", vec![("na=me".to_string(), "a;b".to_string())]).unwrap();
+        dict.sadd("tags", vec!["a;b".to_string()]).unwrap();
+        dict.zadd("board", vec![(0.1, "a=b".to_string()), (f64::NEG_INFINITY, "c".to_string())], ZAddOptions::default()).unwrap();
+
+        let s = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH)).get_as_csv();
+        assert!(s.contains("user,na%3Dme=a%3Bb,,hash\n"));
+        assert!(s.contains("tags,a%3Bb,,set\n"));
+        assert!(s.contains("board,c=-inf;a%3Db=0.1,,zset\n"));
+
+        let loaded = Dictionary::new();
+        Serializer::new(&loaded, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv(&s).unwrap();
+        assert_eq!(loaded.hget("user", "na=me"), Ok(Some("a;b".to_string())));
+        assert_eq!(loaded.smembers("tags"), Ok(vec!["a;b".to_string()]));
+        assert_eq!(loaded.zscore("board", "a=b"), Ok(Some(0.1)));
+        assert_eq!(loaded.zscore("board", "c"), Ok(Some(f64::NEG_INFINITY)));
+        assert_eq!(Serializer::new(&loaded, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv("user,field,,hash"), Err(SerializationError::ValueRead));
+        assert_eq!(Serializer::new(&loaded, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv("board,a=NaN,,zset"), Err(SerializationError::ValueRead));
+    }
+
+    #[test]
+    fn csv_stream() {
+        let mut dict = Dictionary::new();
+        dict.run_headless("XADD jobs 1-1 name a=b;c".parse().unwrap()).unwrap();
+        dict.run_headless("XADD jobs 2-0 name d".parse().unwrap()).unwrap();
+        dict.run_headless("XADD jobs MAXLEN 2 3-0 name e".parse().unwrap()).unwrap();
+        dict.run_headless("XGROUP CREATE jobs workers 0".parse().unwrap()).unwrap();
+        dict.run_headless("XREADGROUP GROUP workers alex COUNT 1 STREAMS jobs >".parse().unwrap()).unwrap();
+        dict.run_headless("XGROUP CREATE empty idle $ MKSTREAM".parse().unwrap()).unwrap();
+
+        let s = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH)).get_as_csv();
+        assert!(s.contains("jobs,3-0;2-0=name=d;3-0=name=e;>workers=2-0;!2-0=alex=1="));
+        assert!(s.contains("empty,0-0;>idle=0-0,,stream\n"));
+
+        let loaded = Dictionary::new();
+        Serializer::new(&loaded, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv(&s).unwrap();
+        assert_eq!(loaded.xrange("jobs", Bound::Unbounded, Bound::Unbounded, None, false), dict.xrange("jobs", Bound::Unbounded, Bound::Unbounded, None, false));
+        assert_eq!(loaded.xpending("jobs", "workers"), dict.xpending("jobs", "workers"));
+        assert_eq!(loaded.xlen("empty"), Ok(0));
+        assert_eq!(
+            Serializer::new(&loaded, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv("jobs,1-0;1-0=name,,stream"),
+            Err(SerializationError::ValueRead)
+        );
+    }
+
+    #[test]
+    fn csv_binary() {
+        let mut dict = Dictionary::new();
+        dict.setbit("active", 0, true).unwrap();
+        dict.setbit("active", 15, true).unwrap();
+
+        let s = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH)).get_as_csv();
+        assert_eq!(s, "active,%80\u{1},,string\n");
+
+        let loaded = Dictionary::new();
+        Serializer::new(&loaded, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv(&s).unwrap();
+        assert_eq!(loaded.bitcount("active", None), Ok(2));
+        assert_eq!(loaded.getbit("active", 15), Ok(true));
+    }
+
+    #[test]
+    fn csv_hyperloglog() {
+        let mut dict = Dictionary::new();
+        dict.pfadd("small", &["a".to_string(), "b".to_string()]).unwrap();
+        dict.pfadd("empty", &[]).unwrap();
+        dict.pfadd("big", &(0..10_000).map(|i| i.to_string()).collect::<Vec<_>>()).unwrap();
+
+        let s = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH)).get_as_csv();
+        assert!(s.contains("empty,sparse,,hyperloglog\n"));
+        assert!(s.contains("big,dense;"));
+
+        let loaded = Dictionary::new();
+        Serializer::new(&loaded, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv(&s).unwrap();
+        for key in ["small", "empty", "big"] {
+            let keys = [key.to_string()];
+            assert_eq!(loaded.pfcount(&keys), dict.pfcount(&keys));
+        }
+
+        let broken = Dictionary::new();
+        let csv = "hll,sparse;16384=1,,hyperloglog";
+        assert_eq!(Serializer::new(&broken, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv(csv), Err(SerializationError::ValueRead));
+        // Registers go up to 51 in both encodings
+        let csv = "hll,sparse;0=52,,hyperloglog";
+        assert_eq!(Serializer::new(&broken, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv(csv), Err(SerializationError::ValueRead));
+        let csv = format!("hll,dense;3f{},,hyperloglog", "00".repeat(DENSE_LEN - 1));
+        assert_eq!(Serializer::new(&broken, PathBuf::from(DEFAULT_STORAGE_PATH)).set_from_csv(&csv), Err(SerializationError::ValueRead));
+    }
+
+    #[test]
+    fn csv_broken_escape() {
+        let dict = Dictionary::new();
+
+        let mut sd = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH));
+        assert_eq!(sd.set_from_csv("list,a;%2,,list"), Err(SerializationError::ValueRead));
+        assert_eq!(sd.set_from_csv("list,a,,nope"), Err(SerializationError::ValueRead));
+    }
+
+    #[test]
+    fn csv_database_out_of_range() {
+        let dict = Dictionary::with_databases(2);
+
+        let mut sd = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH));
+        assert_eq!(sd.set_from_csv("1,one\n[2]\n2,two"), Err(SerializationError::DatabaseRead));
+    }
+
     #[test]
     fn csv_to_map_expiration_corrupted() {
         let dict = Dictionary::new();
@@ -185,6 +605,6 @@ mod persistence {
 
         // NOTE: Second argument to Serializer::new is useless
         let mut sd = Serializer::new(&dict, PathBuf::from(DEFAULT_STORAGE_PATH));
-        assert_eq!(sd.set_from_csv(&csv), Err(SerializationError::TimestampRead));
+        assert_eq!(sd.set_from_csv(csv), Err(SerializationError::TimestampRead));
     }
 }
@@ -0,0 +1,101 @@
+use std::{panic::{self, AssertUnwindSafe}, sync::{mpsc, Arc, Mutex}, thread};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A fixed set of worker threads pulling jobs off a shared queue.
+/// Dropping the pool waits for every queued job to finish.
+pub struct ThreadPool {
+    workers: Vec<thread::JoinHandle<()>>,
+    sender: Option<mpsc::Sender<Job>>
+}
+
+impl ThreadPool {
+    /// # Panics
+    /// If `size` is zero
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "A thread pool needs at least one worker");
+
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..size).map(|id| {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("kvdis-worker-{id}"))
+                .spawn(move || loop {
+                    // The guard is dropped before the job runs so other workers can pick up jobs
+                    let job = receiver.lock().unwrap().recv();
+                    match job {
+                        // A panicking job should not shrink the pool
+                        Ok(job) => {
+                            if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                                eprintln!("Worker {id} recovered from a panicking job");
+                            }
+                        },
+                        // The pool has been dropped
+                        Err(_) => break
+                    }
+                })
+                .expect("Worker thread could not be spawned")
+        }).collect();
+
+        ThreadPool { workers, sender: Some(sender) }
+    }
+
+    pub fn execute<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static
+    {
+        // The sender only goes away in Drop
+        self.sender.as_ref().unwrap().send(Box::new(f)).unwrap();
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        // Closing the channel makes every idle worker leave its loop
+        drop(self.sender.take());
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod pool {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn runs_every_job() {
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let pool = ThreadPool::new(4);
+        for _ in 0..32 {
+            let counter = Arc::clone(&counter);
+            pool.execute(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        drop(pool);
+
+        assert_eq!(counter.load(Ordering::SeqCst), 32);
+    }
+
+    #[test]
+    fn survives_panicking_job() {
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let pool = ThreadPool::new(1);
+        pool.execute(|| panic!("boom"));
+        let c = Arc::clone(&counter);
+        pool.execute(move || {
+            c.fetch_add(1, Ordering::SeqCst);
+        });
+        drop(pool);
+
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
+}
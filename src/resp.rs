@@ -0,0 +1,465 @@
+//! RESP2/RESP3 framing
+//! Requests are arrays of bulk strings, replies are encoded from `Frame`s.
+//! Types that only exist in RESP3 are downgraded when talking RESP2.
+
+use std::io::{self, BufRead, Read};
+
+use crate::{command::CommandResult, errors::{DictionaryError, ParseError}, stream::StreamEntry};
+
+/// Upper bounds mirroring Redis' own, so a bogus header can't make us allocate the world
+const MAX_ARRAY_LEN: usize = 1024 * 1024;
+const MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+/// Longest header line
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    #[default]
+    Resp2,
+    Resp3
+}
+
+impl Protocol {
+    pub fn version(&self) -> u8 {
+        match self {
+            Protocol::Resp2 => 2,
+            Protocol::Resp3 => 3
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+    Simple(String),
+    Error(String),
+    Integer(i64),
+    /// Any bytes
+    Bulk(Vec<u8>),
+    Null,
+    Array(Vec<Frame>),
+    /// RESP3 only, an integer in RESP2
+    Boolean(bool),
+    /// RESP3 only, a bulk string in RESP2
+    Double(f64),
+    /// RESP3 only, a flat array of keys and values in RESP2
+    Map(Vec<(Frame, Frame)>)
+}
+
+impl Frame {
+    pub fn bulk(bytes: impl Into<Vec<u8>>) -> Self {
+        Frame::Bulk(bytes.into())
+    }
+
+    pub fn encode(&self, protocol: Protocol, out: &mut Vec<u8>) {
+        use Frame::*;
+        match self {
+            Simple(s) => {
+                out.push(b'+');
+                out.extend_from_slice(s.as_bytes());
+                out.extend_from_slice(b"\r\n");
+            },
+            Error(e) => {
+                out.push(b'-');
+                out.extend_from_slice(e.as_bytes());
+                out.extend_from_slice(b"\r\n");
+            },
+            Integer(i) => {
+                out.extend_from_slice(format!(":{i}\r\n").as_bytes());
+            },
+            Bulk(bytes) => {
+                out.extend_from_slice(format!("${}\r\n", bytes.len()).as_bytes());
+                out.extend_from_slice(bytes);
+                out.extend_from_slice(b"\r\n");
+            },
+            Null => match protocol {
+                Protocol::Resp2 => out.extend_from_slice(b"$-1\r\n"),
+                Protocol::Resp3 => out.extend_from_slice(b"_\r\n")
+            },
+            Array(frames) => {
+                out.extend_from_slice(format!("*{}\r\n", frames.len()).as_bytes());
+                for frame in frames {
+                    frame.encode(protocol, out);
+                }
+            },
+            Boolean(b) => match protocol {
+                Protocol::Resp2 => Integer(*b as i64).encode(protocol, out),
+                Protocol::Resp3 => out.extend_from_slice(if *b { b"#t\r\n" } else { b"#f\r\n" })
+            },
+            Double(d) => match protocol {
+                Protocol::Resp2 => Frame::bulk(d.to_string()).encode(protocol, out),
+                Protocol::Resp3 => {
+                    let d = match d {
+                        d if d.is_nan() => "nan".to_string(),
+                        d if d.is_infinite() && d.is_sign_positive() => "inf".to_string(),
+                        d if d.is_infinite() => "-inf".to_string(),
+                        d => d.to_string()
+                    };
+                    out.extend_from_slice(format!(",{d}\r\n").as_bytes());
+                }
+            },
+            Map(pairs) => {
+                let header = match protocol {
+                    Protocol::Resp2 => format!("*{}\r\n", pairs.len() * 2),
+                    Protocol::Resp3 => format!("%{}\r\n", pairs.len())
+                };
+                out.extend_from_slice(header.as_bytes());
+                for (key, value) in pairs {
+                    key.encode(protocol, out);
+                    value.encode(protocol, out);
+                }
+            }
+        }
+    }
+}
+
+impl From<CommandResult> for Frame {
+    fn from(result: CommandResult) -> Self {
+        use CommandResult::*;
+        match result {
+            Get(value) => Frame::bulk(value),
+            Append(len) | Strlen(len) | SetRange(len) => Frame::Integer(len as i64),
+            Set(false) => Frame::Null,
+            SetGet(_, Some(previous)) => Frame::bulk(previous),
+            SetGet(_, None) => Frame::Null,
+            Del(count) | Exists(count) => Frame::Integer(count as i64),
+            MGet(values) => Frame::Array(values.into_iter().map(|value| value.map_or(Frame::Null, Frame::bulk)).collect()),
+            MSetNx(set) => Frame::Integer(set as i64),
+            RenameNx(done) | Copy(done) | Move(done) => Frame::Integer(done as i64),
+            Keys(keys) => Frame::Array(keys.into_iter().map(Frame::bulk).collect()),
+            // The cursor is a string in Redis too
+            Scan(cursor, keys) => Frame::Array(vec![
+                Frame::bulk(cursor.to_string()),
+                Frame::Array(keys.into_iter().map(Frame::bulk).collect())
+            ]),
+            DbSize(size) => Frame::Integer(size as i64),
+            Type(type_name) => Frame::Simple(type_name),
+            Push(len) | LLen(len) | HSet(len) | HDel(len) | HLen(len) => Frame::Integer(len as i64),
+            SAdd(len) | SRem(len) | SCard(len) | SetOpStore(len) => Frame::Integer(len as i64),
+            ZAdd(len) | ZCard(len) | ZRem(len) => Frame::Integer(len as i64),
+            XAdd(id) => Frame::bulk(id.to_string()),
+            XLen(len) | XAck(len) => Frame::Integer(len as i64),
+            XGroupDestroy(existed) => Frame::Integer(existed as i64),
+            Bit(bit) => Frame::Integer(bit as i64),
+            BitCount(count) | BitOp(count) => Frame::Integer(count as i64),
+            BitPos(position) => Frame::Integer(position),
+            PfAdd(changed) => Frame::Integer(changed as i64),
+            PfCount(count) => Frame::Integer(count as i64),
+            BitField(values) => Frame::Array(values.into_iter().map(|value| value.map_or(Frame::Null, Frame::Integer)).collect()),
+            Entries(entries) => entries_frame(entries),
+            // Nil when nothing was read, like Redis
+            XRead(streams) if streams.is_empty() => Frame::Null,
+            XRead(streams) => Frame::Array(streams.into_iter()
+                .map(|(key, entries)| Frame::Array(vec![Frame::bulk(key), entries_frame(entries)]))
+                .collect()),
+            XPending(summary) => {
+                let (first, last) = match summary.range {
+                    Some((first, last)) => (Frame::bulk(first.to_string()), Frame::bulk(last.to_string())),
+                    None => (Frame::Null, Frame::Null)
+                };
+                let consumers = match summary.consumers.is_empty() {
+                    true => Frame::Null,
+                    false => Frame::Array(summary.consumers.into_iter()
+                        .map(|(consumer, count)| Frame::Array(vec![Frame::bulk(consumer), Frame::bulk(count.to_string())]))
+                        .collect())
+                };
+                Frame::Array(vec![Frame::Integer(summary.count as i64), first, last, consumers])
+            },
+            XPendingRange(pending) => Frame::Array(pending.into_iter()
+                .map(|info| Frame::Array(vec![
+                    Frame::bulk(info.id.to_string()),
+                    Frame::bulk(info.consumer),
+                    Frame::Integer(info.idle.as_millis() as i64),
+                    Frame::Integer(info.deliveries as i64)
+                ]))
+                .collect()),
+            ZRank(rank) => rank.map_or(Frame::Null, |rank| Frame::Integer(rank as i64)),
+            // Scores are bulk strings like INCRBYFLOAT replies
+            Score(score) => score.map_or(Frame::Null, |score| Frame::bulk(score.to_string())),
+            // Each member followed by its score, as RESP2 does
+            Scored(members) => Frame::Array(members.into_iter()
+                .flat_map(|(member, score)| [Frame::bulk(member), Frame::bulk(score.to_string())])
+                .collect()),
+            List(values) => Frame::Array(values.into_iter().map(Frame::bulk).collect()),
+            LIndex(value) | HGet(value) | Member(value) => value.map_or(Frame::Null, Frame::bulk),
+            HSetNx(set) | HExists(set) | SIsMember(set) => Frame::Integer(set as i64),
+            // Flattened to field value pairs over RESP2
+            HGetAll(pairs) => Frame::Map(pairs.into_iter().map(|(field, value)| (Frame::bulk(field), Frame::bulk(value))).collect()),
+            RandomKey(key) => key.map_or(Frame::Null, Frame::bulk),
+            Expire(set) => Frame::Integer(set as i64),
+            Ttl(ttl) => Frame::Integer(ttl.as_secs()),
+            Pttl(ttl) => Frame::Integer(ttl.as_millis()),
+            Persist(persisted) => Frame::Integer(persisted as i64),
+            Incr(value) | Decr(value) => Frame::Integer(value),
+            // Redis replies with a bulk string so no precision is lost
+            IncrByFloat(value) => Frame::bulk(value.to_string()),
+            Ping(None) => Frame::Simple("PONG".to_string()),
+            Ping(Some(message)) => Frame::bulk(message),
+            // Redis style, one `name:value` per line
+            Info(info) => {
+                let lines: Vec<String> = info.iter().map(|(name, value)| format!("{name}:{value}")).collect();
+                Frame::bulk(lines.join("\r\n"))
+            },
+            // Connections answer HELLO themselves as they know the negotiated protocol
+            Hello(_) => hello(Protocol::default()),
+            Set(true) | MSet | HMSet | Rename | Clear | FlushDb | Select | SwapDb | LTrim | XGroupCreate | PfMerge | Save | Load | Shutdown(_) => Frame::Simple("OK".to_string())
+        }
+    }
+}
+
+/// Each entry as its ID and a flat array of fields and values
+fn entries_frame(entries: Vec<StreamEntry>) -> Frame {
+    Frame::Array(entries.into_iter()
+        .map(|(id, fields)| Frame::Array(vec![
+            Frame::bulk(id.to_string()),
+            Frame::Array(fields.into_iter().flat_map(|(field, value)| [Frame::bulk(field), Frame::bulk(value)]).collect())
+        ]))
+        .collect())
+}
+
+/// Reply to HELLO
+pub fn hello(protocol: Protocol) -> Frame {
+    Frame::Map(vec![
+        (Frame::bulk("server".to_string()), Frame::bulk("kvdis".to_string())),
+        (Frame::bulk("version".to_string()), Frame::bulk(env!("CARGO_PKG_VERSION").to_string())),
+        (Frame::bulk("proto".to_string()), Frame::Integer(protocol.version() as i64))
+    ])
+}
+
+impl From<&DictionaryError> for Frame {
+    /// See `Command::nil_when_missing` for the commands replying nil instead
+    fn from(e: &DictionaryError) -> Self {
+        match e {
+            DictionaryError::DoesNotExist => Frame::Error("ERR no such key".to_string()),
+            // Clients look for the Redis error code
+            DictionaryError::InvalidOperationType => Frame::Error(format!("WRONGTYPE {e}")),
+            DictionaryError::NoGroup => Frame::Error(format!("NOGROUP {e}")),
+            DictionaryError::GroupExists => Frame::Error(format!("BUSYGROUP {e}")),
+            e => Frame::Error(format!("ERR {e}"))
+        }
+    }
+}
+
+impl From<&ParseError> for Frame {
+    fn from(e: &ParseError) -> Self {
+        Frame::Error(format!("ERR {e}"))
+    }
+}
+
+/// Reads one request, a RESP array of bulk strings, from `reader`.
+/// The words are raw bytes, `Command::from_request` decides which may be binary.
+/// # Returns
+/// - Ok(words) on success
+/// - Err with `io::ErrorKind::InvalidData` on broken framing
+pub fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Vec<Vec<u8>>> {
+    let mut line = Vec::new();
+    read_line(reader, &mut line, MAX_LINE_LEN)?;
+    let len = match array_len(&line)? {
+        Some(len) => len,
+        // Null arrays carry no command
+        None => return Ok(Vec::new())
+    };
+
+    let mut words = Vec::with_capacity(len);
+    for _ in 0..len {
+        line.clear();
+        read_line(reader, &mut line, MAX_LINE_LEN)?;
+        let bulk_len = bulk_len(&line)? + 2;
+
+        // Payload and its trailing CRLF, the buffer grows as the bytes actually arrive
+        let mut bulk = Vec::new();
+        reader.by_ref().take(bulk_len as u64).read_to_end(&mut bulk)?;
+        words.push(bulk_payload(bulk, bulk_len)?);
+    }
+
+    Ok(words)
+}
+
+/// Async twin of `read_request`
+#[cfg(feature = "async")]
+pub async fn read_request_async<R>(reader: &mut R) -> io::Result<Vec<Vec<u8>>>
+where
+    R: tokio::io::AsyncBufRead + Unpin
+{
+    use tokio::io::AsyncReadExt;
+
+    let mut line = Vec::new();
+    read_line_async(reader, &mut line, MAX_LINE_LEN).await?;
+    let len = match array_len(&line)? {
+        Some(len) => len,
+        None => return Ok(Vec::new())
+    };
+
+    let mut words = Vec::with_capacity(len);
+    for _ in 0..len {
+        line.clear();
+        read_line_async(reader, &mut line, MAX_LINE_LEN).await?;
+        let bulk_len = bulk_len(&line)? + 2;
+
+        let mut bulk = Vec::new();
+        (&mut *reader).take(bulk_len as u64).read_to_end(&mut bulk).await?;
+        words.push(bulk_payload(bulk, bulk_len)?);
+    }
+
+    Ok(words)
+}
+
+/// Reads up to and including the next `\n` into `line`
+/// # Returns
+/// Err with `io::ErrorKind::InvalidData` if the line is longer than `max_len`
+pub fn read_line<R: BufRead>(reader: &mut R, line: &mut Vec<u8>, max_len: usize) -> io::Result<()> {
+    reader.by_ref().take(max_len as u64).read_until(b'\n', line)?;
+    check_line(line, max_len)
+}
+
+/// Async twin of `read_line`
+#[cfg(feature = "async")]
+pub async fn read_line_async<R>(reader: &mut R, line: &mut Vec<u8>, max_len: usize) -> io::Result<()>
+where
+    R: tokio::io::AsyncBufRead + Unpin
+{
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+    (&mut *reader).take(max_len as u64).read_until(b'\n', line).await?;
+    check_line(line, max_len)
+}
+
+fn check_line(line: &[u8], max_len: usize) -> io::Result<()> {
+    match line.len() >= max_len && !line.ends_with(b"\n") {
+        true => Err(protocol_error()),
+        false => Ok(())
+    }
+}
+
+/// Parses a `*<len>\r\n` line, `None` being a null array
+fn array_len(line: &[u8]) -> io::Result<Option<usize>> {
+    match parse_header(line, b'*')? {
+        Some(len) if len > MAX_ARRAY_LEN => Err(protocol_error()),
+        len => Ok(len)
+    }
+}
+
+/// Parses a `$<len>\r\n` line, requests can't hold null bulk strings
+fn bulk_len(line: &[u8]) -> io::Result<usize> {
+    match parse_header(line, b'$')? {
+        Some(len) if len <= MAX_BULK_LEN => Ok(len),
+        _ => Err(protocol_error())
+    }
+}
+
+/// Strips the CRLF off a bulk string that should be `len` bytes long with it
+fn bulk_payload(mut bulk: Vec<u8>, len: usize) -> io::Result<Vec<u8>> {
+    if bulk.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    if !bulk.ends_with(b"\r\n") {
+        return Err(protocol_error());
+    }
+    bulk.truncate(bulk.len() - 2);
+
+    Ok(bulk)
+}
+
+/// Parses a `<prefix><length>\r\n` line, a length of -1 meaning null
+fn parse_header(line: &[u8], prefix: u8) -> io::Result<Option<usize>> {
+    let digits = line.strip_prefix(&[prefix])
+        .and_then(|line| line.strip_suffix(b"\r\n"))
+        .ok_or_else(protocol_error)?;
+
+    match digits {
+        b"-1" => Ok(None),
+        digits => std::str::from_utf8(digits).ok()
+            .and_then(|digits| digits.parse::<usize>().ok())
+            .map(Some)
+            .ok_or_else(protocol_error)
+    }
+}
+
+fn protocol_error() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, ParseError::Protocol.to_string())
+}
+
+#[cfg(test)]
+mod resp {
+    use super::*;
+
+    fn encode(frame: Frame, protocol: Protocol) -> String {
+        let mut out = Vec::new();
+        frame.encode(protocol, &mut out);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn encode_resp2() {
+        assert_eq!(encode(Frame::Simple("OK".to_string()), Protocol::Resp2), "+OK\r\n");
+        assert_eq!(encode(Frame::bulk("19".to_string()), Protocol::Resp2), "$2\r\n19\r\n");
+        assert_eq!(encode(Frame::Integer(-3), Protocol::Resp2), ":-3\r\n");
+        assert_eq!(encode(Frame::Null, Protocol::Resp2), "$-1\r\n");
+        assert_eq!(encode(Frame::Boolean(true), Protocol::Resp2), ":1\r\n");
+        assert_eq!(encode(Frame::Error("ERR nope".to_string()), Protocol::Resp2), "-ERR nope\r\n");
+        assert_eq!(
+            encode(Frame::Array(vec![Frame::Integer(1), Frame::Null]), Protocol::Resp2),
+            "*2\r\n:1\r\n$-1\r\n"
+        );
+        assert_eq!(
+            encode(Frame::Map(vec![(Frame::bulk("a".to_string()), Frame::Integer(1))]), Protocol::Resp2),
+            "*2\r\n$1\r\na\r\n:1\r\n"
+        );
+    }
+
+    #[test]
+    fn error_codes() {
+        assert_eq!(Frame::from(&DictionaryError::DoesNotExist), Frame::Error("ERR no such key".to_string()));
+        assert!(matches!(Frame::from(&DictionaryError::InvalidOperationType), Frame::Error(e) if e.starts_with("WRONGTYPE ")));
+        assert!(matches!(Frame::from(&DictionaryError::NotANumber), Frame::Error(e) if e.starts_with("ERR ")));
+        assert!(matches!(Frame::from(&DictionaryError::NoGroup), Frame::Error(e) if e.starts_with("NOGROUP ")));
+        assert!(matches!(Frame::from(&DictionaryError::GroupExists), Frame::Error(e) if e.starts_with("BUSYGROUP ")));
+    }
+
+    #[test]
+    fn encode_resp3() {
+        assert_eq!(encode(Frame::Null, Protocol::Resp3), "_\r\n");
+        assert_eq!(encode(Frame::Boolean(false), Protocol::Resp3), "#f\r\n");
+        assert_eq!(encode(Frame::Double(1.5), Protocol::Resp3), ",1.5\r\n");
+        assert_eq!(encode(Frame::Double(f64::NEG_INFINITY), Protocol::Resp3), ",-inf\r\n");
+        assert_eq!(
+            encode(Frame::Map(vec![(Frame::bulk("a".to_string()), Frame::Integer(1))]), Protocol::Resp3),
+            "%1\r\n$1\r\na\r\n:1\r\n"
+        );
+    }
+
+    #[test]
+    fn read_array() {
+        let mut input: &[u8] = b"*3\r\n$3\r\nSET\r\n$8\r\ngreeting\r\n$12\r\nhello\r\nworld\r\n";
+        let words = read_request(&mut input).unwrap();
+
+        assert_eq!(words, vec![b"SET".to_vec(), b"greeting".to_vec(), b"hello\r\nworld".to_vec()]);
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn read_broken_framing() {
+        let mut input: &[u8] = b"*1\r\n:3\r\n";
+        assert_eq!(read_request(&mut input).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut input: &[u8] = b"*1\r\n$3\r\nGETX\r\n";
+        assert_eq!(read_request(&mut input).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_limits() {
+        // Payloads are only buffered as they arrive
+        let mut input: &[u8] = b"*1\r\n$536870912\r\nshort\r\n";
+        assert_eq!(read_request(&mut input).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        let long = [b"*1\r\n$".as_slice(), &[b'1'; MAX_LINE_LEN]].concat();
+        assert_eq!(read_request(&mut long.as_slice()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_binary() {
+        let mut input: &[u8] = b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$2\r\n\xff\x00\r\n";
+
+        assert_eq!(read_request(&mut input).unwrap()[2], vec![0xff, 0x00]);
+        assert!(input.is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+//! The map behind databases and hashes
+//! A `HashMap` that also keeps its keys in the order SCAN cursors walk,
+//! so a batch costs the size of the batch rather than of the map.
+
+use std::{collections::{BTreeMap, HashMap}, hash::{BuildHasher, Hasher, RandomState}, ops::Deref};
+
+use crate::{dictionary::random_index, hyperloglog::murmur_hash64a};
+
+/// Reads go through `Deref`, writes through the methods below so the order stays in sync
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanMap<V> {
+    entries: HashMap<String, V>,
+    /// Every key by `scan_hash`, keys sharing a hash kept together
+    order: BTreeMap<u64, Vec<String>>
+}
+
+impl<V> Default for ScanMap<V> {
+    fn default() -> Self {
+        ScanMap { entries: HashMap::new(), order: BTreeMap::new() }
+    }
+}
+
+impl<V> Deref for ScanMap<V> {
+    type Target = HashMap<String, V>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl<V> ScanMap<V> {
+    pub fn new() -> Self {
+        ScanMap::default()
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.entries.get_mut(key)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.entries.values_mut()
+    }
+
+    /// # Returns
+    /// The previous value
+    pub fn insert(&mut self, key: String, value: V) -> Option<V> {
+        if let Some(current) = self.entries.get_mut(&key) {
+            return Some(std::mem::replace(current, value));
+        }
+
+        self.order.entry(scan_hash(&key)).or_default().push(key.clone());
+        self.entries.insert(key, value)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        let value = self.entries.remove(key)?;
+
+        let hash = scan_hash(key);
+        if let Some(keys) = self.order.get_mut(&hash) {
+            keys.retain(|other| other != key);
+            if keys.is_empty() {
+                self.order.remove(&hash);
+            }
+        }
+        Some(value)
+    }
+
+    pub fn get_or_insert_with(&mut self, key: &str, default: impl FnOnce() -> V) -> &mut V {
+        if !self.entries.contains_key(key) {
+            self.insert(key.to_string(), default());
+        }
+        self.entries.get_mut(key).unwrap()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    /// The next `count` entries from `cursor` on, see `Dictionary::scan`
+    /// # Returns
+    /// The cursor to continue from, 0 once done, and the batch
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&String, &V)>) {
+        let mut batch = Vec::with_capacity(count);
+        // Keys sharing a hash are returned together, the cursor can't tell them apart
+        for (hash, keys) in self.order.range(cursor..) {
+            if batch.len() >= count {
+                return (*hash, batch);
+            }
+            batch.extend(keys.iter().map(|key| (key, &self.entries[key])));
+        }
+
+        (0, batch)
+    }
+
+    /// # Returns
+    /// A key picked at random, None if the map is empty
+    pub fn random(&self) -> Option<(&String, &V)> {
+        if self.order.is_empty() {
+            return None;
+        }
+
+        // The first key from a random hash on, wrapping around
+        let start = RandomState::new().build_hasher().finish();
+        let (_, keys) = self.order.range(start..).next().or_else(|| self.order.iter().next())?;
+        let key = &keys[random_index(keys.len())];
+        Some((key, &self.entries[key]))
+    }
+}
+
+impl<V> FromIterator<(String, V)> for ScanMap<V> {
+    fn from_iter<I: IntoIterator<Item = (String, V)>>(iter: I) -> Self {
+        let mut map = ScanMap::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl<V> IntoIterator for ScanMap<V> {
+    type Item = (String, V);
+    type IntoIter = std::collections::hash_map::IntoIter<String, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+/// Orders keys for SCAN, the same for every map, run and build of the server
+fn scan_hash(key: &str) -> u64 {
+    murmur_hash64a(key.as_bytes(), 0)
+}
+
+#[cfg(test)]
+mod scan_map {
+    use super::*;
+
+    #[test]
+    fn order_follows_writes() {
+        let mut map: ScanMap<usize> = (0..100).map(|i| (i.to_string(), i)).collect();
+        assert_eq!(map.insert("7".to_string(), 70), Some(7));
+        assert_eq!(map.remove("8"), Some(8));
+        assert_eq!(map.remove("8"), None);
+        *map.get_or_insert_with("new", || 0) += 1;
+
+        let mut seen = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (next, batch) = map.scan(cursor, 10);
+            assert!(batch.len() <= 11);
+            seen.extend(batch.into_iter().map(|(key, value)| (key.clone(), *value)));
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+
+        seen.sort();
+        let mut expected: Vec<(String, usize)> = map.iter().map(|(key, value)| (key.clone(), *value)).collect();
+        expected.sort();
+        assert_eq!(seen, expected);
+        assert_eq!(map.order.values().map(Vec::len).sum::<usize>(), map.len());
+        assert!(map.random().is_some_and(|(key, _)| map.contains_key(key)));
+
+        map.clear();
+        assert_eq!(map.scan(0, 10), (0, vec![]));
+        assert_eq!(map.random(), None);
+    }
+}
@@ -0,0 +1,262 @@
+//! Set commands
+//! Sets are created by adding to a missing key and deleted once emptied.
+//! Commands over several keys run under a single lock of the selected database,
+//! so they see and write a consistent state.
+
+use std::collections::HashSet;
+
+use crate::{command::SetOperation, dictionary::{random_index, Dictionary, Entry}, errors::DictionaryError, keyspace::Keyspace, value::Value};
+
+/// The most members SRANDMEMBER picks with a negative count
+pub const MAX_RANDOM_MEMBERS: u64 = 1024 * 1024;
+
+impl Dictionary {
+    /// # Returns
+    /// How many of the members are new
+    pub fn sadd(&mut self, key: &str, members: Vec<String>) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let set = self.live_entry_or_insert(&mut map, key, || Value::Set(HashSet::new())).value.as_set_mut()?;
+
+        Ok(members.into_iter().filter(|member| set.insert(member.clone())).count())
+    }
+
+    /// # Returns
+    /// How many members were removed
+    pub fn srem(&mut self, key: &str, members: &[String]) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let set = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_set_mut()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(0),
+            Err(e) => return Err(e)
+        };
+
+        let removed = members.iter().filter(|member| set.remove(member.as_str())).count();
+        if set.is_empty() {
+            map.remove(key);
+        }
+
+        Ok(removed)
+    }
+
+    /// Every member, in no particular order, none for a missing key
+    pub fn smembers(&self, key: &str) -> Result<Vec<String>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        Ok(self.members(&mut map, key)?.map_or_else(Vec::new, |set| set.iter().cloned().collect()))
+    }
+
+    pub fn sismember(&self, key: &str, member: &str) -> Result<bool, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        match self.live_entry(&mut map, key) {
+            Ok(entry) => Ok(entry.value.as_set()?.contains(member)),
+            Err(DictionaryError::DoesNotExist) => Ok(false),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// # Returns
+    /// The number of members, 0 for a missing key
+    pub fn scard(&self, key: &str) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        Ok(self.members(&mut map, key)?.map_or(0, HashSet::len))
+    }
+
+    /// SINTER, SUNION and SDIFF, missing keys being empty sets
+    pub fn set_operation(&self, operation: SetOperation, keys: &[String]) -> Result<Vec<String>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        Ok(self.combine(&mut map, operation, keys)?.into_iter().collect())
+    }
+
+    /// The `*STORE` variants, `destination` is overwritten whatever it held
+    /// and deleted if the result is empty
+    /// # Returns
+    /// The number of members in the result
+    pub fn set_operation_store(&mut self, operation: SetOperation, destination: String, keys: &[String]) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let result = self.combine(&mut map, operation, keys)?;
+
+        let len = result.len();
+        match result.is_empty() {
+            true => {
+                map.remove(&destination);
+            },
+            false => {
+                map.insert(destination, Entry::new(Value::Set(result)));
+            }
+        }
+
+        Ok(len)
+    }
+
+    /// Random members, left in the set
+    /// - A positive `count` picks up to that many distinct members
+    /// - A negative one picks exactly `-count` members, possibly the same ones
+    /// # Returns
+    /// - If `-count` is more than `MAX_RANDOM_MEMBERS`, Err(DictionaryError::CountOutOfRange)
+    pub fn srandmember(&self, key: &str, count: i64) -> Result<Vec<String>, DictionaryError> {
+        if count < 0 && count.unsigned_abs() > MAX_RANDOM_MEMBERS {
+            return Err(DictionaryError::CountOutOfRange);
+        }
+
+        let mut map = self.map.lock().unwrap();
+        let Some(set) = self.members(&mut map, key)? else {
+            return Ok(Vec::new());
+        };
+
+        let members: Vec<&String> = set.iter().collect();
+        Ok(match count >= 0 {
+            true => pick_distinct(members, count as usize).into_iter().cloned().collect(),
+            false => (0..count.unsigned_abs()).map(|_| members[random_index(members.len())].clone()).collect()
+        })
+    }
+
+    /// Removes up to `count` random members
+    /// # Returns
+    /// The removed members, none for a missing key
+    pub fn spop(&mut self, key: &str, count: usize) -> Result<Vec<String>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let set = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_set_mut()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(Vec::new()),
+            Err(e) => return Err(e)
+        };
+
+        let popped: Vec<String> = pick_distinct(set.iter().collect(), count).into_iter().cloned().collect();
+        for member in &popped {
+            set.remove(member);
+        }
+        if set.is_empty() {
+            map.remove(key);
+        }
+
+        Ok(popped)
+    }
+
+    /// The set at `key`, None for a missing key
+    fn members<'a>(&self, map: &'a mut Keyspace, key: &str) -> Result<Option<&'a HashSet<String>>, DictionaryError> {
+        match self.live_entry(map, key) {
+            Ok(entry) => Ok(Some(entry.value.as_set()?)),
+            Err(DictionaryError::DoesNotExist) => Ok(None),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Every key is checked to be a set, even once the result can't change.
+    /// Only the members of the result are cloned.
+    fn combine(&self, map: &mut Keyspace, operation: SetOperation, keys: &[String]) -> Result<HashSet<String>, DictionaryError> {
+        // Expired keys are dropped first, so the sets can then be borrowed together
+        for key in keys {
+            self.members(map, key)?;
+        }
+        let empty = HashSet::new();
+        let sets = keys.iter()
+            .map(|key| map.get(key.as_str()).map_or(Ok(&empty), |entry| entry.value.as_set()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some((first, rest)) = sets.split_first() else {
+            return Ok(HashSet::new());
+        };
+
+        let result: HashSet<&String> = match operation {
+            SetOperation::Inter => first.iter().filter(|member| rest.iter().all(|set| set.contains(*member))).collect(),
+            SetOperation::Union => sets.iter().flat_map(|set| set.iter()).collect(),
+            SetOperation::Diff => first.iter().filter(|member| !rest.iter().any(|set| set.contains(*member))).collect()
+        };
+        Ok(result.into_iter().cloned().collect())
+    }
+}
+
+/// Up to `count` of `members` in random order
+fn pick_distinct<T>(mut members: Vec<T>, count: usize) -> Vec<T> {
+    let count = count.min(members.len());
+    // The first `count` steps of a Fisher-Yates shuffle
+    for i in 0..count {
+        let j = i + random_index(members.len() - i);
+        members.swap(i, j);
+    }
+
+    members.truncate(count);
+    members
+}
+
+#[cfg(test)]
+mod set {
+    use crate::{command::CommandResult, dictionary::testing::run};
+
+    use super::*;
+
+    fn sorted(mut members: Vec<String>) -> Vec<String> {
+        members.sort();
+        members
+    }
+
+    #[test]
+    fn sadd_srem() {
+        let mut dict = Dictionary::new();
+
+        assert_eq!(run(&mut dict, "SADD tags rust redis rust"), Ok(CommandResult::SAdd(2)));
+        assert_eq!(run(&mut dict, "SADD tags redis csv"), Ok(CommandResult::SAdd(1)));
+        assert_eq!(run(&mut dict, "SCARD tags"), Ok(CommandResult::SCard(3)));
+        assert_eq!(run(&mut dict, "SISMEMBER tags csv"), Ok(CommandResult::SIsMember(true)));
+        assert_eq!(run(&mut dict, "SISMEMBER tags nope"), Ok(CommandResult::SIsMember(false)));
+        assert_eq!(dict.smembers("tags").map(sorted), Ok(vec!["csv".to_string(), "redis".to_string(), "rust".to_string()]));
+
+        assert_eq!(run(&mut dict, "SREM tags rust nope"), Ok(CommandResult::SRem(1)));
+        assert_eq!(run(&mut dict, "SREM tags redis csv"), Ok(CommandResult::SRem(2)));
+        // Emptied sets are gone
+        assert!(!dict.exists("tags"));
+        assert_eq!(dict.smembers("tags"), Ok(vec![]));
+    }
+
+    #[test]
+    fn algebra() {
+        let mut dict = Dictionary::new();
+        run(&mut dict, "SADD a 1 2 3").unwrap();
+        run(&mut dict, "SADD b 2 3 4").unwrap();
+        run(&mut dict, "SADD c 3").unwrap();
+
+        let strings = |members: &[&str]| members.iter().map(|member| member.to_string()).collect::<Vec<_>>();
+        assert_eq!(dict.set_operation(SetOperation::Inter, &strings(&["a", "b", "c"])), Ok(strings(&["3"])));
+        assert_eq!(dict.set_operation(SetOperation::Union, &strings(&["a", "b"])).map(sorted), Ok(strings(&["1", "2", "3", "4"])));
+        assert_eq!(dict.set_operation(SetOperation::Diff, &strings(&["a", "b"])), Ok(strings(&["1"])));
+        assert_eq!(dict.set_operation(SetOperation::Inter, &strings(&["a", "nope"])), Ok(vec![]));
+        assert_eq!(dict.set_operation(SetOperation::Diff, &strings(&["a", "nope"])).map(sorted), Ok(strings(&["1", "2", "3"])));
+
+        assert_eq!(run(&mut dict, "SUNIONSTORE dest a b"), Ok(CommandResult::SetOpStore(4)));
+        assert_eq!(dict.scard("dest"), Ok(4));
+        // The destination may be one of the sources
+        assert_eq!(run(&mut dict, "SINTERSTORE dest dest c"), Ok(CommandResult::SetOpStore(1)));
+        assert_eq!(dict.smembers("dest"), Ok(strings(&["3"])));
+        assert_eq!(run(&mut dict, "SDIFFSTORE dest c b"), Ok(CommandResult::SetOpStore(0)));
+        assert!(!dict.exists("dest"));
+    }
+
+    #[test]
+    fn random_members() {
+        let mut dict = Dictionary::new();
+        run(&mut dict, "SADD set a b c").unwrap();
+
+        assert!(matches!(run(&mut dict, "SRANDMEMBER set"), Ok(CommandResult::Member(Some(_)))));
+        assert_eq!(dict.srandmember("set", 10).map(sorted), dict.smembers("set").map(sorted));
+        assert_eq!(dict.srandmember("set", 2).map(|members| members.len()), Ok(2));
+        assert_eq!(dict.srandmember("set", -5).map(|members| members.len()), Ok(5));
+        assert_eq!(dict.srandmember("nope", -5), Ok(vec![]));
+        assert_eq!(dict.srandmember("set", i64::MIN), Err(DictionaryError::CountOutOfRange));
+        assert_eq!(dict.scard("set"), Ok(3));
+
+        let popped = dict.spop("set", 2).unwrap();
+        assert_eq!(popped.len(), 2);
+        assert!(popped.iter().all(|member| !dict.sismember("set", member).unwrap()));
+        assert!(matches!(run(&mut dict, "SPOP set"), Ok(CommandResult::Member(Some(_)))));
+        assert!(!dict.exists("set"));
+        assert_eq!(run(&mut dict, "SPOP set"), Ok(CommandResult::Member(None)));
+    }
+
+    #[test]
+    fn store_overwrites_any_type() {
+        let mut dict = Dictionary::new();
+        run(&mut dict, "SET string 19").unwrap();
+        run(&mut dict, "SADD set a").unwrap();
+
+        assert_eq!(run(&mut dict, "SUNIONSTORE string set"), Ok(CommandResult::SetOpStore(1)));
+        assert_eq!(dict.smembers("string"), Ok(vec!["a".to_string()]));
+    }
+}
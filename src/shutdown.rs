@@ -0,0 +1,190 @@
+use std::{collections::HashMap, io, net::{Shutdown as Half, TcpStream}, sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Arc, Condvar, Mutex}};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+/// A connected client the server can hang up on
+pub trait ClientStream: Send + Sync + 'static {
+    /// Makes blocked and future reads return EOF, writes still go through
+    fn close_read(&self) -> io::Result<()>;
+}
+
+impl ClientStream for TcpStream {
+    fn close_read(&self) -> io::Result<()> {
+        self.shutdown(Half::Read)
+    }
+}
+
+#[cfg(unix)]
+impl ClientStream for UnixStream {
+    fn close_read(&self) -> io::Result<()> {
+        self.shutdown(Half::Read)
+    }
+}
+
+/// Shared by the server, its connections and signal handlers.
+/// Once requested, the server stops accepting clients,
+/// answers the commands it already received, and returns.
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+    nosave: AtomicBool,
+    next_client: AtomicUsize,
+    clients: Mutex<HashMap<usize, Arc<dyn ClientStream>>>,
+    /// Signaled along with `requested` for threads blocked in `wait`
+    wake: (Mutex<()>, Condvar),
+    /// Flipped along with `requested` so async tasks can wait on it, see `requested`
+    #[cfg(feature = "async")]
+    notify: tokio::sync::watch::Sender<bool>
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Shutdown {
+            requested: Arc::new(AtomicBool::new(false)),
+            nosave: AtomicBool::new(false),
+            next_client: AtomicUsize::new(0),
+            clients: Mutex::new(HashMap::new()),
+            wake: (Mutex::new(()), Condvar::new()),
+            #[cfg(feature = "async")]
+            notify: tokio::sync::watch::Sender::new(false)
+        }
+    }
+}
+
+impl std::fmt::Debug for Shutdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Shutdown")
+            .field("requested", &self.is_requested())
+            .field("save", &self.should_save())
+            .finish()
+    }
+}
+
+impl Shutdown {
+    /// Asks the server to stop, `save` deciding whether a final snapshot should be written.
+    /// The first request wins.
+    pub fn request(&self, save: bool) {
+        if !self.requested.swap(true, Ordering::SeqCst) {
+            self.nosave.store(!save, Ordering::SeqCst);
+        }
+
+        // Holding the lock, a waiter can't miss this between its check and going to sleep
+        let (lock, condvar) = &self.wake;
+        let _guard = lock.lock().unwrap();
+        condvar.notify_all();
+
+        #[cfg(feature = "async")]
+        self.notify.send_replace(true);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Signals ask for a final snapshot, only `SHUTDOWN NOSAVE` opts out
+    pub fn should_save(&self) -> bool {
+        !self.nosave.load(Ordering::SeqCst)
+    }
+
+    /// Blocks until a shutdown is requested, returns right away if it already was
+    pub fn wait(&self) {
+        let (lock, condvar) = &self.wake;
+        let _guard = condvar.wait_while(lock.lock().unwrap(), |_| !self.is_requested()).unwrap();
+    }
+
+    /// Resolves once a shutdown is requested, right away if it already was
+    #[cfg(feature = "async")]
+    pub async fn requested(&self) {
+        let mut receiver = self.notify.subscribe();
+        // The sender lives as long as `self`, so this can't fail
+        let _ = receiver.wait_for(|requested| *requested).await;
+    }
+
+    /// Requests a shutdown on SIGINT and SIGTERM.
+    /// A second signal while shutting down terminates the process right away.
+    #[cfg(unix)]
+    pub fn register_signals(self: &Arc<Self>) -> io::Result<()> {
+        use signal_hook::{consts::{SIGINT, SIGTERM}, flag, iterator::Signals};
+
+        for signal in [SIGINT, SIGTERM] {
+            flag::register_conditional_shutdown(signal, 1, Arc::clone(&self.requested))?;
+        }
+
+        // Handlers can only raise flags, a thread makes the request so waiters are woken up
+        let mut signals = Signals::new([SIGINT, SIGTERM])?;
+        let shutdown = Arc::clone(self);
+        std::thread::spawn(move || {
+            for _ in signals.forever() {
+                shutdown.request(true);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Keeps track of a client so it can be hung up on, see `close_clients`
+    pub(crate) fn track(&self, client: Arc<dyn ClientStream>) -> usize {
+        let id = self.next_client.fetch_add(1, Ordering::SeqCst);
+        self.clients.lock().unwrap().insert(id, client);
+        id
+    }
+
+    pub(crate) fn untrack(&self, id: usize) {
+        self.clients.lock().unwrap().remove(&id);
+    }
+
+    /// Stops every tracked client from sending more commands,
+    /// what they already sent is still answered
+    pub(crate) fn close_clients(&self) {
+        for client in self.clients.lock().unwrap().values() {
+            let _ = client.close_read();
+        }
+    }
+}
+
+#[cfg(test)]
+mod shutdown {
+    use super::*;
+
+    #[test]
+    fn first_request_wins() {
+        let shutdown = Shutdown::default();
+        assert!(!shutdown.is_requested());
+        assert!(shutdown.should_save());
+
+        shutdown.request(false);
+        shutdown.request(true);
+        assert!(shutdown.is_requested());
+        assert!(!shutdown.should_save());
+    }
+
+    #[test]
+    fn request_wakes_threads() {
+        let shutdown = Arc::new(Shutdown::default());
+
+        let waiter = std::thread::spawn({
+            let shutdown = Arc::clone(&shutdown);
+            move || shutdown.wait()
+        });
+        shutdown.request(true);
+        waiter.join().unwrap();
+
+        shutdown.wait();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn request_wakes_waiters() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let shutdown = Arc::new(Shutdown::default());
+
+        let waiter = runtime.spawn({
+            let shutdown = Arc::clone(&shutdown);
+            async move { shutdown.requested().await }
+        });
+        shutdown.request(true);
+        runtime.block_on(waiter).unwrap();
+
+        // Already requested, nothing to wait for
+        runtime.block_on(shutdown.requested());
+    }
+}
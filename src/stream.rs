@@ -0,0 +1,507 @@
+//! Stream commands
+//! Streams are append-only logs of field/value entries under increasing IDs.
+//! Unlike other types they are kept once emptied, along with their last ID and consumer groups.
+//! A consumer group remembers what it delivered to each consumer until it is acknowledged,
+//! so every entry is delivered at least once.
+
+use std::{collections::{BTreeMap, HashMap}, fmt::Display, ops::{Bound, RangeBounds}, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use crate::{command::{StreamStart, XAddId}, dictionary::Dictionary, errors::DictionaryError, keyspace::Keyspace, value::Value};
+
+/// `ms-seq`, milliseconds since the epoch and a sequence number within them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64
+}
+
+impl StreamId {
+    /// `ms-seq`, or just `ms` which gets `default_seq`
+    pub fn parse(s: &str, default_seq: u64) -> Option<StreamId> {
+        let (ms, seq) = match s.split_once('-') {
+            Some((ms, seq)) => (ms, seq.parse::<u64>().ok()?),
+            None => (s, default_seq)
+        };
+
+        Some(StreamId { ms: ms.parse::<u64>().ok()?, seq })
+    }
+}
+
+impl Display for StreamId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// An ID and its field/value pairs
+pub type StreamEntry = (StreamId, Vec<(String, String)>);
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stream {
+    pub(crate) entries: BTreeMap<StreamId, Vec<(String, String)>>,
+    /// Kept when the entry itself is trimmed, new IDs must be greater
+    pub(crate) last_id: StreamId,
+    pub(crate) groups: HashMap<String, ConsumerGroup>
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConsumerGroup {
+    /// New entries for the group come after this one
+    pub(crate) last_delivered: StreamId,
+    /// Delivered entries waiting for an XACK
+    pub(crate) pending: BTreeMap<StreamId, PendingEntry>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingEntry {
+    pub consumer: String,
+    /// When it was last delivered
+    pub delivered: SystemTime,
+    pub deliveries: u64
+}
+
+/// XPENDING without a range
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingSummary {
+    pub count: usize,
+    /// The lowest and highest pending IDs
+    pub range: Option<(StreamId, StreamId)>,
+    /// How many entries each consumer has pending
+    pub consumers: Vec<(String, usize)>
+}
+
+/// An XPENDING line
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingInfo {
+    pub id: StreamId,
+    pub consumer: String,
+    /// Since the last delivery
+    pub idle: Duration,
+    pub deliveries: u64
+}
+
+impl Stream {
+    /// # Returns
+    /// The ID XADD gives with `id`, greater than every previous one
+    fn next_id(&self, id: XAddId) -> Result<StreamId, DictionaryError> {
+        let last = self.last_id;
+        let after = |ms: u64| match ms.cmp(&last.ms) {
+            std::cmp::Ordering::Greater => Some(StreamId { ms, seq: 0 }),
+            std::cmp::Ordering::Equal => last.seq.checked_add(1).map(|seq| StreamId { ms, seq }),
+            std::cmp::Ordering::Less => None
+        };
+
+        let id = match id {
+            // Clocks going back keep using the last millisecond
+            XAddId::Auto => after(now_ms().max(last.ms)).or_else(|| last.ms.checked_add(1).and_then(after)),
+            XAddId::AutoSeq(ms) => after(ms),
+            XAddId::Explicit(id) => (id > last).then_some(id)
+        };
+
+        id.ok_or(DictionaryError::StreamIdTooSmall)
+    }
+
+    /// Entries between `start` and `end`, an inverted range is empty
+    fn range(&self, start: Bound<StreamId>, end: Bound<StreamId>) -> impl DoubleEndedIterator<Item = (&StreamId, &Vec<(String, String)>)> {
+        // `BTreeMap::range` panics on those
+        let empty = match (start, end) {
+            (Bound::Included(start), Bound::Included(end)) => start > end,
+            (Bound::Included(start) | Bound::Excluded(start), Bound::Included(end) | Bound::Excluded(end)) => start >= end,
+            _ => false
+        };
+
+        let range = match empty {
+            true => None,
+            false => Some(self.entries.range((start, end)))
+        };
+        range.into_iter().flatten()
+    }
+}
+
+impl Dictionary {
+    /// Appends an entry, creating the stream, then trims it down to `max_len` entries if any
+    /// # Returns
+    /// The ID of the new entry
+    pub fn xadd(&mut self, key: &str, id: XAddId, fields: Vec<(String, String)>, max_len: Option<usize>) -> Result<StreamId, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let existed = self.live_entry(&mut map, key).is_ok();
+        let stream = self.live_entry_or_insert(&mut map, key, || Value::Stream(Stream::default())).value.as_stream_mut()?;
+
+        let id = match stream.next_id(id) {
+            Ok(id) => id,
+            Err(e) => {
+                // A failed XADD creates nothing
+                if !existed {
+                    map.remove(key);
+                }
+                return Err(e);
+            }
+        };
+
+        stream.entries.insert(id, fields);
+        stream.last_id = id;
+        if let Some(max_len) = max_len {
+            while stream.entries.len() > max_len {
+                stream.entries.pop_first();
+            }
+        }
+
+        Ok(id)
+    }
+
+    /// # Returns
+    /// The number of entries, 0 for a missing key
+    pub fn xlen(&self, key: &str) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        Ok(self.stream(&mut map, key)?.map_or(0, |stream| stream.entries.len()))
+    }
+
+    /// XRANGE, and XREVRANGE with `rev` which returns the highest IDs first
+    /// # Returns
+    /// Up to `count` entries in range, none for a missing key
+    pub fn xrange(&self, key: &str, start: Bound<StreamId>, end: Bound<StreamId>, count: Option<usize>, rev: bool) -> Result<Vec<StreamEntry>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let Some(stream) = self.stream(&mut map, key)? else {
+            return Ok(Vec::new());
+        };
+
+        let range = stream.range(start, end).map(|(id, fields)| (*id, fields.clone()));
+        let count = count.unwrap_or(usize::MAX);
+        Ok(match rev {
+            true => range.rev().take(count).collect(),
+            false => range.take(count).collect()
+        })
+    }
+
+    /// Entries after the given IDs of every stream, `$` never having any as nothing blocks
+    /// # Returns
+    /// The streams with entries, each with up to `count` of them
+    pub fn xread(&self, streams: &[(String, StreamStart)], count: Option<usize>) -> Result<Vec<(String, Vec<StreamEntry>)>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+
+        let mut read = Vec::new();
+        for (key, start) in streams {
+            let Some(stream) = self.stream(&mut map, key)? else {
+                continue;
+            };
+            let after = match start {
+                StreamStart::After(id) => *id,
+                _ => stream.last_id
+            };
+
+            let entries: Vec<StreamEntry> = stream.range(Bound::Excluded(after), Bound::Unbounded)
+                .take(count.unwrap_or(usize::MAX))
+                .map(|(id, fields)| (*id, fields.clone()))
+                .collect();
+            if !entries.is_empty() {
+                read.push((key.clone(), entries));
+            }
+        }
+
+        Ok(read)
+    }
+
+    /// XGROUP CREATE, the group gets the entries after `start`
+    pub fn xgroup_create(&mut self, key: &str, group: String, start: StreamStart, make_stream: bool) -> Result<(), DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        if make_stream {
+            self.live_entry_or_insert(&mut map, key, || Value::Stream(Stream::default()));
+        }
+        let stream = self.stream(&mut map, key)?.ok_or(DictionaryError::NoGroup)?;
+
+        if stream.groups.contains_key(&group) {
+            return Err(DictionaryError::GroupExists);
+        }
+        let last_delivered = match start {
+            StreamStart::After(id) => id,
+            _ => stream.last_id
+        };
+        stream.groups.insert(group, ConsumerGroup { last_delivered, pending: BTreeMap::new() });
+
+        Ok(())
+    }
+
+    /// XGROUP DESTROY, pending entries go with the group
+    /// # Returns
+    /// Whether the group existed
+    pub fn xgroup_destroy(&mut self, key: &str, group: &str) -> Result<bool, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let stream = self.stream(&mut map, key)?.ok_or(DictionaryError::NoGroup)?;
+        Ok(stream.groups.remove(group).is_some())
+    }
+
+    /// XREADGROUP, `>` reads entries never delivered to the group and marks them pending for `consumer`,
+    /// an ID rereads what `consumer` has pending after it
+    /// # Returns
+    /// The streams with entries, each with up to `count` of them
+    pub fn xreadgroup(&mut self, group: &str, consumer: &str, streams: &[(String, StreamStart)], count: Option<usize>) -> Result<Vec<(String, Vec<StreamEntry>)>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let now = SystemTime::now();
+        let count = count.unwrap_or(usize::MAX);
+
+        let mut read = Vec::new();
+        for (key, start) in streams {
+            let stream = self.stream(&mut map, key)?.ok_or(DictionaryError::NoGroup)?;
+            let consumer_group = stream.groups.get_mut(group).ok_or(DictionaryError::NoGroup)?;
+
+            let entries: Vec<StreamEntry> = match start {
+                StreamStart::After(after) => consumer_group.pending
+                    .range_mut((Bound::Excluded(*after), Bound::Unbounded))
+                    .filter(|(_, pending)| pending.consumer == consumer)
+                    // Trimmed entries stay pending until acknowledged but can't be read anymore
+                    .filter_map(|(id, pending)| Some((id, pending, stream.entries.get(id)?)))
+                    .take(count)
+                    .map(|(id, pending, fields)| {
+                        pending.delivered = now;
+                        pending.deliveries += 1;
+                        (*id, fields.clone())
+                    })
+                    .collect(),
+                _ => {
+                    let entries: Vec<StreamEntry> = stream.entries
+                        .range((Bound::Excluded(consumer_group.last_delivered), Bound::Unbounded))
+                        .take(count)
+                        .map(|(id, fields)| (*id, fields.clone()))
+                        .collect();
+                    for (id, _) in &entries {
+                        consumer_group.pending.insert(*id, PendingEntry { consumer: consumer.to_string(), delivered: now, deliveries: 1 });
+                        consumer_group.last_delivered = *id;
+                    }
+                    entries
+                }
+            };
+
+            if !entries.is_empty() {
+                read.push((key.clone(), entries));
+            }
+        }
+
+        Ok(read)
+    }
+
+    /// # Returns
+    /// How many of the IDs were pending, 0 for a missing key or group
+    pub fn xack(&mut self, key: &str, group: &str, ids: &[StreamId]) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let Some(consumer_group) = self.stream(&mut map, key)?.and_then(|stream| stream.groups.get_mut(group)) else {
+            return Ok(0);
+        };
+
+        Ok(ids.iter().filter(|id| consumer_group.pending.remove(id).is_some()).count())
+    }
+
+    /// XPENDING without a range
+    pub fn xpending(&self, key: &str, group: &str) -> Result<PendingSummary, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let stream = self.stream(&mut map, key)?.ok_or(DictionaryError::NoGroup)?;
+        let consumer_group = stream.groups.get(group).ok_or(DictionaryError::NoGroup)?;
+
+        let pending = &consumer_group.pending;
+        let range = pending.first_key_value().zip(pending.last_key_value()).map(|((first, _), (last, _))| (*first, *last));
+        let mut consumers: BTreeMap<&str, usize> = BTreeMap::new();
+        for entry in pending.values() {
+            *consumers.entry(&entry.consumer).or_default() += 1;
+        }
+
+        Ok(PendingSummary {
+            count: pending.len(),
+            range,
+            consumers: consumers.into_iter().map(|(consumer, count)| (consumer.to_string(), count)).collect()
+        })
+    }
+
+    /// XPENDING with a range, optionally for a single consumer
+    /// # Returns
+    /// Up to `count` pending entries from `start` on
+    pub fn xpending_range(&self, key: &str, group: &str, start: Bound<StreamId>, end: Bound<StreamId>, count: usize, consumer: Option<&str>) -> Result<Vec<PendingInfo>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let stream = self.stream(&mut map, key)?.ok_or(DictionaryError::NoGroup)?;
+        let consumer_group = stream.groups.get(group).ok_or(DictionaryError::NoGroup)?;
+
+        let now = SystemTime::now();
+        Ok(consumer_group.pending.iter()
+            .filter(|(id, _)| (start, end).contains(*id))
+            .filter(|(_, pending)| consumer.is_none_or(|consumer| pending.consumer == consumer))
+            .take(count)
+            .map(|(id, pending)| PendingInfo {
+                id: *id,
+                consumer: pending.consumer.clone(),
+                idle: now.duration_since(pending.delivered).unwrap_or_default(),
+                deliveries: pending.deliveries
+            })
+            .collect())
+    }
+
+    /// Hands the pending entries idle for at least `min_idle` over to `consumer`,
+    /// as if they were just delivered to it. Trimmed ones are dropped from the pending entries.
+    /// # Returns
+    /// The claimed entries
+    pub fn xclaim(&mut self, key: &str, group: &str, consumer: &str, min_idle: Duration, ids: &[StreamId]) -> Result<Vec<StreamEntry>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let stream = self.stream(&mut map, key)?.ok_or(DictionaryError::NoGroup)?;
+        let consumer_group = stream.groups.get_mut(group).ok_or(DictionaryError::NoGroup)?;
+
+        let now = SystemTime::now();
+        let mut claimed = Vec::new();
+        for id in ids {
+            let Some(pending) = consumer_group.pending.get_mut(id) else {
+                continue;
+            };
+            let Some(fields) = stream.entries.get(id) else {
+                consumer_group.pending.remove(id);
+                continue;
+            };
+
+            if now.duration_since(pending.delivered).unwrap_or_default() >= min_idle {
+                *pending = PendingEntry { consumer: consumer.to_string(), delivered: now, deliveries: pending.deliveries + 1 };
+                claimed.push((*id, fields.clone()));
+            }
+        }
+
+        Ok(claimed)
+    }
+
+    /// The stream at `key`, None for a missing key
+    fn stream<'a>(&self, map: &'a mut Keyspace, key: &str) -> Result<Option<&'a mut Stream>, DictionaryError> {
+        match self.live_entry(map, key) {
+            Ok(entry) => Ok(Some(entry.value.as_stream_mut()?)),
+            Err(DictionaryError::DoesNotExist) => Ok(None),
+            Err(e) => Err(e)
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[cfg(test)]
+mod stream {
+    use crate::{command::CommandResult, dictionary::testing::run};
+
+    use super::*;
+
+    fn id(ms: u64, seq: u64) -> StreamId {
+        StreamId { ms, seq }
+    }
+
+    fn ids(entries: &[StreamEntry]) -> Vec<StreamId> {
+        entries.iter().map(|(id, _)| *id).collect()
+    }
+
+    #[test]
+    fn xadd_ids() {
+        let mut dict = Dictionary::new();
+
+        assert_eq!(run(&mut dict, "XADD log 5-1 level info"), Ok(CommandResult::XAdd(id(5, 1))));
+        assert_eq!(run(&mut dict, "XADD log 5-* level warn"), Ok(CommandResult::XAdd(id(5, 2))));
+        assert_eq!(run(&mut dict, "XADD log 7 level info"), Ok(CommandResult::XAdd(id(7, 0))));
+        assert_eq!(run(&mut dict, "XADD log 7-0 level info"), Err(DictionaryError::StreamIdTooSmall));
+        assert_eq!(run(&mut dict, "XADD log 6-* level info"), Err(DictionaryError::StreamIdTooSmall));
+        assert!(matches!(run(&mut dict, "XADD log * level info"), Ok(CommandResult::XAdd(new)) if new.ms > 7));
+        assert_eq!(run(&mut dict, "XLEN log"), Ok(CommandResult::XLen(4)));
+
+        // 0-0 is never valid, and a failed XADD creates nothing
+        assert_eq!(run(&mut dict, "XADD other 0-0 a b"), Err(DictionaryError::StreamIdTooSmall));
+        assert!(!dict.exists("other"));
+        assert_eq!(run(&mut dict, "XADD other 0-* a b"), Ok(CommandResult::XAdd(id(0, 1))));
+    }
+
+    #[test]
+    fn xadd_maxlen() {
+        let mut dict = Dictionary::new();
+        for ms in 1..=5 {
+            dict.xadd("log", XAddId::Explicit(id(ms, 0)), vec![("n".to_string(), ms.to_string())], None).unwrap();
+        }
+
+        run(&mut dict, "XADD log MAXLEN ~ 3 6 n 6").unwrap();
+        assert_eq!(dict.xrange("log", Bound::Unbounded, Bound::Unbounded, None, false).map(|entries| ids(&entries)), Ok(vec![id(4, 0), id(5, 0), id(6, 0)]));
+
+        // Emptied streams stay, and so does their last ID
+        run(&mut dict, "XADD log MAXLEN 0 7 n 7").unwrap();
+        assert_eq!(dict.xlen("log"), Ok(0));
+        assert_eq!(run(&mut dict, "TYPE log"), Ok(CommandResult::Type("stream".to_string())));
+        assert_eq!(run(&mut dict, "XADD log 7 n 7"), Err(DictionaryError::StreamIdTooSmall));
+    }
+
+    #[test]
+    fn xrange() {
+        let mut dict = Dictionary::new();
+        for ms in 1..=5 {
+            dict.xadd("log", XAddId::Explicit(id(ms, 0)), vec![("n".to_string(), ms.to_string())], None).unwrap();
+        }
+
+        assert_eq!(
+            run(&mut dict, "XRANGE log 2 3"),
+            Ok(CommandResult::Entries(vec![(id(2, 0), vec![("n".to_string(), "2".to_string())]), (id(3, 0), vec![("n".to_string(), "3".to_string())])]))
+        );
+        let range = |dict: &mut Dictionary, command: &str| match run(dict, command) {
+            Ok(CommandResult::Entries(entries)) => ids(&entries),
+            other => panic!("{other:?}")
+        };
+        assert_eq!(range(&mut dict, "XRANGE log - + COUNT 2"), [id(1, 0), id(2, 0)]);
+        assert_eq!(range(&mut dict, "XRANGE log (4-0 +"), [id(5, 0)]);
+        assert_eq!(range(&mut dict, "XREVRANGE log + 4 COUNT 5"), [id(5, 0), id(4, 0)]);
+        assert_eq!(range(&mut dict, "XRANGE log 4 2"), []);
+        assert_eq!(range(&mut dict, "XRANGE log (3 3"), []);
+        assert_eq!(range(&mut dict, "XRANGE nope - +"), []);
+    }
+
+    #[test]
+    fn xread() {
+        let mut dict = Dictionary::new();
+        dict.xadd("a", XAddId::Explicit(id(1, 0)), vec![("n".to_string(), "1".to_string())], None).unwrap();
+        dict.xadd("a", XAddId::Explicit(id(2, 0)), vec![("n".to_string(), "2".to_string())], None).unwrap();
+        dict.xadd("b", XAddId::Explicit(id(3, 0)), vec![("n".to_string(), "3".to_string())], None).unwrap();
+
+        let read = dict.xread(&[("a".to_string(), StreamStart::After(id(1, 0))), ("b".to_string(), StreamStart::After(id(0, 0)))], None).unwrap();
+        assert_eq!(read.iter().map(|(key, entries)| (key.as_str(), ids(entries))).collect::<Vec<_>>(), [("a", vec![id(2, 0)]), ("b", vec![id(3, 0)])]);
+
+        assert_eq!(run(&mut dict, "XREAD COUNT 1 STREAMS a nope 0 0").map(|result| result.to_string()), Ok("a 1-0 n 1".to_string()));
+        assert_eq!(run(&mut dict, "XREAD STREAMS a $"), Ok(CommandResult::XRead(vec![])));
+    }
+
+    #[test]
+    fn consumer_groups() {
+        let mut dict = Dictionary::new();
+        for ms in 1..=3 {
+            dict.xadd("jobs", XAddId::Explicit(id(ms, 0)), vec![("job".to_string(), ms.to_string())], None).unwrap();
+        }
+
+        assert_eq!(run(&mut dict, "XGROUP CREATE jobs workers 0"), Ok(CommandResult::XGroupCreate));
+        assert_eq!(run(&mut dict, "XGROUP CREATE jobs workers 0"), Err(DictionaryError::GroupExists));
+        assert_eq!(run(&mut dict, "XGROUP CREATE nope workers $"), Err(DictionaryError::NoGroup));
+        assert_eq!(run(&mut dict, "XREADGROUP GROUP nope alex STREAMS jobs >"), Err(DictionaryError::NoGroup));
+
+        // Each new entry goes to a single consumer
+        let read = dict.xreadgroup("workers", "alex", &[("jobs".to_string(), StreamStart::New)], Some(2)).unwrap();
+        assert_eq!(ids(&read[0].1), [id(1, 0), id(2, 0)]);
+        let read = dict.xreadgroup("workers", "sam", &[("jobs".to_string(), StreamStart::New)], None).unwrap();
+        assert_eq!(ids(&read[0].1), [id(3, 0)]);
+        assert_eq!(dict.xreadgroup("workers", "sam", &[("jobs".to_string(), StreamStart::New)], None), Ok(vec![]));
+
+        let summary = dict.xpending("jobs", "workers").unwrap();
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.range, Some((id(1, 0), id(3, 0))));
+        assert_eq!(summary.consumers, [("alex".to_string(), 2), ("sam".to_string(), 1)]);
+
+        // Rereading the history of a consumer
+        let read = dict.xreadgroup("workers", "alex", &[("jobs".to_string(), StreamStart::After(id(0, 0)))], None).unwrap();
+        assert_eq!(ids(&read[0].1), [id(1, 0), id(2, 0)]);
+
+        assert_eq!(run(&mut dict, "XACK jobs workers 1-0 3-0 9-0"), Ok(CommandResult::XAck(2)));
+        let pending = dict.xpending_range("jobs", "workers", Bound::Unbounded, Bound::Unbounded, 10, None).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].id, pending[0].consumer.as_str(), pending[0].deliveries), (id(2, 0), "alex", 2));
+
+        // Nothing has been idle for an hour
+        assert_eq!(run(&mut dict, "XCLAIM jobs workers sam 3600000 2-0"), Ok(CommandResult::Entries(vec![])));
+        let claimed = dict.xclaim("jobs", "workers", "sam", Duration::ZERO, &[id(2, 0), id(3, 0)]).unwrap();
+        assert_eq!(ids(&claimed), [id(2, 0)]);
+        let pending = dict.xpending_range("jobs", "workers", Bound::Unbounded, Bound::Unbounded, 10, Some("sam")).unwrap();
+        assert_eq!((pending[0].id, pending[0].deliveries), (id(2, 0), 3));
+
+        assert_eq!(run(&mut dict, "XGROUP DESTROY jobs workers"), Ok(CommandResult::XGroupDestroy(true)));
+        assert_eq!(dict.xpending("jobs", "workers"), Err(DictionaryError::NoGroup));
+    }
+}
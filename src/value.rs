@@ -0,0 +1,141 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{errors::DictionaryError, hyperloglog::HyperLogLog, scan_map::ScanMap, stream::Stream, zset::SortedSet};
+
+/// What a key holds, commands of one type fail with
+/// `DictionaryError::InvalidOperationType` on the others
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Binary-safe, bitmaps may hold any bytes
+    String(Vec<u8>),
+    List(VecDeque<String>),
+    Hash(ScanMap<String>),
+    Set(HashSet<String>),
+    ZSet(SortedSet),
+    Stream(Stream),
+    HyperLogLog(HyperLogLog)
+}
+
+impl Value {
+    /// As reported by TYPE and filtered on by SCAN's TYPE
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+            Value::ZSet(_) => "zset",
+            Value::Stream(_) => "stream",
+            Value::HyperLogLog(_) => "hyperloglog"
+        }
+    }
+
+    pub fn as_bytes(&self) -> Result<&Vec<u8>, DictionaryError> {
+        match self {
+            Value::String(bytes) => Ok(bytes),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+
+    pub fn as_bytes_mut(&mut self) -> Result<&mut Vec<u8>, DictionaryError> {
+        match self {
+            Value::String(bytes) => Ok(bytes),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&VecDeque<String>, DictionaryError> {
+        match self {
+            Value::List(list) => Ok(list),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Result<&mut VecDeque<String>, DictionaryError> {
+        match self {
+            Value::List(list) => Ok(list),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+
+    pub fn as_hash(&self) -> Result<&ScanMap<String>, DictionaryError> {
+        match self {
+            Value::Hash(hash) => Ok(hash),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+
+    pub fn as_hash_mut(&mut self) -> Result<&mut ScanMap<String>, DictionaryError> {
+        match self {
+            Value::Hash(hash) => Ok(hash),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+
+    pub fn as_set(&self) -> Result<&HashSet<String>, DictionaryError> {
+        match self {
+            Value::Set(set) => Ok(set),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+
+    pub fn as_set_mut(&mut self) -> Result<&mut HashSet<String>, DictionaryError> {
+        match self {
+            Value::Set(set) => Ok(set),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+
+    pub fn as_zset(&self) -> Result<&SortedSet, DictionaryError> {
+        match self {
+            Value::ZSet(zset) => Ok(zset),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+
+    pub fn as_zset_mut(&mut self) -> Result<&mut SortedSet, DictionaryError> {
+        match self {
+            Value::ZSet(zset) => Ok(zset),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+
+    pub fn as_stream_mut(&mut self) -> Result<&mut Stream, DictionaryError> {
+        match self {
+            Value::Stream(stream) => Ok(stream),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+
+    pub fn as_hyperloglog(&self) -> Result<&HyperLogLog, DictionaryError> {
+        match self {
+            Value::HyperLogLog(hll) => Ok(hll),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+
+    pub fn as_hyperloglog_mut(&mut self) -> Result<&mut HyperLogLog, DictionaryError> {
+        match self {
+            Value::HyperLogLog(hll) => Ok(hll),
+            _ => Err(DictionaryError::InvalidOperationType)
+        }
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value.into_bytes())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.as_bytes().to_vec())
+    }
+}
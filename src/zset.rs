@@ -0,0 +1,485 @@
+//! Sorted set commands
+//! Sorted sets are created by adding to a missing key and deleted once emptied.
+//! Members are ordered by score, then by member, with O(log n) updates and score or lex lookups;
+//! ranks are counted by walking the set from its nearer end, so they cost O(n) at worst.
+
+use std::{cmp::Ordering, collections::{BTreeSet, HashMap}, ops::Bound};
+
+use crate::{command::{SetCondition, ZAddComparison, ZAddOptions, ZPopEnd, ZRangeBy, ZRangeOptions}, dictionary::{range_bounds, Dictionary}, errors::DictionaryError, value::Value};
+
+/// Ordered by `f64::total_cmp`, never NaN
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Members with a score, kept both by member and in order
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SortedSet {
+    scores: HashMap<String, f64>,
+    order: BTreeSet<(Score, String)>
+}
+
+impl SortedSet {
+    pub fn new() -> Self {
+        SortedSet::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Adds `member` or moves it to its new score, which must not be NaN
+    /// # Returns
+    /// Whether the member is new
+    pub fn insert(&mut self, member: String, score: f64) -> bool {
+        // -0 and 0 are the same score
+        let score = score + 0.0;
+        let previous = self.scores.insert(member.clone(), score);
+        if let Some(previous) = previous {
+            self.order.remove(&(Score(previous), member.clone()));
+        }
+
+        self.order.insert((Score(score), member));
+        previous.is_none()
+    }
+
+    /// # Returns
+    /// Whether the member was there
+    pub fn remove(&mut self, member: &str) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => self.order.remove(&(Score(score), member.to_string())),
+            None => false
+        }
+    }
+
+    /// Position of `member` from the lowest score on, in O(min(rank, len - rank))
+    pub fn rank(&self, member: &str) -> Option<usize> {
+        let key = (Score(self.score(member)?), member.to_string());
+        let mut below = self.order.range(..&key);
+        let mut above = self.order.range((Bound::Excluded(&key), Bound::Unbounded));
+
+        // Whichever side runs out first tells the rank
+        let mut steps = 0;
+        loop {
+            if below.next().is_none() {
+                return Some(steps);
+            }
+            if above.next().is_none() {
+                return Some(self.len() - 1 - steps);
+            }
+            steps += 1;
+        }
+    }
+
+    /// Members from rank `start` to `end` inclusive, which must be in bounds,
+    /// reached from the nearer end of the set
+    pub fn range_by_rank(&self, start: usize, end: usize) -> Box<dyn DoubleEndedIterator<Item = (&String, f64)> + '_> {
+        let count = end - start + 1;
+        match start <= self.len() - 1 - end {
+            true => Box::new(self.iter().skip(start).take(count)),
+            false => Box::new(self.iter().rev().skip(self.len() - 1 - end).take(count).rev())
+        }
+    }
+
+    /// Members with a score within `min` and `max`, found in O(log n)
+    pub fn range_by_score<'a>(&'a self, min: Bound<&'a f64>, max: Bound<&'a f64>) -> Box<dyn DoubleEndedIterator<Item = (&'a String, f64)> + 'a> {
+        if let (Bound::Included(min) | Bound::Excluded(min), Bound::Included(max) | Bound::Excluded(max)) = (min, max)
+            && Score(*min) > Score(*max) {
+            return Box::new(std::iter::empty());
+        }
+
+        // Every member with a bound score is in, exclusive bounds are filtered afterwards
+        let lower = match min {
+            Bound::Included(min) | Bound::Excluded(min) => Bound::Included((Score(*min), String::new())),
+            Bound::Unbounded => Bound::Unbounded
+        };
+        let upper = match max {
+            Bound::Included(max) | Bound::Excluded(max) if *max < f64::INFINITY => Bound::Excluded((Score(max.next_up()), String::new())),
+            _ => Bound::Unbounded
+        };
+        Box::new(self.order.range((lower, upper))
+            .map(|(score, member)| (member, score.0))
+            .filter(move |(_, score)| locate(score, min, max) == Ordering::Equal))
+    }
+
+    /// Members within `min` and `max`, found in O(log n) as long as every member
+    /// has the same score, which BYLEX expects
+    pub fn range_by_lex<'a>(&'a self, min: Bound<&'a String>, max: Bound<&'a String>) -> Box<dyn DoubleEndedIterator<Item = (&'a String, f64)> + 'a> {
+        let Some((score, _)) = self.order.first() else {
+            return Box::new(std::iter::empty());
+        };
+        if let (Bound::Included(min) | Bound::Excluded(min), Bound::Included(max) | Bound::Excluded(max)) = (min, max)
+            && min > max {
+            return Box::new(std::iter::empty());
+        }
+
+        let lower = match min {
+            Bound::Included(min) | Bound::Excluded(min) => Bound::Included((*score, min.clone())),
+            Bound::Unbounded => Bound::Unbounded
+        };
+        let upper = match max {
+            Bound::Included(max) | Bound::Excluded(max) => Bound::Included((*score, max.clone())),
+            Bound::Unbounded => Bound::Unbounded
+        };
+        Box::new(self.order.range((lower, upper))
+            .map(|(score, member)| (member, score.0))
+            .filter(move |(member, _)| locate(*member, min, max) == Ordering::Equal))
+    }
+
+    /// Members and scores from the lowest score on
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&String, f64)> + ExactSizeIterator {
+        self.order.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// Removes the member with the lowest or highest score
+    pub fn pop(&mut self, end: ZPopEnd) -> Option<(String, f64)> {
+        let (score, member) = match end {
+            ZPopEnd::Min => self.order.pop_first()?,
+            ZPopEnd::Max => self.order.pop_last()?
+        };
+
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+}
+
+impl FromIterator<(String, f64)> for SortedSet {
+    fn from_iter<I: IntoIterator<Item = (String, f64)>>(iter: I) -> Self {
+        let mut set = SortedSet::new();
+        for (member, score) in iter {
+            set.insert(member, score);
+        }
+
+        set
+    }
+}
+
+impl Dictionary {
+    /// # Returns
+    /// How many members are new, or were changed too with `options.changed`
+    pub fn zadd(&mut self, key: &str, pairs: Vec<(f64, String)>, options: ZAddOptions) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let set = self.live_entry_or_insert(&mut map, key, || Value::ZSet(SortedSet::new())).value.as_zset_mut()?;
+
+        let mut count = 0;
+        for (score, member) in pairs {
+            let previous = set.score(&member);
+            if zadd_allowed(previous, score, options) {
+                set.insert(member, score);
+                if previous.is_none() || (options.changed && previous != Some(score)) {
+                    count += 1;
+                }
+            }
+        }
+
+        // XX on a missing key adds nothing
+        if set.is_empty() {
+            map.remove(key);
+        }
+
+        Ok(count)
+    }
+
+    /// ZADD with INCR, adding `by` to the score of `member`, which starts at 0 when missing
+    /// # Returns
+    /// The new score, None if `options` prevented the update
+    pub fn zadd_incr(&mut self, key: &str, by: f64, member: String, options: ZAddOptions) -> Result<Option<f64>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let set = self.live_entry_or_insert(&mut map, key, || Value::ZSet(SortedSet::new())).value.as_zset_mut()?;
+
+        let previous = set.score(&member);
+        let score = previous.unwrap_or_default() + by;
+        let result = match score.is_nan() {
+            true => Err(DictionaryError::NotANumber),
+            false if zadd_allowed(previous, score, options) => {
+                set.insert(member, score);
+                Ok(Some(score))
+            },
+            false => Ok(None)
+        };
+
+        if set.is_empty() {
+            map.remove(key);
+        }
+
+        result
+    }
+
+    /// # Returns
+    /// The new score of `member`
+    pub fn zincr_by(&mut self, key: &str, by: f64, member: String) -> Result<f64, DictionaryError> {
+        // Without options the score is always updated
+        Ok(self.zadd_incr(key, by, member, ZAddOptions::default())?.unwrap())
+    }
+
+    /// # Returns
+    /// The members in range, each with its score, none for a missing key
+    pub fn zrange(&self, key: &str, options: &ZRangeOptions) -> Result<Vec<(String, f64)>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let set = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_zset()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(Vec::new()),
+            Err(e) => return Err(e)
+        };
+
+        let in_range = match &options.by {
+            // Ranks count from the highest score with REV
+            ZRangeBy::Rank(start, end) => match range_bounds(*start, *end, set.len()) {
+                Some((start, end)) if options.rev => set.range_by_rank(set.len() - 1 - end, set.len() - 1 - start),
+                Some((start, end)) => set.range_by_rank(start, end),
+                None => Box::new(std::iter::empty())
+            },
+            ZRangeBy::Score(min, max) => set.range_by_score(min.as_ref(), max.as_ref()),
+            ZRangeBy::Lex(min, max) => set.range_by_lex(min.as_ref(), max.as_ref())
+        };
+        let in_range: Box<dyn Iterator<Item = (&String, f64)>> = match options.rev {
+            true => Box::new(in_range.rev()),
+            false => in_range
+        };
+
+        let (offset, count) = options.limit.unwrap_or((0, usize::MAX));
+        Ok(in_range.skip(offset).take(count).map(|(member, score)| (member.clone(), score)).collect())
+    }
+
+    /// # Returns
+    /// The position of `member` from the lowest score on, None if it is missing
+    pub fn zrank(&self, key: &str, member: &str) -> Result<Option<usize>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        match self.live_entry(&mut map, key) {
+            Ok(entry) => Ok(entry.value.as_zset()?.rank(member)),
+            Err(DictionaryError::DoesNotExist) => Ok(None),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// # Returns
+    /// The score of `member`, None if it is missing
+    pub fn zscore(&self, key: &str, member: &str) -> Result<Option<f64>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        match self.live_entry(&mut map, key) {
+            Ok(entry) => Ok(entry.value.as_zset()?.score(member)),
+            Err(DictionaryError::DoesNotExist) => Ok(None),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// # Returns
+    /// The number of members, 0 for a missing key
+    pub fn zcard(&self, key: &str) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        match self.live_entry(&mut map, key) {
+            Ok(entry) => Ok(entry.value.as_zset()?.len()),
+            Err(DictionaryError::DoesNotExist) => Ok(0),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// # Returns
+    /// How many members were removed
+    pub fn zrem(&mut self, key: &str, members: &[String]) -> Result<usize, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let set = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_zset_mut()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(0),
+            Err(e) => return Err(e)
+        };
+
+        let removed = members.iter().filter(|member| set.remove(member)).count();
+        if set.is_empty() {
+            map.remove(key);
+        }
+
+        Ok(removed)
+    }
+
+    /// Removes up to `count` members with the lowest or highest scores
+    /// # Returns
+    /// The members in the order they were popped, each with its score
+    pub fn zpop(&mut self, key: &str, count: usize, end: ZPopEnd) -> Result<Vec<(String, f64)>, DictionaryError> {
+        let mut map = self.map.lock().unwrap();
+        let set = match self.live_entry(&mut map, key) {
+            Ok(entry) => entry.value.as_zset_mut()?,
+            Err(DictionaryError::DoesNotExist) => return Ok(Vec::new()),
+            Err(e) => return Err(e)
+        };
+
+        let popped = (0..count).map_while(|_| set.pop(end)).collect();
+        if set.is_empty() {
+            map.remove(key);
+        }
+
+        Ok(popped)
+    }
+}
+
+/// Whether ZADD may set `member` to `score`, given its `previous` one
+fn zadd_allowed(previous: Option<f64>, score: f64, options: ZAddOptions) -> bool {
+    let condition = match options.condition {
+        Some(SetCondition::Nx) => previous.is_none(),
+        Some(SetCondition::Xx) => previous.is_some(),
+        None => true
+    };
+
+    // GT and LT only stop updates, never new members
+    condition && match (previous, options.comparison) {
+        (Some(previous), Some(ZAddComparison::Gt)) => score > previous,
+        (Some(previous), Some(ZAddComparison::Lt)) => score < previous,
+        _ => true
+    }
+}
+
+/// # Returns
+/// Whether `value` is below, within or above the range
+fn locate<T: PartialOrd + ?Sized>(value: &T, min: Bound<&T>, max: Bound<&T>) -> Ordering {
+    let below = match min {
+        Bound::Included(min) => value < min,
+        Bound::Excluded(min) => value <= min,
+        Bound::Unbounded => false
+    };
+    let above = match max {
+        Bound::Included(max) => value > max,
+        Bound::Excluded(max) => value >= max,
+        Bound::Unbounded => false
+    };
+
+    match (below, above) {
+        (true, _) => Ordering::Less,
+        (_, true) => Ordering::Greater,
+        _ => Ordering::Equal
+    }
+}
+
+#[cfg(test)]
+mod zset {
+    use crate::{command::CommandResult, dictionary::testing::run};
+
+    use super::*;
+
+    fn members(dict: &mut Dictionary, command: &str) -> Vec<String> {
+        match run(dict, command) {
+            Ok(CommandResult::List(members)) => members,
+            other => panic!("{other:?}")
+        }
+    }
+
+    fn leaderboard() -> Dictionary {
+        let mut dict = Dictionary::new();
+        run(&mut dict, "ZADD board 10 alex 30 sam 20 kim 20 jo").unwrap();
+        dict
+    }
+
+    #[test]
+    fn zadd_options() {
+        let mut dict = leaderboard();
+
+        assert_eq!(run(&mut dict, "ZADD board 5 alex 1 new"), Ok(CommandResult::ZAdd(1)));
+        assert_eq!(run(&mut dict, "ZADD board CH 6 alex 1 new"), Ok(CommandResult::ZAdd(1)));
+        assert_eq!(run(&mut dict, "ZADD board NX 100 alex 2 other"), Ok(CommandResult::ZAdd(1)));
+        assert_eq!(run(&mut dict, "ZADD board XX 100 alex 2 ghost"), Ok(CommandResult::ZAdd(0)));
+        assert_eq!(dict.zscore("board", "alex"), Ok(Some(100.0)));
+        assert_eq!(dict.zscore("board", "ghost"), Ok(None));
+
+        assert_eq!(run(&mut dict, "ZADD board GT CH 50 alex 40 sam"), Ok(CommandResult::ZAdd(1)));
+        assert_eq!(run(&mut dict, "ZADD board LT CH 150 alex 45 sam"), Ok(CommandResult::ZAdd(0)));
+        assert_eq!(dict.zscore("board", "sam"), Ok(Some(40.0)));
+
+        assert_eq!(run(&mut dict, "ZADD board INCR 5 sam"), Ok(CommandResult::Score(Some(45.0))));
+        assert_eq!(run(&mut dict, "ZADD board NX INCR 5 sam"), Ok(CommandResult::Score(None)));
+        assert_eq!(run(&mut dict, "ZINCRBY board -45 sam"), Ok(CommandResult::IncrByFloat(0.0)));
+        assert_eq!(run(&mut dict, "ZINCRBY board +inf sam"), Ok(CommandResult::IncrByFloat(f64::INFINITY)));
+        assert_eq!(run(&mut dict, "ZINCRBY board -inf sam"), Err(DictionaryError::NotANumber));
+
+        // Nothing is left behind by XX on a missing key
+        assert_eq!(run(&mut dict, "ZADD nope XX 1 a"), Ok(CommandResult::ZAdd(0)));
+        assert!(!dict.exists("nope"));
+    }
+
+    #[test]
+    fn zrange_by_rank() {
+        let mut dict = leaderboard();
+
+        assert_eq!(members(&mut dict, "ZRANGE board 0 -1"), ["alex", "jo", "kim", "sam"]);
+        assert_eq!(members(&mut dict, "ZRANGE board 0 1 REV"), ["sam", "kim"]);
+        assert_eq!(members(&mut dict, "ZRANGE board -2 100"), ["kim", "sam"]);
+        assert_eq!(members(&mut dict, "ZRANGE board 2 3 REV"), ["jo", "alex"]);
+        assert_eq!(members(&mut dict, "ZRANGE board 1 2"), ["jo", "kim"]);
+        assert_eq!(
+            run(&mut dict, "ZRANGE board 0 0 WITHSCORES"),
+            Ok(CommandResult::Scored(vec![("alex".to_string(), 10.0)]))
+        );
+        assert_eq!(members(&mut dict, "ZRANGE nope 0 -1"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn zrange_by_score_and_lex() {
+        let mut dict = leaderboard();
+
+        assert_eq!(members(&mut dict, "ZRANGE board 20 +inf BYSCORE"), ["jo", "kim", "sam"]);
+        assert_eq!(members(&mut dict, "ZRANGE board (20 +inf BYSCORE"), ["sam"]);
+        assert_eq!(members(&mut dict, "ZRANGE board (30 10 BYSCORE REV"), ["kim", "jo", "alex"]);
+        assert_eq!(members(&mut dict, "ZRANGE board -inf +inf BYSCORE LIMIT 1 2"), ["jo", "kim"]);
+        assert_eq!(members(&mut dict, "ZRANGEBYSCORE board 10 20 LIMIT 1 -1"), ["jo", "kim"]);
+        assert_eq!(members(&mut dict, "ZRANGEBYSCORE board 40 50"), Vec::<String>::new());
+        assert_eq!(members(&mut dict, "ZRANGEBYSCORE board 30 10"), Vec::<String>::new());
+        assert_eq!(members(&mut dict, "ZRANGEBYSCORE board (10 (20"), Vec::<String>::new());
+        assert_eq!(members(&mut dict, "ZRANGE board +inf (10 BYSCORE REV"), ["sam", "kim", "jo"]);
+
+        run(&mut dict, "ZADD names 0 a 0 b 0 c 0 d").unwrap();
+        assert_eq!(members(&mut dict, "ZRANGE names [b (d BYLEX"), ["b", "c"]);
+        assert_eq!(members(&mut dict, "ZRANGE names - [b BYLEX"), ["a", "b"]);
+        assert_eq!(members(&mut dict, "ZRANGE names + (b BYLEX REV"), ["d", "c"]);
+        assert_eq!(members(&mut dict, "ZRANGE names - + BYLEX LIMIT 3 10"), ["d"]);
+        assert_eq!(members(&mut dict, "ZRANGE names (c [b BYLEX"), Vec::<String>::new());
+        assert_eq!(members(&mut dict, "ZRANGE names (a (b BYLEX"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn zrank_zrem_zpop() {
+        let mut dict = leaderboard();
+
+        assert_eq!(run(&mut dict, "ZRANK board kim"), Ok(CommandResult::ZRank(Some(2))));
+        assert_eq!(run(&mut dict, "ZRANK board alex"), Ok(CommandResult::ZRank(Some(0))));
+        assert_eq!(run(&mut dict, "ZRANK board sam"), Ok(CommandResult::ZRank(Some(3))));
+        assert_eq!(run(&mut dict, "ZRANK board nope"), Ok(CommandResult::ZRank(None)));
+        assert_eq!(run(&mut dict, "ZCARD board"), Ok(CommandResult::ZCard(4)));
+
+        assert_eq!(run(&mut dict, "ZREM board kim nope"), Ok(CommandResult::ZRem(1)));
+        assert_eq!(dict.zrank("board", "sam"), Ok(Some(2)));
+
+        assert_eq!(run(&mut dict, "ZPOPMAX board"), Ok(CommandResult::Scored(vec![("sam".to_string(), 30.0)])));
+        assert_eq!(
+            run(&mut dict, "ZPOPMIN board 5"),
+            Ok(CommandResult::Scored(vec![("alex".to_string(), 10.0), ("jo".to_string(), 20.0)]))
+        );
+        // Emptied sorted sets are gone
+        assert!(!dict.exists("board"));
+        assert_eq!(run(&mut dict, "ZPOPMIN board"), Ok(CommandResult::Scored(vec![])));
+    }
+}